@@ -0,0 +1,25 @@
+#![no_main]
+
+use focus::hosts::{self, Markers};
+use libfuzzer_sys::fuzz_target;
+
+// Whatever is in the hosts file, blocking and unblocking must leave it consistent
+fuzz_target!(|content: &str| {
+    let markers = Markers::default();
+    let sites = [String::from("example.com")];
+    for scope in [None, Some("work")] {
+        let blocked = hosts::set_block(content, &markers, scope, None, "0.0.0.0", &sites);
+        assert!(hosts::has_block(&blocked, &markers, scope));
+        assert_eq!(
+            hosts::set_block(&blocked, &markers, scope, None, "0.0.0.0", &sites),
+            blocked
+        );
+
+        let unblocked = hosts::remove_block(&blocked, &markers, scope);
+        assert!(!hosts::has_block(&unblocked, &markers, scope));
+        assert_eq!(hosts::remove_block(&unblocked, &markers, scope), unblocked);
+        assert_eq!(hosts::remove_block(content, &markers, scope), unblocked);
+    }
+    let repaired = hosts::repair(content, &markers);
+    assert_eq!(hosts::repair(&repaired, &markers), repaired);
+});
@@ -0,0 +1,96 @@
+use crate::{
+    prompt,
+    util::{self, Config},
+};
+use argon2::{
+    Argon2,
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+};
+use colored::Colorize;
+use std::process;
+
+const MAX_ATTEMPTS: u32 = 3;
+const SALT_LENGTH: usize = 16;
+
+/// Prompts for the admin password when one is configured.
+pub fn require_admin(config: &Config, action: &str) -> bool {
+    let Some(hash) = &config.admin_password_hash else {
+        return true;
+    };
+    let Ok(hash) = PasswordHash::new(hash) else {
+        eprintln!(
+            "{}",
+            "[!] admin_password_hash in config.toml is not a valid password hash"
+                .bold()
+                .red()
+        );
+        return false;
+    };
+
+    println!(
+        "{}",
+        format!("[>] {} requires the admin password", action)
+            .bold()
+            .cyan()
+    );
+    for _ in 0..MAX_ATTEMPTS {
+        let Ok(password) =
+            prompt::with_reader_suspended(|| rpassword::prompt_password("[?] Admin password: "))
+        else {
+            break;
+        };
+        if Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+        {
+            return true;
+        }
+        eprintln!("{}", "[!] Incorrect password".bold().red());
+    }
+    false
+}
+
+pub fn set_admin_password(config: &Config) -> bool {
+    if !require_admin(config, "Changing the admin password") {
+        return false;
+    }
+
+    let password = rpassword::prompt_password("[?] New admin password (empty to remove): ")
+        .unwrap_or_default();
+    let mut config = config.clone();
+    if password.is_empty() {
+        config.admin_password_hash = None;
+        util::save_config(&config).expect("[!] Failed to save configuration");
+        println!("{}", "[+] Admin password removed".bold().green());
+        return true;
+    }
+
+    let confirmation =
+        rpassword::prompt_password("[?] Repeat new admin password: ").unwrap_or_default();
+    if password != confirmation {
+        eprintln!("{}", "[!] Passwords do not match".bold().red());
+        return false;
+    }
+
+    config.admin_password_hash = Some(hash_password(&password));
+    util::save_config(&config).expect("[!] Failed to save configuration");
+    println!("{}", "[+] Admin password set".bold().green());
+    true
+}
+
+pub fn hash_password(password: &str) -> String {
+    let mut salt = [0u8; SALT_LENGTH];
+    getrandom::getrandom(&mut salt).unwrap_or_else(|e| {
+        eprintln!(
+            "{}",
+            format!("[!] Could not generate salt: {}", e).bold().red()
+        );
+        process::exit(1);
+    });
+    let salt = SaltString::encode_b64(&salt).expect("[!] Could not encode salt");
+
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("[!] Could not hash password")
+        .to_string()
+}
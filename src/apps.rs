@@ -0,0 +1,135 @@
+use crate::util::{self, Config};
+#[cfg(target_os = "linux")]
+use crate::{audit, platform};
+use colored::Colorize;
+#[cfg(target_os = "linux")]
+use std::{collections::HashSet, fs, os::unix::fs::MetadataExt, path::Path, process, sync::Mutex};
+
+/// Processes sent SIGTERM at the last check; any still running get SIGKILL.
+#[cfg(target_os = "linux")]
+static TERMINATED: Mutex<Option<HashSet<i32>>> = Mutex::new(None);
+
+/// The blocked app a process is, matched by its name or executable, case-insensitively.
+#[cfg(target_os = "linux")]
+fn blocked_name<'a>(config: &'a Config, pid: &str) -> Option<&'a str> {
+    let proc = Path::new("/proc").join(pid);
+    let comm = fs::read_to_string(proc.join("comm")).ok()?;
+    let exe = fs::read_link(proc.join("exe"))
+        .ok()
+        .and_then(|exe| Some(exe.file_name()?.to_string_lossy().into_owned()));
+    config
+        .blocked_apps
+        .iter()
+        .find(|app| {
+            // The kernel cuts comm to 15 bytes, so long names only match the executable
+            comm.trim().eq_ignore_ascii_case(app)
+                || exe
+                    .as_deref()
+                    .is_some_and(|exe| exe.eq_ignore_ascii_case(app))
+        })
+        .map(String::as_str)
+}
+
+/// Closes running blocked apps; called with every tamper check while a session is active.
+#[cfg(target_os = "linux")]
+pub fn enforce(config: &Config) {
+    if config.blocked_apps.is_empty() || config.warn_only {
+        return;
+    }
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return;
+    };
+    let own = process::id() as i32;
+    let mut terminated = TERMINATED.lock().unwrap();
+    let previous = terminated.take().unwrap_or_default();
+    let mut current = HashSet::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Ok(pid) = name.parse::<i32>() else {
+            continue;
+        };
+        // System services are never desktop apps, whatever they're called
+        if pid == own || entry.metadata().is_ok_and(|metadata| metadata.uid() == 0) {
+            continue;
+        }
+        let Some(app) = blocked_name(config, &name) else {
+            continue;
+        };
+        if previous.contains(&pid) {
+            platform::terminate(pid as u32, true);
+            continue;
+        }
+        if !platform::terminate(pid as u32, false) {
+            continue;
+        }
+        current.insert(pid);
+        println!(
+            "{}",
+            format!("[!] Closed {} (pid {}), it's blocked", app, pid)
+                .bold()
+                .yellow()
+        );
+        audit::record(config, "app", &format!("closed {}", app));
+    }
+    *terminated = Some(current);
+}
+
+/// Processes are only listed through /proc, which other systems don't have.
+#[cfg(not(target_os = "linux"))]
+pub fn enforce(_config: &Config) {}
+
+pub fn list(config: &Config) {
+    if config.blocked_apps.is_empty() {
+        println!(
+            "{}",
+            "[>] No blocked apps; add one with: focus app add steam"
+                .bold()
+                .cyan()
+        );
+        return;
+    }
+    println!("{}", "[>] Blocked apps".bold().cyan());
+    for app in &config.blocked_apps {
+        println!("    {}", app);
+    }
+}
+
+pub fn add(config: &Config, apps: &[String]) {
+    let mut config = config.clone();
+    for app in apps {
+        if config
+            .blocked_apps
+            .iter()
+            .any(|blocked| blocked.eq_ignore_ascii_case(app))
+        {
+            println!(
+                "{}",
+                format!("[>] {} is already blocked", app).bold().cyan()
+            );
+            continue;
+        }
+        config.blocked_apps.push(app.clone());
+        println!("{}", format!("[+] Blocked {}", app).bold().green());
+    }
+    util::save_config(&config).expect("[!] Failed to save configuration");
+}
+
+/// Unblocks apps; returns false if any of them wasn't blocked.
+pub fn remove(config: &Config, apps: &[String]) -> bool {
+    let mut config = config.clone();
+    let mut found = true;
+    for app in apps {
+        let before = config.blocked_apps.len();
+        config
+            .blocked_apps
+            .retain(|blocked| !blocked.eq_ignore_ascii_case(app));
+        if config.blocked_apps.len() == before {
+            eprintln!("{}", format!("[!] {} isn't blocked", app).bold().red());
+            found = false;
+        } else {
+            println!("{}", format!("[+] Unblocked {}", app).bold().green());
+        }
+    }
+    util::save_config(&config).expect("[!] Failed to save configuration");
+    found
+}
@@ -1,24 +1,155 @@
+use crate::{
+    companion::{self, Task},
+    desktop, platform,
+    user::SessionUser,
+    util::Config,
+};
 use colored::Colorize;
+#[cfg(feature = "audio")]
 use gag::Gag;
-use rodio::{Decoder, OutputStreamBuilder, Sink};
-use std::{env, fs::File, io::BufReader};
+#[cfg(feature = "audio")]
+use rodio::{Decoder, OutputStreamBuilder, Sink, Source};
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    process::{Command, Stdio},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+#[cfg(feature = "audio")]
+use std::{fs::File, io::BufReader};
 
-pub fn play_audio(path: String) {
-    let _print_gag = Gag::stderr().unwrap();
+const SPEECH_COMMANDS: [&str; 2] = ["spd-say", "espeak"];
+pub const AMBIENT_POLL: Duration = Duration::from_millis(500);
 
-    let audio_runtime_path = get_audio_runtime_path();
-    if env::var("XDG_RUNTIME_DIR").is_err() {
-        unsafe {
-            env::set_var("XDG_RUNTIME_DIR", audio_runtime_path);
+/// How session sounds are made; `none` silences them, as does building without the
+/// `audio` feature.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AudioBackend {
+    #[default]
+    Native,
+    None,
+}
+
+/// What session sounds do in a `--background` session, which has no terminal to play from.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackgroundAudio {
+    #[default]
+    Never,
+    /// A desktop notification naming the cue instead of the sound
+    Notify,
+    /// Only through `focus companion`, which runs in the user's session
+    UserService,
+}
+
+/// Plays a session sound, following `background_audio` when in the background.
+pub fn play_cue(config: &Config, file: &str, cue: &str, background: bool) {
+    if file.is_empty() || config.audio_backend == AudioBackend::None {
+        return;
+    }
+    if !background {
+        play_data_file(config, file);
+        return;
+    }
+    match config.background_audio {
+        BackgroundAudio::Never => {}
+        BackgroundAudio::Notify => desktop::show("focus", cue),
+        BackgroundAudio::UserService => {
+            companion::send(&Task::Play {
+                path: format!("{}/{}", config.data_directory, file),
+            });
+        }
+    }
+}
+
+/// Speaks a session announcement under the same policy as `play_cue`.
+pub fn speak_cue(config: &Config, text: &str, background: bool) {
+    if config.audio_backend == AudioBackend::None {
+        return;
+    }
+    if !background {
+        speak(text);
+        return;
+    }
+    match config.background_audio {
+        BackgroundAudio::Never => {}
+        BackgroundAudio::Notify => desktop::show("focus", text),
+        BackgroundAudio::UserService => {
+            companion::send(&Task::Speak {
+                text: text.to_string(),
+            });
         }
     }
+}
+
+/// Starts the ambient track; in the background only the companion can loop it.
+pub fn play_session_ambient(config: &Config, running: Arc<AtomicBool>, background: bool) {
+    let Some(ambient) = &config.ambient_audio else {
+        return;
+    };
+    if config.audio_backend == AudioBackend::None {
+        return;
+    }
+    let path = format!("{}/{}", config.data_directory, ambient);
+    if !background {
+        play_ambient(path, running);
+    } else if config.background_audio == BackgroundAudio::UserService {
+        companion::play_ambient(&path, running);
+    }
+}
+
+/// Plays a file from the data directory; an empty name means silence.
+pub fn play_data_file(config: &Config, file: &str) {
+    if file.is_empty() || config.audio_backend == AudioBackend::None {
+        return;
+    }
+    let path = format!("{}/{}", config.data_directory, file);
+    if !companion::send(&Task::Play { path: path.clone() }) {
+        play_audio(path);
+    }
+}
+
+pub fn play_audio(path: String) {
+    // Root often can't reach the user's PipeWire or PulseAudio server, so play as them
+    if let Some(user) = SessionUser::from_sudo()
+        && let Ok(executable) = env::current_exe()
+    {
+        let _ = user
+            .command(executable)
+            .args(["play-sound", &path])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        return;
+    }
+    play_in_process(&path, false);
+}
+
+/// Plays through this process's own audio connection, looping forever with `repeat`.
+#[cfg(feature = "audio")]
+pub fn play_in_process(path: &str, repeat: bool) {
+    // Another sound may already hold the gag, e.g. the ambient track
+    let _print_gag = Gag::stderr().ok();
+
+    set_runtime_dir();
 
     if let Ok(stream) = OutputStreamBuilder::open_default_stream() {
         let sink = Sink::connect_new(stream.mixer());
-        if let Ok(file) = File::open(&path) {
+        if let Ok(file) = File::open(path) {
             let reader = BufReader::new(file);
             if let Ok(source) = Decoder::new(reader) {
-                sink.append(source);
+                if repeat {
+                    sink.append(source.repeat_infinite());
+                } else {
+                    sink.append(source);
+                }
                 sink.sleep_until_end();
             }
         }
@@ -32,14 +163,128 @@ pub fn play_audio(path: String) {
     }
 }
 
-pub fn get_audio_runtime_path() -> String {
-    if let Ok(sudo_uid) = env::var("SUDO_UID") {
-        return format!("/run/user/{}", sudo_uid);
+/// Whether this process can open the default output device right now.
+#[cfg(feature = "audio")]
+pub fn has_output_device() -> bool {
+    let _print_gag = Gag::stderr().ok();
+    set_runtime_dir();
+    OutputStreamBuilder::open_default_stream().is_ok()
+}
+
+#[cfg(not(feature = "audio"))]
+pub fn has_output_device() -> bool {
+    false
+}
+
+#[cfg(not(feature = "audio"))]
+pub fn play_in_process(path: &str, _repeat: bool) {
+    eprintln!(
+        "{}",
+        format!("[!] Built without audio support; not playing {}", path)
+            .bold()
+            .yellow()
+    );
+}
+
+/// Loops a track in the background until `running` is cleared.
+pub fn play_ambient(path: String, running: Arc<AtomicBool>) {
+    if !companion::play_ambient(&path, Arc::clone(&running)) {
+        play_ambient_locally(path, running);
     }
+}
 
-    if let Ok(path) = env::var("XDG_RUNTIME_DIR") {
-        return path;
+pub fn play_ambient_locally(path: String, running: Arc<AtomicBool>) {
+    if let Some(user) = SessionUser::from_sudo()
+        && let Ok(executable) = env::current_exe()
+        && let Ok(mut child) = user
+            .command(executable)
+            .args(["play-sound", "--repeat", &path])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+    {
+        thread::spawn(move || {
+            while running.load(Ordering::SeqCst) {
+                thread::sleep(AMBIENT_POLL);
+            }
+            let _ = child.kill();
+            let _ = child.wait();
+        });
+        return;
     }
+    play_ambient_in_process(path, running);
+}
+
+#[cfg(feature = "audio")]
+fn play_ambient_in_process(path: String, running: Arc<AtomicBool>) {
+    thread::spawn(move || {
+        let stream = {
+            let _print_gag = Gag::stderr().ok();
+            set_runtime_dir();
+            OutputStreamBuilder::open_default_stream()
+        };
+        let Ok(stream) = stream else {
+            return;
+        };
+        let sink = Sink::connect_new(stream.mixer());
+        let Some(source) = File::open(&path)
+            .ok()
+            .and_then(|file| Decoder::new(BufReader::new(file)).ok())
+        else {
+            eprintln!(
+                "{}",
+                format!("[!] Could not play ambient track {}", path)
+                    .bold()
+                    .yellow()
+            );
+            return;
+        };
+        sink.append(source.repeat_infinite());
+        while running.load(Ordering::SeqCst) {
+            thread::sleep(AMBIENT_POLL);
+        }
+        sink.stop();
+    });
+}
+
+#[cfg(not(feature = "audio"))]
+fn play_ambient_in_process(_path: String, _running: Arc<AtomicBool>) {}
 
-    String::from("/run/user/1000")
+#[cfg(feature = "audio")]
+fn set_runtime_dir() {
+    if env::var("XDG_RUNTIME_DIR").is_err() {
+        unsafe {
+            env::set_var("XDG_RUNTIME_DIR", get_audio_runtime_path());
+        }
+    }
+}
+
+pub fn speak(text: &str) {
+    if !companion::send(&Task::Speak {
+        text: text.to_string(),
+    }) {
+        speak_locally(text);
+    }
+}
+
+pub fn speak_locally(text: &str) {
+    for command in SPEECH_COMMANDS {
+        let spoken = SessionUser::from_sudo()
+            .map(|user| user.command(command))
+            .unwrap_or_else(|| Command::new(command))
+            .arg(text)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        if spoken {
+            return;
+        }
+    }
+}
+
+/// Under sudo playback runs as the user instead, so this is the process's own runtime dir.
+pub fn get_audio_runtime_path() -> String {
+    platform::user_runtime_directory()
 }
@@ -0,0 +1,213 @@
+use crate::{notify::Event, util::Config};
+use chrono::Local;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+};
+
+const AUDIT_FILE: &str = "focus.audit";
+const TAIL_CHUNK: u64 = 4096;
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub timestamp: String,
+    pub user: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub outcome: String,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl Entry {
+    fn compute_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        for field in [
+            &self.prev_hash,
+            &self.timestamp,
+            &self.user,
+            &self.command,
+            &self.args.join("\u{1f}"),
+            &self.outcome,
+        ] {
+            hasher.update(field.as_bytes());
+            hasher.update([0x1e]);
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+pub fn audit_path(config: &Config) -> String {
+    format!("{}/{}", config.log_directory, AUDIT_FILE)
+}
+
+pub fn record(config: &Config, command: &str, outcome: &str) {
+    let path = audit_path(config);
+    // Held from reading the last hash to appending, across threads and concurrent commands
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|file| file.lock().map(|()| file));
+    let mut file = match file {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                format!("[!] Failed to write audit log {}: {}", path, e)
+                    .bold()
+                    .yellow()
+            );
+            return;
+        }
+    };
+    // A corrupt last line chains from an empty hash, as verification expects
+    let prev_hash = match last_line(&path) {
+        Some(line) => serde_json::from_str::<Entry>(&line)
+            .map(|entry| entry.hash)
+            .unwrap_or_default(),
+        None => GENESIS_HASH.to_string(),
+    };
+
+    let mut entry = Entry {
+        timestamp: Local::now().to_rfc3339(),
+        user: current_user(),
+        command: command.to_string(),
+        args: env::args().skip(1).collect(),
+        outcome: outcome.to_string(),
+        prev_hash,
+        hash: String::new(),
+    };
+    entry.hash = entry.compute_hash();
+
+    let line = serde_json::to_string(&entry).expect("[!] Could not encode audit entry");
+    if let Err(e) = writeln!(file, "{}", line) {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to write audit log {}: {}", path, e)
+                .bold()
+                .yellow()
+        );
+    }
+}
+
+/// The audit side of the event pipeline: session starts and ends.
+pub fn record_event(config: &Config, event: &Event) {
+    let outcome = match event {
+        Event::SessionStarted { resumed: true, .. } => "resumed",
+        Event::SessionStarted { .. } => "started",
+        Event::SessionEnded { .. } => "completed",
+        Event::SessionStopped { outcome } => outcome,
+        _ => return,
+    };
+    record(config, "session", outcome);
+}
+
+pub fn show_audit(config: &Config) {
+    let path = audit_path(config);
+    let entries = read_entries(&path);
+    if entries.is_empty() {
+        println!("{}", "[+] Audit log is empty".bold().green());
+        return;
+    }
+
+    for entry in &entries {
+        println!(
+            "{} {} {} [{}] {}",
+            entry.timestamp.dimmed(),
+            entry.user.bold(),
+            entry.command.cyan(),
+            entry.args.join(" "),
+            entry.outcome
+        );
+    }
+
+    match verify_chain(&entries) {
+        None => println!(
+            "{}",
+            format!("[+] Audit chain intact ({} entries)", entries.len())
+                .bold()
+                .green()
+        ),
+        Some(index) => eprintln!(
+            "{}",
+            format!(
+                "[!] Audit chain broken at entry {}: the log has been tampered with",
+                index + 1
+            )
+            .bold()
+            .red()
+        ),
+    }
+}
+
+/// Returns the index of the first entry whose hash or back-link does not match.
+fn verify_chain(entries: &[Entry]) -> Option<usize> {
+    let mut prev_hash = GENESIS_HASH;
+    for (index, entry) in entries.iter().enumerate() {
+        if entry.prev_hash != prev_hash || entry.hash != entry.compute_hash() {
+            return Some(index);
+        }
+        prev_hash = &entry.hash;
+    }
+    None
+}
+
+/// The log's last non-empty line, read from the end so appending stays cheap as it grows.
+fn last_line(path: &str) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let mut end = file.seek(SeekFrom::End(0)).ok()?;
+    let mut tail: Vec<u8> = Vec::new();
+    while end > 0 {
+        let start = end.saturating_sub(TAIL_CHUNK);
+        let mut chunk = vec![0; (end - start) as usize];
+        file.seek(SeekFrom::Start(start)).ok()?;
+        file.read_exact(&mut chunk).ok()?;
+        chunk.extend_from_slice(&tail);
+        tail = chunk;
+        end = start;
+        let trimmed = tail.trim_ascii_end();
+        if let Some(newline) = trimmed.iter().rposition(|byte| *byte == b'\n') {
+            return Some(String::from_utf8_lossy(&trimmed[newline + 1..]).into_owned());
+        }
+    }
+    let trimmed = tail.trim_ascii_end();
+    (!trimmed.is_empty()).then(|| String::from_utf8_lossy(trimmed).into_owned())
+}
+
+fn read_entries(path: &str) -> Vec<Entry> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).unwrap_or(Entry {
+                timestamp: String::new(),
+                user: String::new(),
+                command: String::from("<corrupt>"),
+                args: vec![line.to_string()],
+                outcome: String::new(),
+                prev_hash: String::new(),
+                hash: String::new(),
+            })
+        })
+        .collect()
+}
+
+fn current_user() -> String {
+    env::var("SUDO_USER")
+        .or_else(|_| env::var("USER"))
+        .unwrap_or_else(|_| String::from("unknown"))
+}
@@ -0,0 +1,58 @@
+use crate::{
+    firewall::Firewall,
+    util::{self, Config},
+};
+use serde::{Deserialize, Serialize};
+
+/// Where blocked sites are enforced; the DNS, SNI, eBPF and proxy backends add to this.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockingBackend {
+    /// Entries in the hosts file
+    #[default]
+    Hosts,
+    /// nftables rules rejecting the sites' addresses, which editing the hosts file
+    /// or using another resolver won't get around
+    Firewall,
+    Both,
+}
+
+/// A way of blocking the session's sites that can be applied again whenever they change.
+pub trait Blocker {
+    /// Blocks the current sites, replacing what this blocker blocked before. `in_place`
+    /// asks to keep an existing block where it is, for blockers where that matters.
+    fn block(&self, config: &Config, in_place: bool) -> Result<(), String>;
+    fn unblock(&self, config: &Config) -> Result<(), String>;
+}
+
+pub struct Hosts;
+
+impl Blocker for Hosts {
+    fn block(&self, config: &Config, in_place: bool) -> Result<(), String> {
+        util::write_hosts_block(config, in_place)
+    }
+
+    fn unblock(&self, config: &Config) -> Result<(), String> {
+        util::remove_hosts_block(config)
+    }
+}
+
+/// The blockers `blocking_backend` selects, in the order they are applied.
+pub fn blockers(config: &Config) -> Vec<Box<dyn Blocker>> {
+    let mut blockers: Vec<Box<dyn Blocker>> = Vec::new();
+    if uses_hosts(config) {
+        blockers.push(Box::new(Hosts));
+    }
+    if uses_firewall(config) {
+        blockers.push(Box::new(Firewall));
+    }
+    blockers
+}
+
+pub fn uses_hosts(config: &Config) -> bool {
+    config.blocking_backend != BlockingBackend::Firewall
+}
+
+pub fn uses_firewall(config: &Config) -> bool {
+    config.blocking_backend != BlockingBackend::Hosts
+}
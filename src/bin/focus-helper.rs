@@ -0,0 +1,68 @@
+//! Privileged helper for `focus`. Install it next to the `focus` binary with either
+//! `chown root focus-helper && chmod u+s focus-helper` or
+//! `setcap cap_dac_override,cap_net_admin+ep focus-helper`, and the rest of focus
+//! can run unprivileged. It only accepts the requests in `privileged::Request`.
+
+#[allow(dead_code)]
+#[path = "../hosts.rs"]
+mod hosts;
+#[allow(dead_code)]
+#[path = "../privileged.rs"]
+mod privileged;
+
+#[cfg(unix)]
+use privileged::{CONFIG_PATH, Settings};
+use std::process;
+#[cfg(unix)]
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    os::unix::fs::MetadataExt,
+};
+
+#[cfg(unix)]
+fn main() {
+    let settings = match load_settings() {
+        Ok(settings) => settings,
+        Err(e) => {
+            println!("error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let mut stdout = io::stdout();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let reply = serde_json::from_str::<privileged::Request>(&line)
+            .map_err(|e| format!("malformed request: {}", e))
+            .and_then(|request| privileged::execute(&request, &settings));
+        let _ = match reply {
+            Ok(()) => writeln!(stdout, "ok"),
+            Err(e) => writeln!(stdout, "error: {}", e.replace('\n', " ")),
+        };
+        let _ = stdout.flush();
+    }
+}
+
+/// Windows can't grant a helper rights of its own; focus runs elevated there instead.
+#[cfg(target_os = "windows")]
+fn main() {
+    println!("error: focus-helper isn't used on Windows; run focus as administrator");
+    process::exit(1);
+}
+
+/// Reads the settings from the system config, refusing one a user could have edited.
+#[cfg(unix)]
+fn load_settings() -> Result<Settings, String> {
+    let metadata = fs::metadata(CONFIG_PATH).map_err(|e| format!("{}: {}", CONFIG_PATH, e))?;
+    if metadata.uid() != 0 || metadata.mode() & 0o022 != 0 {
+        return Err(format!(
+            "{} must be owned by root and writable only by root",
+            CONFIG_PATH
+        ));
+    }
+    let content = fs::read_to_string(CONFIG_PATH).map_err(|e| format!("{}: {}", CONFIG_PATH, e))?;
+    toml::from_str(&content).map_err(|e| e.to_string())
+}
@@ -0,0 +1,136 @@
+//! Drives a running focus session from scripts and editors: one command per line on
+//! stdin, e.g. `add x.com`, `extend 10` or `status --json`, and one JSON result per line
+//! on stdout, all over a single connection to the session's socket.
+
+use focus::{
+    Config,
+    socket::{self, Client},
+};
+use serde_json::{Value, json};
+use std::{
+    env,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+    process::{self, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+const START_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn main() {
+    let mut config = match focus::util::load_config() {
+        Ok(config) => config,
+        Err(e) => {
+            println!(
+                "{}",
+                json!({ "ok": false, "error": format!("invalid config: {}", e) })
+            );
+            process::exit(1);
+        }
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--group" | "-g", Some(group)) => config.scope = Some(group),
+            _ => {
+                eprintln!("usage: focusctl [--group NAME] < commands");
+                process::exit(2);
+            }
+        }
+    }
+
+    let mut client: Option<Client> = None;
+    let mut stdout = io::stdout();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+        let mut reply = run(&config, &mut client, &words);
+        reply["command"] = Value::String(line.trim().to_string());
+        let _ = writeln!(stdout, "{}", reply);
+        let _ = stdout.flush();
+    }
+}
+
+fn run(config: &Config, client: &mut Option<Client>, words: &[&str]) -> Value {
+    let request = match words {
+        ["status", ..] => json!({ "op": "status" }),
+        ["remaining"] => json!({ "op": "remaining" }),
+        ["stats"] => json!({ "op": "stats" }),
+        ["pause"] => json!({ "op": "pause" }),
+        ["resume"] => json!({ "op": "resume" }),
+        ["stop"] => json!({ "op": "stop" }),
+        ["extend", minutes] => match minutes.parse::<u64>() {
+            Ok(minutes) => json!({ "op": "extend", "minutes": minutes }),
+            Err(_) => return error(&format!("'{}' is not a number of minutes", minutes)),
+        },
+        ["add", sites @ ..] if !sites.is_empty() => json!({ "op": "add", "sites": sites }),
+        ["start", minutes] => return start(config, client, minutes),
+        _ => return error(&format!("unknown command '{}'", words.join(" "))),
+    };
+    // The session closes idle connections, so a failed request gets one fresh try
+    for _ in 0..2 {
+        if client.is_none() {
+            match Client::connect(config) {
+                Ok(connected) => *client = Some(connected),
+                Err(_) => return error("no focus session is running"),
+            }
+        }
+        if let Some(connected) = client.as_mut()
+            && let Ok(reply) = connected.request(&request)
+        {
+            return reply;
+        }
+        *client = None;
+    }
+    error("the session stopped answering")
+}
+
+/// Starts a background session the way `focus --duration` would, then connects to it.
+fn start(config: &Config, client: &mut Option<Client>, minutes: &str) -> Value {
+    if minutes.parse::<u64>().is_err() {
+        return error(&format!("'{}' is not a number of minutes", minutes));
+    }
+    if socket::ask(config, json!({ "op": "remaining" })).is_ok() {
+        return error("a focus session is already running");
+    }
+    // Installed side by side, like focus-helper
+    let focus = env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join("focus")))
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from("focus"));
+    let mut command = Command::new(focus);
+    if let Some(group) = &config.scope {
+        command.args(["--group", group]);
+    }
+    let started = command
+        .args(["--duration", minutes, "--background", "--no-ritual"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if !started {
+        return error("focus failed to start; is it installed with root or focus-helper?");
+    }
+    // The background session opens its socket a moment after `focus` returns
+    let deadline = Instant::now() + START_TIMEOUT;
+    while Instant::now() < deadline {
+        if let Ok(connected) = Client::connect(config) {
+            *client = Some(connected);
+            return json!({ "ok": true });
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    error("focus started but its socket never opened; is [socket] disabled?")
+}
+
+fn error(message: &str) -> Value {
+    json!({ "ok": false, "error": message })
+}
@@ -0,0 +1,608 @@
+use crate::{
+    certificate, hosts,
+    session::Timer,
+    util::{self, Config, Enforcement},
+};
+use colored::Colorize;
+use handlebars::Handlebars;
+use rustls::{ServerConnection, StreamOwned};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(500);
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REQUEST: usize = 16 * 1024;
+const CONTINUE_PATH: &str = "/__focus/continue";
+const BLOCKED_TEMPLATE: &str = "<!doctype html><html lang=\"{{language}}\"><head><meta charset=\"utf-8\"><title>{{strings.blocked}}</title>\
+<style>body{font-family:sans-serif;text-align:center;margin-top:20vh}{{{style}}}</style></head>\
+<body><h1>{{strings.blocked}}</h1><p>{{strings.minutes_left}}</p></body></html>";
+const NAG_TEMPLATE: &str = "<!doctype html><html lang=\"{{language}}\"><head><meta charset=\"utf-8\"><title>{{strings.nag_title}}</title>\
+<style>body{font-family:sans-serif;text-align:center;margin:0;height:100vh;display:flex;flex-direction:column;justify-content:center}{{{style}}}</style></head>\
+<body><h1>{{strings.nag_title}}</h1><p>{{strings.minutes_left}}</p>\
+<p><a id=\"go\" href=\"{{continue_url}}\" data-ready=\"{{strings.continue}}\" style=\"pointer-events:none;opacity:.4\">{{strings.continue}} (<span id=\"n\">{{delay}}</span>s)</a></p>\
+<script>var n={{delay}};var t=setInterval(function(){n--;document.getElementById('n').textContent=n;\
+if(n<=0){clearInterval(t);var a=document.getElementById('go');a.style.pointerEvents='auto';a.style.opacity=1;a.textContent=a.dataset.ready;}},1000);</script>\
+</body></html>";
+
+static NAGS: Mutex<Option<NagState>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BlockPageConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_listen")]
+    pub listen: String,
+    /// Also answers HTTPS here, e.g. "127.0.0.1:443", once `focus certificate` has run
+    #[serde(default)]
+    pub https_listen: Option<String>,
+    #[serde(default = "default_nag_delay")]
+    pub nag_delay_seconds: u64,
+    #[serde(default = "default_nag_pass")]
+    pub nag_pass_minutes: u64,
+    /// Handlebars templates replacing the built-in pages, relative to the data directory
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub nag_template: Option<String>,
+    /// Extra CSS for the built-in pages
+    #[serde(default)]
+    pub style: String,
+    /// Built-in wording: en, de, es or fr
+    #[serde(default = "default_language")]
+    pub language: String,
+    /// Overrides individual strings: blocked, minutes_left, nag_title, continue
+    #[serde(default)]
+    pub strings: BTreeMap<String, String>,
+    /// Blocked sites sent somewhere better instead of the block page: a URL or host to
+    /// redirect to, or `page:` and a page in the data directory, e.g.
+    /// `"reddit.com" = "my-reading-list.local"` or `"youtube.com" = "page:reading-list.html"`
+    #[serde(default)]
+    pub redirects: BTreeMap<String, Redirect>,
+}
+
+/// Where a redirected site goes; checked when the config loads, since URLs end up in a
+/// `Location:` header.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Redirect {
+    Url(String),
+    /// A bare host such as `my-reading-list.local`, optionally with a port and path
+    Host(String),
+    Page(String),
+}
+
+impl TryFrom<String> for Redirect {
+    type Error = String;
+
+    fn try_from(destination: String) -> Result<Self, Self::Error> {
+        if destination.is_empty()
+            || destination
+                .chars()
+                .any(|c| c.is_control() || c.is_whitespace())
+        {
+            return Err(format!("invalid redirect '{}'", destination.escape_debug()));
+        }
+        if let Some(page) = destination.strip_prefix("page:") {
+            return if page.is_empty() {
+                Err(String::from("a page: redirect needs a file name"))
+            } else {
+                Ok(Redirect::Page(page.to_string()))
+            };
+        }
+        let (url, rest) = match destination.split_once("://") {
+            Some((scheme, rest)) if scheme == "http" || scheme == "https" => (true, rest),
+            Some(_) => {
+                return Err(format!(
+                    "redirect '{}' must be an http or https URL",
+                    destination
+                ));
+            }
+            None => (false, destination.as_str()),
+        };
+        let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+        let host = match authority.rsplit_once(':') {
+            Some((host, port)) if port.parse::<u16>().is_ok() => host,
+            _ => authority,
+        };
+        if !hosts::is_valid_hostname(host) {
+            return Err(format!("redirect '{}' has no valid host", destination));
+        }
+        Ok(if url {
+            Redirect::Url(destination)
+        } else {
+            Redirect::Host(destination)
+        })
+    }
+}
+
+impl From<Redirect> for String {
+    fn from(redirect: Redirect) -> Self {
+        match redirect {
+            Redirect::Url(destination) | Redirect::Host(destination) => destination,
+            Redirect::Page(page) => format!("page:{}", page),
+        }
+    }
+}
+
+impl Default for BlockPageConfig {
+    fn default() -> Self {
+        BlockPageConfig {
+            enabled: false,
+            listen: default_listen(),
+            https_listen: None,
+            nag_delay_seconds: default_nag_delay(),
+            nag_pass_minutes: default_nag_pass(),
+            template: None,
+            nag_template: None,
+            style: String::new(),
+            language: default_language(),
+            strings: BTreeMap::new(),
+            redirects: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_listen() -> String {
+    String::from("127.0.0.1:80")
+}
+
+fn default_nag_delay() -> u64 {
+    15
+}
+
+fn default_nag_pass() -> u64 {
+    10
+}
+
+fn default_language() -> String {
+    String::from("en")
+}
+
+#[derive(Default)]
+struct NagState {
+    /// When the interstitial was first shown for each site
+    shown: HashMap<String, Instant>,
+    /// Sites let through after waiting out the interstitial, with their expiry
+    passes: HashMap<String, Instant>,
+}
+
+/// Sites temporarily let through by nag mode; they are left out of the hosts block.
+pub fn has_pass(site: &str) -> bool {
+    NAGS.lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|nags| nags.passes.contains_key(site))
+}
+
+fn bind(address: &str) -> Option<TcpListener> {
+    match TcpListener::bind(address) {
+        Ok(listener) => {
+            let _ = listener.set_nonblocking(true);
+            Some(listener)
+        }
+        Err(e) => {
+            eprintln!(
+                "{}",
+                format!("[!] Failed to start block page on {}: {}", address, e)
+                    .bold()
+                    .red()
+            );
+            None
+        }
+    }
+}
+
+pub fn start_server(config: Arc<Config>, running: Arc<AtomicBool>, timer: Arc<Timer>) {
+    let Some(listener) = bind(&config.block_page.listen) else {
+        return;
+    };
+    *NAGS.lock().unwrap() = Some(NagState::default());
+    println!(
+        "{}",
+        format!("[>] Block page served on {}", config.block_page.listen)
+            .bold()
+            .cyan()
+    );
+    let https = config.block_page.https_listen.as_ref().and_then(|address| {
+        let Some(tls) = certificate::server_config(&config, util::effective_sites(&config)) else {
+            eprintln!(
+                "{}",
+                "[!] No local certificate for the HTTPS block page; run `focus certificate`"
+                    .bold()
+                    .yellow()
+            );
+            return None;
+        };
+        let listener = bind(address)?;
+        println!(
+            "{}",
+            format!("[>] HTTPS block page served on {}", address)
+                .bold()
+                .cyan()
+        );
+        Some((listener, tls))
+    });
+
+    thread::spawn(move || {
+        while running.load(Ordering::SeqCst) {
+            expire_passes(&config);
+            let mut accepted = false;
+            if let Ok((client, _)) = listener.accept() {
+                accepted = true;
+                let config = Arc::clone(&config);
+                let timer = Arc::clone(&timer);
+                thread::spawn(move || {
+                    if prepare(&client) {
+                        handle_client(&config, &timer, client, "http");
+                    }
+                });
+            }
+            if let Some((listener, tls)) = &https
+                && let Ok((client, _)) = listener.accept()
+            {
+                accepted = true;
+                let config = Arc::clone(&config);
+                let timer = Arc::clone(&timer);
+                let tls = Arc::clone(tls);
+                thread::spawn(move || {
+                    if prepare(&client)
+                        && let Ok(connection) = ServerConnection::new(tls)
+                    {
+                        let client = StreamOwned::new(connection, client);
+                        handle_client(&config, &timer, client, "https");
+                    }
+                });
+            }
+            if !accepted {
+                thread::sleep(ACCEPT_INTERVAL);
+            }
+        }
+    });
+}
+
+fn prepare(client: &TcpStream) -> bool {
+    client.set_nonblocking(false).is_ok() && client.set_read_timeout(Some(READ_TIMEOUT)).is_ok()
+}
+
+fn handle_client(config: &Config, timer: &Timer, mut client: impl Read + Write, scheme: &str) {
+    let Some((target, host)) = read_request(&mut client) else {
+        return;
+    };
+    let sites = util::effective_sites(config);
+    let site = sites
+        .iter()
+        .find(|site| host == **site || host.ends_with(&format!(".{}", site)))
+        .cloned()
+        .unwrap_or(host.clone());
+    let minutes_left = timer.remaining().as_secs().div_ceil(60);
+
+    if let Some(destination) = config.block_page.redirects.get(&site) {
+        redirect(&mut client, config, &host, minutes_left, destination);
+        return;
+    }
+
+    if config.enforcement != Enforcement::Nag {
+        respond(
+            &mut client,
+            "403 Forbidden",
+            &blocked_page(config, &host, minutes_left),
+        );
+        return;
+    }
+
+    if let Some(query) = target.strip_prefix(&format!("{}?", CONTINUE_PATH)) {
+        let path = query_value(query, "path")
+            .filter(|path| path.starts_with('/') && !path.chars().any(char::is_control))
+            .unwrap_or_else(|| String::from("/"));
+        if grant_pass(config, &site) {
+            let location = format!("{}://{}{}", scheme, host, path);
+            let _ = client.write_all(
+                format!(
+                    "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    location
+                )
+                .as_bytes(),
+            );
+            return;
+        }
+    }
+
+    let waited = {
+        let mut nags = NAGS.lock().unwrap();
+        let shown = nags
+            .get_or_insert_with(NagState::default)
+            .shown
+            .entry(site)
+            .or_insert_with(Instant::now);
+        shown.elapsed().as_secs()
+    };
+    let delay = config.block_page.nag_delay_seconds.saturating_sub(waited);
+    respond(
+        &mut client,
+        "200 OK",
+        &nag_page(config, &host, &target, minutes_left, delay),
+    );
+}
+
+/// Bounces a blocked site to its alternative, which is either a URL, a host or a page of our own.
+fn redirect(
+    client: &mut impl Write,
+    config: &Config,
+    host: &str,
+    minutes_left: u64,
+    destination: &Redirect,
+) {
+    let location = match destination {
+        Redirect::Url(url) => url.clone(),
+        Redirect::Host(target) if target.contains('/') => format!("http://{}", target),
+        Redirect::Host(target) => format!("http://{}/", target),
+        Redirect::Page(page) => {
+            let page = render_page(
+                config,
+                Some(page),
+                BLOCKED_TEMPLATE,
+                serde_json::json!({ "domain": host, "minutes": minutes_left }),
+            );
+            respond(client, "200 OK", &page);
+            return;
+        }
+    };
+    let _ = client.write_all(
+        format!(
+            "HTTP/1.1 302 Found\r\nLocation: {}\r\nCache-Control: no-store\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            location
+        )
+        .as_bytes(),
+    );
+}
+
+/// Lets a site through once its interstitial has been up for the full delay.
+fn grant_pass(config: &Config, site: &str) -> bool {
+    let _hosts = util::lock_hosts();
+    {
+        let mut nags = NAGS.lock().unwrap();
+        let nags = nags.get_or_insert_with(NagState::default);
+        let waited = nags
+            .shown
+            .get(site)
+            .is_some_and(|shown| shown.elapsed().as_secs() >= config.block_page.nag_delay_seconds);
+        if !waited {
+            return false;
+        }
+        let expiry = Instant::now() + Duration::from_mins(config.block_page.nag_pass_minutes);
+        nags.passes.insert(site.to_string(), expiry);
+    }
+    util::rewrite_block(config);
+    true
+}
+
+fn expire_passes(config: &Config) {
+    let _hosts = util::lock_hosts();
+    let expired = {
+        let mut nags = NAGS.lock().unwrap();
+        let Some(nags) = nags.as_mut() else {
+            return;
+        };
+        let expired: Vec<String> = nags
+            .passes
+            .iter()
+            .filter(|(_, expiry)| **expiry <= Instant::now())
+            .map(|(site, _)| site.clone())
+            .collect();
+        for site in &expired {
+            nags.passes.remove(site);
+            nags.shown.remove(site);
+        }
+        !expired.is_empty()
+    };
+    if expired {
+        util::rewrite_block(config);
+    }
+}
+
+pub fn read_request(client: &mut impl Read) -> Option<(String, String)> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    while !buffer.windows(4).any(|window| window == b"\r\n\r\n") {
+        if buffer.len() > MAX_REQUEST {
+            return None;
+        }
+        let length = client.read(&mut chunk).ok()?;
+        if length == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..length]);
+    }
+
+    let head = String::from_utf8_lossy(&buffer).to_string();
+    let mut lines = head.split("\r\n");
+    let target = lines.next()?.split_whitespace().nth(1)?.to_string();
+    let host = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("host"))
+        .map(|(_, value)| value.trim())
+        .map(|value| value.split(':').next().unwrap_or(value).to_lowercase())
+        .unwrap_or_default();
+    Some((target, host))
+}
+
+fn query_value(query: &str, key: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == key)
+        .map(|(_, value)| percent_decode(value))
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| u8::from_str_radix(&String::from_utf8_lossy(hex), 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+pub fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn respond(client: &mut impl Write, status: &str, body: &str) {
+    let _ = client.write_all(
+        format!(
+            "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nCache-Control: no-store\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+        .as_bytes(),
+    );
+}
+
+/// Built-in wording per `language`; anything in `strings` overrides it.
+fn builtin_strings(language: &str) -> [(&'static str, &'static str); 4] {
+    match language {
+        "de" => [
+            ("blocked", "{{domain}} ist gesperrt"),
+            (
+                "minutes_left",
+                "Noch {{minutes}} Minuten in dieser Sitzung.",
+            ),
+            ("nag_title", "Bist du sicher?"),
+            ("continue", "Weiter zu {{domain}}"),
+        ],
+        "es" => [
+            ("blocked", "{{domain}} está bloqueado"),
+            ("minutes_left", "Quedan {{minutes}} minutos de sesión."),
+            ("nag_title", "¿Seguro?"),
+            ("continue", "Continuar a {{domain}}"),
+        ],
+        "fr" => [
+            ("blocked", "{{domain}} est bloqué"),
+            (
+                "minutes_left",
+                "Encore {{minutes}} minutes dans cette session.",
+            ),
+            ("nag_title", "Tu es sûr ?"),
+            ("continue", "Continuer vers {{domain}}"),
+        ],
+        _ => [
+            ("blocked", "{{domain}} is blocked"),
+            ("minutes_left", "{{minutes}} minutes left in your session."),
+            ("nag_title", "Are you sure?"),
+            ("continue", "Continue to {{domain}}"),
+        ],
+    }
+}
+
+/// Renders a page from the user's template when set, falling back to the built-in one.
+fn render_page(
+    config: &Config,
+    custom: Option<&String>,
+    builtin: &str,
+    mut data: serde_json::Value,
+) -> String {
+    let page = &config.block_page;
+    // Strings are plain text here; the page template escapes them once when inserting
+    let mut text = Handlebars::new();
+    text.register_escape_fn(handlebars::no_escape);
+    let strings: serde_json::Map<String, serde_json::Value> = builtin_strings(&page.language)
+        .into_iter()
+        .map(|(key, default)| {
+            let template = page.strings.get(key).map(String::as_str).unwrap_or(default);
+            let rendered = text
+                .render_template(template, &data)
+                .unwrap_or_else(|_| template.to_string());
+            (key.to_string(), serde_json::Value::String(rendered))
+        })
+        .collect();
+    data["strings"] = serde_json::Value::Object(strings);
+    data["style"] = serde_json::Value::String(page.style.replace("</", "<\\/"));
+    data["language"] = serde_json::Value::String(page.language.clone());
+
+    let handlebars = Handlebars::new();
+    if let Some(path) = custom {
+        let path = format!("{}/{}", config.data_directory, path);
+        match fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|template| {
+                handlebars
+                    .render_template(&template, &data)
+                    .map_err(|e| e.to_string())
+            }) {
+            Ok(page) => return page,
+            Err(e) => eprintln!(
+                "{}",
+                format!("[!] Block page template {} failed: {}", path, e)
+                    .bold()
+                    .yellow()
+            ),
+        }
+    }
+    handlebars
+        .render_template(builtin, &data)
+        .unwrap_or_default()
+}
+
+fn blocked_page(config: &Config, host: &str, minutes_left: u64) -> String {
+    render_page(
+        config,
+        config.block_page.template.as_ref(),
+        BLOCKED_TEMPLATE,
+        serde_json::json!({ "domain": host, "minutes": minutes_left }),
+    )
+}
+
+fn nag_page(config: &Config, host: &str, target: &str, minutes_left: u64, delay: u64) -> String {
+    render_page(
+        config,
+        config.block_page.nag_template.as_ref(),
+        NAG_TEMPLATE,
+        serde_json::json!({
+            "domain": host,
+            "minutes": minutes_left,
+            "delay": delay,
+            "continue_url": format!("{}?path={}", CONTINUE_PATH, percent_encode(target)),
+        }),
+    )
+}
@@ -0,0 +1,421 @@
+use crate::controls;
+#[cfg(target_os = "macos")]
+use std::ffi::CString;
+use std::{
+    env, thread,
+    time::{Duration, Instant},
+};
+#[cfg(unix)]
+use std::{
+    fs::File,
+    io,
+    os::{
+        fd::{AsFd, AsRawFd, FromRawFd},
+        unix::net::UnixStream,
+    },
+};
+#[cfg(unix)]
+use wayland_client::{
+    Connection, Dispatch, QueueHandle, delegate_noop,
+    globals::{GlobalListContents, registry_queue_init},
+    protocol::{
+        wl_buffer::{self, WlBuffer},
+        wl_compositor::WlCompositor,
+        wl_registry::WlRegistry,
+        wl_shm::{self, WlShm},
+        wl_shm_pool::WlShmPool,
+        wl_surface::WlSurface,
+    },
+};
+#[cfg(unix)]
+use wayland_protocols_wlr::layer_shell::v1::client::{
+    zwlr_layer_shell_v1::{Layer, ZwlrLayerShellV1},
+    zwlr_layer_surface_v1::{self, Anchor, KeyboardInteractivity, ZwlrLayerSurfaceV1},
+};
+use x11rb::{
+    connection::Connection as _,
+    protocol::xproto::{
+        ColormapAlloc, ConnectionExt as _, CreateGCAux, CreateWindowAux, EventMask, GrabMode,
+        Rectangle, VisualClass, WindowClass,
+    },
+};
+
+const FRAME_INTERVAL: Duration = Duration::from_millis(250);
+/// Black at 80% opacity, premultiplied ARGB
+const DIM: u32 = 0xcc00_0000;
+const INK: u32 = 0xffff_ffff;
+/// Countdown glyphs on a 3x5 grid, one row per three bits
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+const COLON: [u8; 5] = [0b000, 0b010, 0b000, 0b010, 0b000];
+
+/// Covers the screen with a dimmed countdown until the break is over: a layer-shell
+/// surface on Wayland, an override-redirect window on X11 and XWayland.
+#[cfg(unix)]
+pub fn show(seconds: u64) -> Result<(), String> {
+    let end = Instant::now() + Duration::from_secs(seconds);
+    let wayland = match wayland_connection() {
+        Some(conn) => show_wayland(conn, end),
+        None => Err(String::from("no Wayland compositor")),
+    };
+    wayland.or_else(|wayland| show_x11(end).map_err(|x11| format!("{}; X11: {}", wayland, x11)))
+}
+
+/// Wayland only runs on Unix; an X server on Windows still gets the window.
+#[cfg(target_os = "windows")]
+pub fn show(seconds: u64) -> Result<(), String> {
+    show_x11(Instant::now() + Duration::from_secs(seconds))
+}
+
+/// sudo drops `WAYLAND_DISPLAY`, so fall back to the usual socket in the runtime dir.
+#[cfg(unix)]
+fn wayland_connection() -> Option<Connection> {
+    let display = env::var("WAYLAND_DISPLAY").unwrap_or_else(|_| String::from("wayland-0"));
+    let path = if display.starts_with('/') {
+        display
+    } else {
+        format!("{}/{}", env::var("XDG_RUNTIME_DIR").ok()?, display)
+    };
+    Connection::from_socket(UnixStream::connect(path).ok()?).ok()
+}
+
+fn countdown(end: Instant) -> String {
+    controls::format_clock(end.saturating_duration_since(Instant::now()).as_secs())
+}
+
+/// The lit cells of `text` as squares, scaled to a third of the screen width and centred.
+fn glyph_rects(text: &str, width: u32, height: u32) -> Vec<(u32, u32, u32)> {
+    let glyphs: Vec<[u8; 5]> = text
+        .chars()
+        .map(|c| match c.to_digit(10) {
+            Some(digit) => DIGITS[digit as usize],
+            None => COLON,
+        })
+        .collect();
+    let columns = (glyphs.len() as u32 * 4).saturating_sub(1).max(1);
+    let cell = (width / 3 / columns).min(height / 3 / 5).max(1);
+    let left = width.saturating_sub(columns * cell) / 2;
+    let top = height.saturating_sub(5 * cell) / 2;
+
+    let mut rects = Vec::new();
+    for (index, glyph) in glyphs.iter().enumerate() {
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) != 0 {
+                    let x = left + (index as u32 * 4 + column) * cell;
+                    rects.push((x, top + row as u32 * cell, cell));
+                }
+            }
+        }
+    }
+    rects
+}
+
+fn show_x11(end: Instant) -> Result<(), String> {
+    let display = env::var("DISPLAY").unwrap_or_else(|_| String::from(":0"));
+    let (conn, screen_num) = x11rb::connect(Some(&display)).map_err(|e| e.to_string())?;
+    let screen = &conn.setup().roots[screen_num];
+    let (width, height) = (screen.width_in_pixels, screen.height_in_pixels);
+    // A 32-bit visual lets a compositor show the desktop through; without one it's just dark
+    let argb = screen
+        .allowed_depths
+        .iter()
+        .filter(|depth| depth.depth == 32)
+        .flat_map(|depth| &depth.visuals)
+        .find(|visual| visual.class == VisualClass::TRUE_COLOR)
+        .map(|visual| visual.visual_id);
+
+    let x11 = |e: x11rb::errors::ConnectionError| e.to_string();
+    let window = conn.generate_id().map_err(|e| e.to_string())?;
+    let mut aux = CreateWindowAux::new()
+        .override_redirect(1)
+        .border_pixel(0)
+        .event_mask(EventMask::EXPOSURE);
+    let (depth, visual) = match argb {
+        Some(visual) => {
+            let colormap = conn.generate_id().map_err(|e| e.to_string())?;
+            conn.create_colormap(ColormapAlloc::NONE, colormap, screen.root, visual)
+                .map_err(x11)?;
+            aux = aux.colormap(colormap).background_pixel(DIM);
+            (32, visual)
+        }
+        None => {
+            aux = aux.background_pixel(screen.black_pixel);
+            (screen.root_depth, screen.root_visual)
+        }
+    };
+    conn.create_window(
+        depth,
+        window,
+        screen.root,
+        0,
+        0,
+        width,
+        height,
+        0,
+        WindowClass::INPUT_OUTPUT,
+        visual,
+        &aux,
+    )
+    .map_err(x11)?;
+    let gc = conn.generate_id().map_err(|e| e.to_string())?;
+    conn.create_gc(gc, window, &CreateGCAux::new().foreground(INK))
+        .map_err(x11)?;
+    conn.map_window(window).map_err(x11)?;
+    conn.flush().map_err(x11)?;
+    // Keys and clicks go nowhere until the break is over
+    let _ = conn.grab_keyboard(
+        true,
+        window,
+        x11rb::CURRENT_TIME,
+        GrabMode::ASYNC,
+        GrabMode::ASYNC,
+    );
+    let _ = conn.grab_pointer(
+        true,
+        window,
+        EventMask::NO_EVENT,
+        GrabMode::ASYNC,
+        GrabMode::ASYNC,
+        x11rb::NONE,
+        x11rb::NONE,
+        x11rb::CURRENT_TIME,
+    );
+
+    while Instant::now() < end {
+        while let Ok(Some(_)) = conn.poll_for_event() {}
+        let rects: Vec<Rectangle> = glyph_rects(&countdown(end), width as u32, height as u32)
+            .into_iter()
+            .map(|(x, y, size)| Rectangle {
+                x: x as i16,
+                y: y as i16,
+                width: size as u16,
+                height: size as u16,
+            })
+            .collect();
+        conn.clear_area(false, window, 0, 0, 0, 0).map_err(x11)?;
+        conn.poly_fill_rectangle(window, gc, &rects).map_err(x11)?;
+        conn.flush().map_err(x11)?;
+        thread::sleep(FRAME_INTERVAL);
+    }
+    conn.destroy_window(window).map_err(x11)?;
+    conn.flush().map_err(x11)
+}
+
+#[derive(Default)]
+#[cfg(unix)]
+struct LayerState {
+    size: Option<(u32, u32)>,
+    closed: bool,
+    buffer_busy: bool,
+}
+
+#[cfg(unix)]
+fn show_wayland(conn: Connection, end: Instant) -> Result<(), String> {
+    let (globals, mut queue) =
+        registry_queue_init::<LayerState>(&conn).map_err(|e| e.to_string())?;
+    let qh = queue.handle();
+    let compositor: WlCompositor = globals.bind(&qh, 4..=6, ()).map_err(|e| e.to_string())?;
+    let shm: WlShm = globals.bind(&qh, 1..=1, ()).map_err(|e| e.to_string())?;
+    let layer_shell: ZwlrLayerShellV1 = globals
+        .bind(&qh, 1..=4, ())
+        .map_err(|_| String::from("the compositor has no layer-shell"))?;
+
+    let surface = compositor.create_surface(&qh, ());
+    let layer_surface = layer_shell.get_layer_surface(
+        &surface,
+        None,
+        Layer::Overlay,
+        String::from("focus-break"),
+        &qh,
+        (),
+    );
+    layer_surface.set_anchor(Anchor::Top | Anchor::Bottom | Anchor::Left | Anchor::Right);
+    layer_surface.set_exclusive_zone(-1);
+    layer_surface.set_keyboard_interactivity(KeyboardInteractivity::Exclusive);
+    surface.commit();
+
+    let mut state = LayerState::default();
+    while state.size.is_none() && !state.closed {
+        queue
+            .blocking_dispatch(&mut state)
+            .map_err(|e| e.to_string())?;
+    }
+    let Some((width, height)) = state.size else {
+        return Ok(());
+    };
+
+    let length = (width * height * 4) as usize;
+    let file = shared_memory().map_err(|e| e.to_string())?;
+    file.set_len(length as u64).map_err(|e| e.to_string())?;
+    let memory = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            length,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            file.as_raw_fd(),
+            0,
+        )
+    };
+    if memory == libc::MAP_FAILED {
+        return Err(String::from("mmap failed"));
+    }
+    let pixels = unsafe { std::slice::from_raw_parts_mut(memory as *mut u32, length / 4) };
+    let pool = shm.create_pool(file.as_fd(), length as i32, &qh, ());
+    let buffer = pool.create_buffer(
+        0,
+        width as i32,
+        height as i32,
+        (width * 4) as i32,
+        wl_shm::Format::Argb8888,
+        &qh,
+        (),
+    );
+
+    let result = (|| {
+        while Instant::now() < end && !state.closed {
+            if !state.buffer_busy {
+                pixels.fill(DIM);
+                for (x, y, size) in glyph_rects(&countdown(end), width, height) {
+                    for row in y..(y + size).min(height) {
+                        let start = (row * width + x) as usize;
+                        let stop = (row * width + (x + size).min(width)) as usize;
+                        pixels[start..stop].fill(INK);
+                    }
+                }
+                surface.attach(Some(&buffer), 0, 0);
+                surface.damage_buffer(0, 0, width as i32, height as i32);
+                surface.commit();
+                state.buffer_busy = true;
+            }
+            queue.flush().map_err(|e| e.to_string())?;
+            if let Some(guard) = queue.prepare_read() {
+                let mut poll = libc::pollfd {
+                    fd: guard.connection_fd().as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+                let ready = unsafe { libc::poll(&mut poll, 1, FRAME_INTERVAL.as_millis() as i32) };
+                if ready > 0 {
+                    guard.read().map_err(|e| e.to_string())?;
+                }
+            }
+            queue
+                .dispatch_pending(&mut state)
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    })();
+
+    layer_surface.destroy();
+    surface.destroy();
+    buffer.destroy();
+    pool.destroy();
+    let _ = conn.flush();
+    unsafe { libc::munmap(memory, length) };
+    result
+}
+
+/// An anonymous file the compositor maps to read the frame.
+#[cfg(target_os = "linux")]
+fn shared_memory() -> io::Result<File> {
+    let fd = unsafe { libc::memfd_create(c"focus-break".as_ptr(), libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+/// macOS has no `memfd_create`, so a POSIX shared memory object is unlinked as soon as it opens.
+#[cfg(target_os = "macos")]
+fn shared_memory() -> io::Result<File> {
+    let name = CString::new(format!("/focus-break-{}", std::process::id()))?;
+    let fd = unsafe {
+        libc::shm_open(
+            name.as_ptr(),
+            libc::O_RDWR | libc::O_CREAT | libc::O_EXCL,
+            0o600 as libc::c_uint,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    unsafe { libc::shm_unlink(name.as_ptr()) };
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+#[cfg(unix)]
+impl Dispatch<WlRegistry, GlobalListContents> for LayerState {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: <WlRegistry as wayland_client::Proxy>::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+#[cfg(unix)]
+impl Dispatch<ZwlrLayerSurfaceV1, ()> for LayerState {
+    fn event(
+        state: &mut Self,
+        layer_surface: &ZwlrLayerSurfaceV1,
+        event: zwlr_layer_surface_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_layer_surface_v1::Event::Configure {
+                serial,
+                width,
+                height,
+            } => {
+                layer_surface.ack_configure(serial);
+                state.size.get_or_insert((width.max(1), height.max(1)));
+            }
+            zwlr_layer_surface_v1::Event::Closed => state.closed = true,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Dispatch<WlBuffer, ()> for LayerState {
+    fn event(
+        state: &mut Self,
+        _: &WlBuffer,
+        event: wl_buffer::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_buffer::Event::Release = event {
+            state.buffer_busy = false;
+        }
+    }
+}
+
+#[cfg(unix)]
+delegate_noop!(LayerState: WlCompositor);
+#[cfg(unix)]
+delegate_noop!(LayerState: WlShmPool);
+#[cfg(unix)]
+delegate_noop!(LayerState: ignore WlShm);
+#[cfg(unix)]
+delegate_noop!(LayerState: ignore WlSurface);
+#[cfg(unix)]
+delegate_noop!(LayerState: ignore ZwlrLayerShellV1);
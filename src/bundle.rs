@@ -0,0 +1,212 @@
+use crate::{
+    audit, limits, onboarding, packs, prompt, stats,
+    util::{self, Config},
+    vacation,
+};
+use chrono::{DateTime, Local};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Component, Path},
+    process,
+};
+
+/// Version 2 added the data directory's files
+const BUNDLE_VERSION: u32 = 2;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Bundle {
+    version: u32,
+    created: DateTime<Local>,
+    config: Config,
+    files: BTreeMap<String, String>,
+    /// Keyed by their path within the data directory
+    #[serde(default)]
+    data_files: BTreeMap<String, String>,
+}
+
+/// Files in the log directory; the session state and PID files belong to this machine's run.
+fn state_files(config: &Config) -> Vec<String> {
+    vec![
+        audit::audit_path(config),
+        limits::counters_path(config),
+        onboarding::state_path(config),
+        stats::history_path(config),
+        stats::query_path(config),
+        stats::tamper_path(config),
+    ]
+}
+
+/// Installed packs and the cached holiday calendar. Sounds and block page files can be
+/// large and are copied by hand, and the local CA key never leaves the machine.
+fn data_files(config: &Config) -> Vec<String> {
+    let mut files = vec![vacation::calendar_path(config)];
+    files.extend(
+        fs::read_dir(packs::lists_path(config))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path().to_string_lossy().to_string()),
+    );
+    files
+}
+
+/// Keeps a bundled data file inside the data directory.
+fn is_relative_file(name: &str) -> bool {
+    let path = Path::new(name);
+    path.components().count() > 0
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+pub fn export_state(config: &Config, file: &str) {
+    let mut files = BTreeMap::new();
+    for path in state_files(config) {
+        if let Ok(content) = fs::read_to_string(&path) {
+            files.insert(file_name(&path), content);
+        }
+    }
+    let mut data = BTreeMap::new();
+    for path in data_files(config) {
+        if let Ok(content) = fs::read_to_string(&path)
+            && let Ok(name) = Path::new(&path).strip_prefix(&config.data_directory)
+        {
+            data.insert(name.to_string_lossy().to_string(), content);
+        }
+    }
+
+    let bundle = Bundle {
+        version: BUNDLE_VERSION,
+        created: Local::now(),
+        config: config.clone(),
+        files,
+        data_files: data,
+    };
+    let content = serde_json::to_string_pretty(&bundle).expect("[!] Could not encode state bundle");
+    // The config carries the bot tokens and the admin password hash
+    if let Err(e) = util::write_private(file, &content) {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to write {}: {}", file, e).bold().red()
+        );
+        process::exit(1);
+    }
+
+    println!(
+        "{}",
+        format!(
+            "[+] Exported config and {} state files to {}",
+            bundle.files.len() + bundle.data_files.len(),
+            file
+        )
+        .bold()
+        .green()
+    );
+}
+
+pub fn import_state(file: &str) {
+    let bundle: Bundle = match fs::read_to_string(file)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+    {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                format!("[!] Failed to read state bundle {}: {}", file, e)
+                    .bold()
+                    .red()
+            );
+            process::exit(1);
+        }
+    };
+
+    if bundle.version > BUNDLE_VERSION {
+        eprintln!(
+            "{}",
+            format!(
+                "[!] State bundle version {} is newer than this focus supports ({})",
+                bundle.version, BUNDLE_VERSION
+            )
+            .bold()
+            .red()
+        );
+        process::exit(1);
+    }
+
+    let question = format!(
+        "[?] Replace the current config and history with the bundle from {}? [y/N] ",
+        bundle.created.format("%Y-%m-%d %H:%M")
+    );
+    if !prompt::confirm(&format!("{}", question.bold().yellow()), None) {
+        println!("{}", "[>] Import cancelled".bold().cyan());
+        return;
+    }
+
+    util::save_config_private(&bundle.config).expect("[!] Failed to save configuration");
+    if let Err(e) = fs::create_dir_all(&bundle.config.log_directory) {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to create log directory: {}", e)
+                .bold()
+                .red()
+        );
+        process::exit(1);
+    }
+    for (name, content) in &bundle.files {
+        let path = format!("{}/{}", bundle.config.log_directory, file_name(name));
+        if let Err(e) = fs::write(&path, content) {
+            eprintln!(
+                "{}",
+                format!("[!] Failed to restore {}: {}", path, e)
+                    .bold()
+                    .yellow()
+            );
+        }
+    }
+
+    for (name, content) in &bundle.data_files {
+        if !is_relative_file(name) {
+            eprintln!(
+                "{}",
+                format!("[!] Skipping {}: outside the data directory", name)
+                    .bold()
+                    .yellow()
+            );
+            continue;
+        }
+        let path = Path::new(&bundle.config.data_directory).join(name);
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, content));
+        if let Err(e) = result {
+            eprintln!(
+                "{}",
+                format!("[!] Failed to restore {}: {}", path.display(), e)
+                    .bold()
+                    .yellow()
+            );
+        }
+    }
+
+    println!(
+        "{}",
+        format!(
+            "[+] Imported config and {} state files",
+            bundle.files.len() + bundle.data_files.len()
+        )
+        .bold()
+        .green()
+    );
+}
@@ -0,0 +1,137 @@
+use crate::{
+    admin, audit, prompt,
+    util::{self, Config},
+};
+use argon2::{
+    Argon2,
+    password_hash::{PasswordHash, PasswordVerifier},
+};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Letters and digits that can't be misread for one another when read out over the phone
+const ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const GROUPS: usize = 3;
+const GROUP_LENGTH: usize = 4;
+
+/// The one-time code of a strict session, kept as a hash next to the session's other logs.
+#[derive(Debug, Serialize, Deserialize)]
+struct Bypass {
+    hash: String,
+    /// Set once the code stopped the session; it is never accepted again
+    #[serde(default)]
+    used: bool,
+}
+
+fn path(config: &Config) -> String {
+    util::scoped_path(config, "bypass")
+}
+
+fn load(config: &Config) -> Option<Bypass> {
+    fs::read_to_string(path(config))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+fn save(config: &Config, bypass: &Bypass) {
+    let content = serde_json::to_string(bypass).expect("[!] Could not encode bypass code");
+    if let Err(e) = fs::write(path(config), content) {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to save the bypass code: {}", e)
+                .bold()
+                .red()
+        );
+    }
+}
+
+/// Drops the last session's code and, for a strict session, generates a new one and prints
+/// it, once.
+pub fn prepare(config: &Config) {
+    let _ = fs::remove_file(path(config));
+    if !config.strict {
+        return;
+    }
+    let mut random = [0u8; GROUPS * GROUP_LENGTH];
+    if getrandom::getrandom(&mut random).is_err() {
+        eprintln!(
+            "{}",
+            "[!] Could not generate an emergency bypass code; this session has none"
+                .bold()
+                .red()
+        );
+        return;
+    }
+    let code = random
+        .chunks(GROUP_LENGTH)
+        .map(|group| {
+            group
+                .iter()
+                .map(|byte| ALPHABET[*byte as usize % ALPHABET.len()] as char)
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("-");
+    save(
+        config,
+        &Bypass {
+            hash: admin::hash_password(&code),
+            used: false,
+        },
+    );
+    println!(
+        "{}",
+        format!("[!] Emergency bypass code: {}", code)
+            .bold()
+            .yellow()
+    );
+    println!(
+        "{}",
+        "[>] Hand it to someone you trust; it is shown only once and stops this session once"
+            .bold()
+            .cyan()
+    );
+}
+
+/// Asks for the code to stop a strict session, spending it when it matches.
+pub fn redeem(config: &Config) -> bool {
+    let Some(mut bypass) = load(config).filter(|bypass| !bypass.used) else {
+        return false;
+    };
+    let Ok(hash) = PasswordHash::new(&bypass.hash) else {
+        return false;
+    };
+    let Ok(code) = prompt::with_reader_suspended(|| {
+        rpassword::prompt_password("[?] Emergency bypass code (Enter to keep going): ")
+    }) else {
+        return false;
+    };
+    let code = code.trim().to_uppercase();
+    if code.is_empty() {
+        return false;
+    }
+    if Argon2::default()
+        .verify_password(code.as_bytes(), &hash)
+        .is_err()
+    {
+        eprintln!("{}", "[!] Incorrect bypass code".bold().red());
+        audit::record(config, "session", "wrong emergency bypass code");
+        return false;
+    }
+    bypass.used = true;
+    save(config, &bypass);
+    println!(
+        "{}",
+        "[!] Emergency bypass used; this is recorded in the stats and the audit log"
+            .bold()
+            .yellow()
+    );
+    audit::record(config, "session", "emergency bypass code used");
+    true
+}
+
+/// Whether the running session was stopped with its code, for the outcome it is recorded with.
+pub fn was_used(config: &Config) -> bool {
+    load(config).is_some_and(|bypass| bypass.used)
+}
@@ -0,0 +1,171 @@
+use crate::{
+    audio, audio::AudioBackend, dbus::Bus, desktop, platform, privileged, readonly, util::Config,
+};
+use colored::Colorize;
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+#[cfg(target_os = "linux")]
+use std::{fs, io};
+
+const SYSTEM_BUS_SOCKET: &str = "/run/dbus/system_bus_socket";
+
+/// One optional integration and the config it decides.
+struct Capability {
+    name: &'static str,
+    used_by: &'static str,
+    status: Result<String, String>,
+}
+
+/// Prints which optional integrations work here, so options can be enabled knowingly.
+pub fn report(config: &Config) {
+    println!(
+        "{}",
+        "[>] Optional integrations on this system".bold().cyan()
+    );
+    let capabilities = [
+        Capability {
+            name: "hosts file",
+            used_by: "blocking sites, so sessions fall back to the DNS sinkhole",
+            status: hosts_file(config),
+        },
+        Capability {
+            name: "systemd",
+            used_by: "`focus companion --unit` and finding the desktop user's session",
+            status: systemd(),
+        },
+        Capability {
+            name: platform::FLUSH_DNS[0][0],
+            used_by: "flushing the DNS cache when a session starts",
+            status: program(platform::FLUSH_DNS[0][0]),
+        },
+        Capability {
+            name: "D-Bus",
+            used_by: "[dbus] signals",
+            status: dbus(config),
+        },
+        Capability {
+            name: "nftables",
+            used_by: "[sni] and blocking_backend = \"firewall\"",
+            status: privileged::nft_path()
+                .map(String::from)
+                .ok_or_else(|| String::from("nft is not installed")),
+        },
+        Capability {
+            name: "notifications",
+            used_by: "`notifications` and [desktop] actions",
+            status: notifications(),
+        },
+        Capability {
+            name: "audio",
+            used_by: "start_audio, end_audio and ambient_audio",
+            status: audio(config),
+        },
+        Capability {
+            name: "inotify",
+            used_by: "watching files for changes instead of polling them",
+            status: inotify(),
+        },
+    ];
+    for capability in capabilities {
+        match capability.status {
+            Ok(detail) => println!(
+                "{}",
+                format!("[+] {:<14} {}", capability.name, detail)
+                    .bold()
+                    .green()
+            ),
+            Err(reason) => println!(
+                "{}",
+                format!(
+                    "[!] {:<14} {}; affects {}",
+                    capability.name, reason, capability.used_by
+                )
+                .bold()
+                .yellow()
+            ),
+        }
+    }
+}
+
+/// Finds `name` on $PATH the way a shell would.
+fn find_program(name: &str) -> Option<PathBuf> {
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|directory| directory.join(name))
+        .find(|path| path.is_file())
+}
+
+fn program(name: &str) -> Result<String, String> {
+    find_program(name)
+        .map(|path| path.display().to_string())
+        .ok_or_else(|| format!("{} is not installed", name))
+}
+
+fn hosts_file(config: &Config) -> Result<String, String> {
+    let target = privileged::resolve_hosts(&config.hosts_path)?;
+    if readonly::is_read_only(&target) {
+        Err(format!("{} is on a read-only filesystem", target.display()))
+    } else {
+        Ok(format!("{} is writable", target.display()))
+    }
+}
+
+fn systemd() -> Result<String, String> {
+    // The check sd_booted() makes
+    if Path::new("/run/systemd/system").is_dir() {
+        Ok(String::from("running as the init system"))
+    } else {
+        Err(String::from("not the init system"))
+    }
+}
+
+fn dbus(config: &Config) -> Result<String, String> {
+    let path = program("dbus-send")?;
+    if config.dbus.bus == Bus::System && !Path::new(SYSTEM_BUS_SOCKET).exists() {
+        return Err(String::from("the system bus isn't running"));
+    }
+    Ok(path)
+}
+
+fn notifications() -> Result<String, String> {
+    if cfg!(not(any(target_os = "macos", target_os = "windows"))) {
+        program("notify-send")?;
+    }
+    if desktop::has_notification_daemon() {
+        Ok(String::from("a notification service is running"))
+    } else {
+        Err(String::from("no notification service on the session bus"))
+    }
+}
+
+fn audio(config: &Config) -> Result<String, String> {
+    if !cfg!(feature = "audio") {
+        return Err(String::from("built without the audio feature"));
+    }
+    if config.audio_backend == AudioBackend::None {
+        return Err(String::from("audio_backend is none"));
+    }
+    if audio::has_output_device() {
+        Ok(String::from("the default output device opens"))
+    } else {
+        Err(String::from("no output device could be opened"))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn inotify() -> Result<String, String> {
+    let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error().to_string());
+    }
+    unsafe { libc::close(fd) };
+    Ok(fs::read_to_string("/proc/sys/fs/inotify/max_user_watches")
+        .map(|watches| format!("up to {} watches per user", watches.trim()))
+        .unwrap_or_else(|_| String::from("available")))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn inotify() -> Result<String, String> {
+    Err(String::from("only Linux has inotify"))
+}
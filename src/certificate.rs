@@ -0,0 +1,239 @@
+use crate::{prompt, util::Config};
+use colored::Colorize;
+use rcgen::{
+    BasicConstraints, CertificateParams, DistinguishedName, DnType, IsCa, Issuer, KeyPair,
+    KeyUsagePurpose,
+};
+use rustls::{
+    ClientConfig, RootCertStore, ServerConfig,
+    crypto::ring::sign,
+    pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer},
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, OpenOptions},
+    io::Write,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+const CA_NAME: &str = "focus local block page CA";
+const CA_CERT_FILE: &str = "focus-ca.pem";
+const CA_KEY_FILE: &str = "focus-ca.key";
+
+static CLIENT_CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+
+fn ca_cert_path(config: &Config) -> String {
+    format!("{}/{}", config.data_directory, CA_CERT_FILE)
+}
+
+fn ca_key_path(config: &Config) -> String {
+    format!("{}/{}", config.data_directory, CA_KEY_FILE)
+}
+
+/// The CA's parameters are fixed, so the issuer can be rebuilt from the key alone.
+fn ca_params() -> CertificateParams {
+    let mut params = CertificateParams::default();
+    let mut name = DistinguishedName::new();
+    name.push(DnType::CommonName, CA_NAME);
+    params.distinguished_name = name;
+    params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+    params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+    params
+}
+
+/// Creates the local CA after explaining what trusting it means, then prints how to install it.
+pub fn create(config: &Config) -> bool {
+    if fs::metadata(ca_key_path(config)).is_ok() {
+        println!("{}", "[>] A local certificate already exists".bold().cyan());
+        print_instructions(config);
+        return true;
+    }
+    println!(
+        "{}",
+        "[>] To show the block page for HTTPS sites, focus needs a local certificate authority (CA)."
+            .bold()
+            .cyan()
+    );
+    println!("    Browsers will only accept it once you install and trust it yourself.");
+    println!("    Anyone who can read its key could impersonate any website to this machine,");
+    println!(
+        "    so the key stays in {} readable by root only,",
+        config.data_directory
+    );
+    println!("    and focus only ever signs certificates for sites on your block list.");
+    println!("    Run `focus certificate --remove` to delete it again.");
+    if !prompt::confirm(
+        &format!("{}", "[?] Create the certificate? [y/N] ".bold().yellow()),
+        None,
+    ) {
+        println!("{}", "[>] No certificate created".bold().cyan());
+        return false;
+    }
+
+    let result = KeyPair::generate()
+        .and_then(|key| Ok((ca_params().self_signed(&key)?, key)))
+        .map_err(|e| e.to_string())
+        .and_then(|(certificate, key)| {
+            // Private from the moment it exists, as the key can impersonate any site
+            let mut options = OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            options.mode(0o600);
+            options
+                .open(ca_key_path(config))
+                .and_then(|mut file| file.write_all(key.serialize_pem().as_bytes()))
+                .and_then(|_| fs::write(ca_cert_path(config), certificate.pem()))
+                .map_err(|e| e.to_string())
+        });
+    match result {
+        Ok(()) => {
+            println!(
+                "{}",
+                format!("[+] Certificate written to {}", ca_cert_path(config))
+                    .bold()
+                    .green()
+            );
+            print_instructions(config);
+            true
+        }
+        Err(e) => {
+            let _ = fs::remove_file(ca_key_path(config));
+            eprintln!(
+                "{}",
+                format!("[!] Could not create the certificate: {}", e)
+                    .bold()
+                    .red()
+            );
+            false
+        }
+    }
+}
+
+fn print_instructions(config: &Config) {
+    let path = ca_cert_path(config);
+    println!("{}", "[>] To trust it system-wide:".bold().cyan());
+    println!(
+        "    Debian/Ubuntu: sudo cp {} /usr/local/share/ca-certificates/focus.crt && sudo update-ca-certificates",
+        path
+    );
+    println!("    Fedora/Arch:   sudo trust anchor {}", path);
+    println!(
+        "    Firefox keeps its own store: Settings > Privacy & Security > Certificates > Import"
+    );
+    println!("    Then set block_page.https_listen (e.g. \"127.0.0.1:443\") in the config.");
+}
+
+/// Deletes the CA; trust entries the user added have to be removed by hand.
+pub fn remove(config: &Config) -> bool {
+    let removed = [ca_key_path(config), ca_cert_path(config)]
+        .iter()
+        .any(|path| fs::remove_file(path).is_ok());
+    if removed {
+        println!("{}", "[+] Local certificate removed".bold().green());
+        println!(
+            "    Also remove it from anywhere you trusted it, e.g. sudo rm /usr/local/share/ca-certificates/focus.crt && sudo update-ca-certificates --fresh"
+        );
+    } else {
+        println!("{}", "[>] No local certificate to remove".bold().cyan());
+    }
+    removed
+}
+
+/// Signs a certificate per blocked host on demand, as the browser asks for it via SNI.
+struct SiteCertificates {
+    issuer: Issuer<'static, KeyPair>,
+    key: KeyPair,
+    sites: Vec<String>,
+    issued: Mutex<HashMap<String, Arc<CertifiedKey>>>,
+}
+
+impl fmt::Debug for SiteCertificates {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SiteCertificates")
+            .field("sites", &self.sites)
+            .finish()
+    }
+}
+
+impl SiteCertificates {
+    fn is_blocked(&self, host: &str) -> bool {
+        self.sites
+            .iter()
+            .any(|site| host == site || host.ends_with(&format!(".{}", site)))
+    }
+
+    fn issue(&self, host: &str) -> Option<Arc<CertifiedKey>> {
+        let certificate = CertificateParams::new(vec![host.to_string()])
+            .ok()?
+            .signed_by(&self.key, &self.issuer)
+            .ok()?;
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(self.key.serialize_der()));
+        let signing_key = sign::any_supported_type(&key).ok()?;
+        Some(Arc::new(CertifiedKey::new(
+            vec![CertificateDer::from(certificate.der().to_vec())],
+            signing_key,
+        )))
+    }
+}
+
+impl ResolvesServerCert for SiteCertificates {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        let host = client_hello.server_name()?.to_lowercase();
+        if !self.is_blocked(&host) {
+            return None;
+        }
+        let mut issued = self.issued.lock().unwrap();
+        if let Some(key) = issued.get(&host) {
+            return Some(Arc::clone(key));
+        }
+        let key = self.issue(&host)?;
+        issued.insert(host, Arc::clone(&key));
+        Some(key)
+    }
+}
+
+/// The TLS setup for the block page, or None when no CA has been created.
+pub fn server_config(config: &Config, sites: Vec<String>) -> Option<Arc<ServerConfig>> {
+    let pem = fs::read_to_string(ca_key_path(config)).ok()?;
+    let resolver = KeyPair::from_pem(&pem)
+        .and_then(|ca_key| {
+            Ok(SiteCertificates {
+                issuer: Issuer::new(ca_params(), ca_key),
+                key: KeyPair::generate()?,
+                sites,
+                issued: Mutex::new(HashMap::new()),
+            })
+        })
+        .map_err(|e| {
+            eprintln!(
+                "{}",
+                format!("[!] Could not load the local certificate: {}", e)
+                    .bold()
+                    .red()
+            )
+        })
+        .ok()?;
+    Some(Arc::new(
+        ServerConfig::builder()
+            .with_no_client_auth()
+            .with_cert_resolver(Arc::new(resolver)),
+    ))
+}
+
+/// The TLS setup for connecting to real servers, trusting the bundled web roots.
+pub fn client_config() -> Arc<ClientConfig> {
+    let config = CLIENT_CONFIG.get_or_init(|| {
+        let roots = RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        Arc::new(
+            ClientConfig::builder()
+                .with_root_certificates(roots)
+                .with_no_client_auth(),
+        )
+    });
+    Arc::clone(config)
+}
@@ -0,0 +1,91 @@
+use crate::{
+    audit, events,
+    notify::Event,
+    session::Timer,
+    util::{self, Config},
+};
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use std::{sync::Mutex, time::Duration};
+
+/// More drift than NTP ever corrects in one step between two tamper checks
+const MAX_DRIFT: Duration = Duration::from_secs(120);
+
+/// The wall clock and the boot clock when the session last checked them.
+static REFERENCE: Mutex<Option<(DateTime<Utc>, Duration)>> = Mutex::new(None);
+
+/// Linux's monotonic clock stops during suspend; the boot clock doesn't.
+#[cfg(target_os = "linux")]
+const BOOT_CLOCK: libc::clockid_t = libc::CLOCK_BOOTTIME;
+/// macOS has no `CLOCK_BOOTTIME`, but its raw monotonic clock keeps counting while asleep.
+#[cfg(target_os = "macos")]
+const BOOT_CLOCK: libc::clockid_t = libc::CLOCK_MONOTONIC_RAW;
+
+/// Time since boot, counting suspend, so sleeping the laptop never looks like a clock change.
+#[cfg(not(target_os = "windows"))]
+fn since_boot() -> Duration {
+    let mut now = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe { libc::clock_gettime(BOOT_CLOCK, &mut now) };
+    Duration::new(now.tv_sec as u64, now.tv_nsec as u32)
+}
+
+/// The tick count keeps running while the machine sleeps, like `CLOCK_BOOTTIME`.
+#[cfg(target_os = "windows")]
+fn since_boot() -> Duration {
+    unsafe extern "system" {
+        fn GetTickCount64() -> u64;
+    }
+    Duration::from_millis(unsafe { GetTickCount64() })
+}
+
+/// How far the wall clock moved on its own since the last call, if more than drift.
+fn jump() -> Option<chrono::Duration> {
+    let (wall, boot) = (Utc::now(), since_boot());
+    let previous = REFERENCE.lock().unwrap().replace((wall, boot));
+    let (last_wall, last_boot) = previous?;
+    let elapsed = chrono::Duration::from_std(boot.saturating_sub(last_boot)).ok()?;
+    let jump = (wall - last_wall) - elapsed;
+    (jump.abs().to_std().ok()? > MAX_DRIFT).then_some(jump)
+}
+
+/// Catches the system clock being set, the classic way to end a session early. A strict
+/// session keeps its length and moves the deadline it wrote; others follow the wall clock.
+pub fn check(config: &Config, timer: &Timer) {
+    let Some(jump) = jump() else {
+        return;
+    };
+    let direction = if jump > chrono::Duration::zero() {
+        "forward"
+    } else {
+        "back"
+    };
+    let minutes = jump.num_minutes().abs();
+    println!(
+        "{}",
+        format!(
+            "[!] Tamper detected! The system clock moved {} by {} minutes",
+            direction, minutes
+        )
+        .bold()
+        .red()
+    );
+    audit::record(
+        config,
+        "session",
+        &format!("clock moved {} by {} minutes", direction, minutes),
+    );
+    events::publish(config, Event::TamperDetected);
+
+    let _hosts = util::lock_hosts();
+    if config.strict {
+        util::sync_deadline(timer);
+        util::rewrite_block(config);
+    } else if let Ok(forward) = jump.to_std() {
+        timer.shorten(forward);
+    } else if let Ok(back) = (-jump).to_std() {
+        timer.extend(back);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::desktop::Choice;
+#[cfg(target_os = "linux")]
+use crate::{audio, desktop, platform, socket};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    env, process,
+    sync::{Arc, atomic::AtomicBool},
+    time::Duration,
+};
+#[cfg(target_os = "linux")]
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    os::{
+        linux::net::SocketAddrExt,
+        unix::net::{SocketAddr, UnixListener, UnixStream},
+    },
+    sync::atomic::Ordering,
+    thread,
+};
+
+#[cfg(target_os = "linux")]
+const SOCKET_NAME: &str = "focus-companion";
+const UNIT_NAME: &str = "focus-companion.service";
+#[cfg(target_os = "linux")]
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Work the root daemon hands to the companion, one JSON line per connection.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "do", rename_all = "kebab-case")]
+pub enum Task {
+    Play {
+        path: String,
+    },
+    /// Loops until the daemon closes the connection, so a crashed session can't leave it playing
+    Ambient {
+        path: String,
+    },
+    Speak {
+        text: String,
+    },
+    Notify {
+        title: String,
+        message: String,
+    },
+    Ask {
+        title: String,
+        message: String,
+        extend_minutes: u64,
+        allow_end: bool,
+        timeout_seconds: u64,
+    },
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Debug, Serialize, Deserialize)]
+struct Answer {
+    choice: Option<String>,
+}
+
+/// Set in the companion itself so its own playback never loops back to it.
+#[cfg(target_os = "linux")]
+static IS_COMPANION: AtomicBool = AtomicBool::new(false);
+
+/// An abstract socket per user: nothing to clean up and no runtime directory to guess.
+#[cfg(target_os = "linux")]
+fn address(uid: u32) -> Option<SocketAddr> {
+    SocketAddr::from_abstract_name(format!("{}-{}", SOCKET_NAME, uid)).ok()
+}
+
+/// Connects to the session owner's companion, making sure it really runs as that user.
+#[cfg(target_os = "linux")]
+fn connect() -> Option<UnixStream> {
+    if IS_COMPANION.load(Ordering::SeqCst) {
+        return None;
+    }
+    let owner = socket::owner_uid();
+    let stream = UnixStream::connect_addr(&address(owner)?).ok()?;
+    if socket::peer_uid(&stream) != Some(owner) {
+        return None;
+    }
+    let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+    Some(stream)
+}
+
+#[cfg(target_os = "linux")]
+fn send_on(task: &Task) -> Option<UnixStream> {
+    let mut stream = connect()?;
+    let line = serde_json::to_string(task).ok()?;
+    writeln!(stream, "{}", line).ok()?;
+    Some(stream)
+}
+
+/// Hands a task to the companion; false when none is running and the caller should do it itself.
+#[cfg(target_os = "linux")]
+pub fn send(task: &Task) -> bool {
+    send_on(task).is_some()
+}
+
+/// Keeps the ambient connection open until `running` is cleared.
+#[cfg(target_os = "linux")]
+pub fn play_ambient(path: &str, running: Arc<AtomicBool>) -> bool {
+    let Some(stream) = send_on(&Task::Ambient {
+        path: path.to_string(),
+    }) else {
+        return false;
+    };
+    thread::spawn(move || {
+        while running.load(Ordering::SeqCst) {
+            thread::sleep(audio::AMBIENT_POLL);
+        }
+        drop(stream);
+    });
+    true
+}
+
+/// The button clicked in the companion's notification, or None when no companion is running.
+#[cfg(target_os = "linux")]
+pub fn ask(
+    title: &str,
+    message: &str,
+    extend_minutes: u64,
+    allow_end: bool,
+    timeout: Duration,
+) -> Option<Option<Choice>> {
+    let stream = send_on(&Task::Ask {
+        title: title.to_string(),
+        message: message.to_string(),
+        extend_minutes,
+        allow_end,
+        timeout_seconds: timeout.as_secs(),
+    })?;
+    let _ = stream.set_read_timeout(Some(timeout + WRITE_TIMEOUT));
+    let mut line = String::new();
+    let answer = BufReader::new(stream)
+        .read_line(&mut line)
+        .ok()
+        .and_then(|_| serde_json::from_str::<Answer>(&line).ok())
+        .and_then(|answer| answer.choice);
+    Some(answer.as_deref().and_then(Choice::from_action))
+}
+
+/// Runs as the logged-in user, playing sounds and showing notifications for the root daemon.
+#[cfg(target_os = "linux")]
+pub fn run() {
+    IS_COMPANION.store(true, Ordering::SeqCst);
+    let uid = platform::user_id();
+    let listener = match address(uid).map(|address| UnixListener::bind_addr(&address)) {
+        Some(Ok(listener)) => listener,
+        Some(Err(e)) => {
+            eprintln!(
+                "{}",
+                format!("[!] Could not start the companion: {}", e)
+                    .bold()
+                    .red()
+            );
+            process::exit(1);
+        }
+        None => process::exit(1),
+    };
+    println!(
+        "{}",
+        format!("[>] Companion running for uid {}", uid)
+            .bold()
+            .cyan()
+    );
+    for client in listener.incoming().flatten() {
+        // Only root (the daemon) and this user may drive the companion
+        if !socket::peer_uid(&client).is_some_and(|peer| peer == 0 || peer == uid) {
+            continue;
+        }
+        thread::spawn(move || handle_task(client));
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn handle_task(client: UnixStream) {
+    let Ok(writer) = client.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(client);
+    let mut line = String::new();
+    if reader.read_line(&mut line).is_err() {
+        return;
+    }
+    let Ok(task) = serde_json::from_str::<Task>(&line) else {
+        return;
+    };
+    match task {
+        Task::Play { path } => audio::play_audio(path),
+        Task::Ambient { path } => {
+            let running = Arc::new(AtomicBool::new(true));
+            audio::play_ambient_locally(path, Arc::clone(&running));
+            // Returns once the daemon hangs up
+            let _ = reader.read_to_end(&mut Vec::new());
+            running.store(false, Ordering::SeqCst);
+        }
+        Task::Speak { text } => audio::speak_locally(&text),
+        Task::Notify { title, message } => desktop::show(&title, &message),
+        Task::Ask {
+            title,
+            message,
+            extend_minutes,
+            allow_end,
+            timeout_seconds,
+        } => {
+            let choice = desktop::show_ask(
+                &title,
+                &message,
+                extend_minutes,
+                allow_end,
+                Duration::from_secs(timeout_seconds),
+            );
+            let answer = Answer {
+                choice: choice.map(|choice| choice.action().to_string()),
+            };
+            let mut writer = writer;
+            let _ = writeln!(
+                writer,
+                "{}",
+                serde_json::to_string(&answer).unwrap_or_default()
+            );
+        }
+    }
+}
+
+/// The companion listens on an abstract socket, which only Linux has; elsewhere the session
+/// plays sounds and shows notifications itself.
+#[cfg(not(target_os = "linux"))]
+pub fn send(_task: &Task) -> bool {
+    false
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn play_ambient(_path: &str, _running: Arc<AtomicBool>) -> bool {
+    false
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn ask(
+    _title: &str,
+    _message: &str,
+    _extend_minutes: u64,
+    _allow_end: bool,
+    _timeout: Duration,
+) -> Option<Option<Choice>> {
+    None
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn run() {
+    eprintln!("{}", "[!] The companion only runs on Linux".bold().red());
+    process::exit(1);
+}
+
+/// Prints a systemd user unit that keeps the companion running in the desktop session.
+pub fn print_unit() {
+    let executable = env::current_exe().expect("[!] Could not locate the focus executable");
+    println!(
+        "[Unit]\nDescription=focus companion: session sounds and notifications\nPartOf=graphical-session.target\nAfter=graphical-session.target\n\n[Service]\nExecStart={} companion\nRestart=on-failure\n\n[Install]\nWantedBy=graphical-session.target",
+        executable.display()
+    );
+    eprintln!(
+        "{}",
+        format!(
+            "[>] Save the unit above as ~/.config/systemd/user/{} and run: systemctl --user enable --now {}",
+            UNIT_NAME, UNIT_NAME
+        )
+        .bold()
+        .cyan()
+    );
+}
@@ -0,0 +1,231 @@
+use crate::{
+    audit,
+    limits::{self, Action},
+    platform, power, prompt,
+    session::{self, Timer},
+    util::{self, Config},
+};
+use colored::Colorize;
+use std::{
+    env,
+    io::{self, Write},
+    process::{self, Command, Stdio},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+const EXTEND_MINUTES: u64 = 5;
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+pub const CLEAR_LINE: &str = "\r\x1b[K";
+
+/// Set by SIGUSR1, which `focus background` sends
+static DETACH_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Shows a live countdown and handles single-key commands until `active` is cleared.
+pub fn start(
+    config: Arc<Config>,
+    timer: Arc<Timer>,
+    active: Arc<AtomicBool>,
+) -> Option<JoinHandle<()>> {
+    if !prompt::enter_cbreak() {
+        return None;
+    }
+    println!(
+        "{}",
+        format!(
+            "[>] Keys: p pause/resume, e extend {} min, s status, b background, q stop",
+            EXTEND_MINUTES
+        )
+        .bold()
+        .cyan()
+    );
+
+    Some(thread::spawn(move || {
+        while active.load(Ordering::SeqCst) {
+            let remaining = timer.remaining().as_secs();
+            // On battery the countdown moves a minute at a time instead of waking every second
+            let (shown, refresh) = if power::saving(&config) {
+                let seconds = match remaining % 60 {
+                    0 => 60,
+                    seconds => seconds,
+                };
+                (remaining.div_ceil(60) * 60, Duration::from_secs(seconds))
+            } else {
+                (remaining, REFRESH_INTERVAL)
+            };
+            if !prompt::is_reader_suspended() {
+                draw_countdown(shown, timer.is_paused());
+            }
+            if DETACH_REQUESTED.swap(false, Ordering::SeqCst) {
+                detach(&config, &timer);
+            }
+            match prompt::read_key(refresh) {
+                Some('p') => {
+                    toggle_pause(&config, &timer);
+                }
+                Some('e') => {
+                    util::extend_session(&config, &timer, Duration::from_mins(EXTEND_MINUTES));
+                    audit::record(&config, "session", "extended");
+                    announce(&format!("[>] Extended by {} minutes", EXTEND_MINUTES));
+                }
+                Some('s') => show_status(&config, &timer),
+                Some('b') => detach(&config, &timer),
+                // Goes through the Ctrl-C path so admin and rate-limit checks still apply
+                Some('q') => {
+                    print!("{}", CLEAR_LINE);
+                    platform::interrupt();
+                }
+                _ => {}
+            }
+        }
+        print!("{}", CLEAR_LINE);
+        let _ = io::stdout().flush();
+    }))
+}
+
+#[cfg(not(target_os = "windows"))]
+extern "C" fn request_detach(_: libc::c_int) {
+    DETACH_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Lets `focus background` reach the session; only sessions with live controls act on it.
+#[cfg(not(target_os = "windows"))]
+pub fn catch_detach_signal() {
+    unsafe {
+        libc::signal(
+            libc::SIGUSR1,
+            request_detach as *const () as libc::sighandler_t,
+        );
+    }
+}
+
+/// Windows has no SIGUSR1, so only the `b` key detaches a session there.
+#[cfg(target_os = "windows")]
+pub fn catch_detach_signal() {}
+
+/// Hands the session to a background process and exits; the block and timer carry over.
+fn detach(config: &Config, timer: &Timer) {
+    if timer.is_paused() {
+        announce("[!] Resume the session before moving it to the background");
+        return;
+    }
+    let Ok(exe) = env::current_exe() else {
+        return;
+    };
+    let mut command = Command::new(exe);
+    if let Some(group) = &config.scope {
+        command.args(["--group", group]);
+    }
+    let spawned = command
+        .args(["--takeover", &process::id().to_string()])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if spawned.is_err() || !session::wait_for_handover(config) {
+        announce("[!] Could not move the session to the background");
+        return;
+    }
+    audit::record(config, "session", "moved to background");
+    print!("{}", CLEAR_LINE);
+    prompt::restore_terminal();
+    println!(
+        "{}",
+        "[+] The session continues in the background; see `focus status`"
+            .bold()
+            .green()
+    );
+    process::exit(0);
+}
+
+pub fn format_clock(seconds: u64) -> String {
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+pub fn draw_countdown(remaining: u64, paused: bool) {
+    let clock = format_clock(remaining);
+    let suffix = if paused { " (paused)" } else { "" };
+    print!(
+        "{}{}",
+        CLEAR_LINE,
+        format!("[>] {} remaining{}", clock, suffix).bold().cyan()
+    );
+    let _ = io::stdout().flush();
+}
+
+fn announce(message: &str) {
+    println!("{}{}", CLEAR_LINE, message.bold().cyan());
+}
+
+/// Returns whether the session was paused or resumed; strict sessions and spent limits refuse.
+pub fn toggle_pause(config: &Config, timer: &Timer) -> bool {
+    if timer.is_paused() {
+        // Block again before the timer resumes so the checker does not report tampering
+        util::sync_deadline(timer);
+        util::reblock_sites(config);
+        timer.resume();
+        session::record_pause(config, false);
+        audit::record(config, "session", "resumed");
+        announce("[>] Resumed: sites blocked again");
+        return true;
+    }
+
+    print!("{}", CLEAR_LINE);
+    if config.strict {
+        announce("[!] Strict session: pausing is disabled");
+        return false;
+    }
+    if !limits::try_consume(config, Action::Pause) {
+        audit::record(config, "session", "pause refused");
+        return false;
+    }
+    timer.pause();
+    session::record_pause(config, true);
+    let _ = util::unblock_sites(config);
+    audit::record(config, "session", "paused");
+    announce("[>] Paused: sites unblocked until you press p again");
+    true
+}
+
+fn show_status(config: &Config, timer: &Timer) {
+    let counters = limits::load_counters(config);
+    let state = session::load_state(config);
+    print!("{}", CLEAR_LINE);
+    println!(
+        "{}",
+        format!(
+            "[+] {} minutes left{}",
+            timer.remaining().as_secs().div_ceil(60),
+            if timer.is_paused() { ", paused" } else { "" }
+        )
+        .bold()
+        .green()
+    );
+    if let Some(tag) = state.and_then(|state| state.tag) {
+        println!("{}", format!("[+] Tag: {}", tag).bold().green());
+    }
+    println!(
+        "{}",
+        format!(
+            "[+] {} sites blocked, {} stops and {} pauses used today",
+            util::effective_sites(config).len(),
+            counters.stops,
+            counters.pauses
+        )
+        .bold()
+        .green()
+    );
+}
@@ -0,0 +1,92 @@
+use crate::{
+    notify::Event,
+    util::{self, Config},
+};
+use serde::{Deserialize, Serialize};
+use std::process::{Command, Stdio};
+
+const OBJECT_PATH: &str = "/io/github/gabriel_sparkes/Focus";
+const INTERFACE: &str = "io.github.gabriel_sparkes.Focus";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Bus {
+    #[default]
+    System,
+    Session,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbusConfig {
+    #[serde(default = "util::default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub bus: Bus,
+}
+
+impl Default for DbusConfig {
+    fn default() -> Self {
+        DbusConfig {
+            enabled: true,
+            bus: Bus::System,
+        }
+    }
+}
+
+fn signal(event: &Event) -> (&'static str, Vec<String>) {
+    match event {
+        Event::SessionStarted { minutes, .. } => {
+            ("SessionStarted", vec![format!("uint64:{}", minutes)])
+        }
+        Event::Halfway { minutes } => (
+            "MilestoneReached",
+            vec![
+                String::from("string:halfway"),
+                format!("uint64:{}", minutes),
+            ],
+        ),
+        Event::WrappingUp { minutes } => (
+            "MilestoneReached",
+            vec![
+                String::from("string:wrapping-up"),
+                format!("uint64:{}", minutes),
+            ],
+        ),
+        Event::SessionEnded { .. } => ("SessionEnded", vec![String::from("string:completed")]),
+        Event::SessionStopped { .. } => ("SessionEnded", vec![String::from("string:stopped")]),
+        Event::TamperDetected => ("TamperDetected", Vec::new()),
+        Event::WouldBlock { site } => ("WouldBlock", vec![format!("string:{}", site)]),
+        Event::MicroBreak { seconds } => ("MicroBreak", vec![format!("uint64:{}", seconds)]),
+        Event::Summary {
+            weekly,
+            focused_minutes,
+            sessions,
+            ..
+        } => (
+            "Summary",
+            vec![
+                format!("string:{}", if *weekly { "weekly" } else { "daily" }),
+                format!("uint64:{}", focused_minutes),
+                format!("uint64:{}", sessions),
+            ],
+        ),
+    }
+}
+
+/// Broadcasts the event as a signal; silently does nothing when no bus is reachable.
+pub fn emit(config: &Config, event: &Event) {
+    let (member, arguments) = signal(event);
+    let bus = match config.dbus.bus {
+        Bus::System => "--system",
+        Bus::Session => "--session",
+    };
+    let _ = Command::new("dbus-send")
+        .arg(bus)
+        .arg("--type=signal")
+        .arg(OBJECT_PATH)
+        .arg(format!("{}.{}", INTERFACE, member))
+        .args(arguments)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
@@ -0,0 +1,223 @@
+use crate::{
+    audit, companion,
+    notify::{Event, Notifier},
+    platform,
+    session::Timer,
+    user::SessionUser,
+    util::{self, Config},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Read,
+    process::{Command, Stdio},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DesktopConfig {
+    #[serde(default)]
+    pub actions: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Choice {
+    Extend,
+    End,
+}
+
+impl Choice {
+    pub fn from_action(action: &str) -> Option<Self> {
+        match action.trim() {
+            "extend" => Some(Choice::Extend),
+            "end" => Some(Choice::End),
+            _ => None,
+        }
+    }
+
+    pub fn action(&self) -> &'static str {
+        match self {
+            Choice::Extend => "extend",
+            Choice::End => "end",
+        }
+    }
+}
+
+/// Runs a desktop program as the user who invoked sudo so it reaches their session bus.
+fn user_command(program: &str) -> Command {
+    SessionUser::from_sudo()
+        .map(|user| user.command(program))
+        .unwrap_or_else(|| Command::new(program))
+}
+
+/// Plain desktop notifications through the platform's own notification service.
+pub struct DesktopNotifier;
+
+impl Notifier for DesktopNotifier {
+    fn notify(&self, event: &Event) {
+        let title = event.title().to_string();
+        let message = event.message();
+        if !companion::send(&companion::Task::Notify {
+            title: title.clone(),
+            message: message.clone(),
+        }) {
+            show(&title, &message);
+        }
+    }
+}
+
+pub fn show(title: &str, message: &str) {
+    let _ = native_command(title, message)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
+/// Whether a notification service owns its name on the user's session bus.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn has_notification_daemon() -> bool {
+    user_command("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus.NameHasOwner",
+            "string:org.freedesktop.Notifications",
+        ])
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("boolean true"))
+}
+
+/// Notification Center and toasts are part of the OS.
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+pub fn has_notification_daemon() -> bool {
+    true
+}
+
+/// libnotify through `notify-send`.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn native_command(title: &str, message: &str) -> Command {
+    let mut command = user_command("notify-send");
+    command.arg("--app-name=focus").arg(title).arg(message);
+    command
+}
+
+/// Notification Center; the text goes in as arguments so nothing needs AppleScript escaping.
+#[cfg(target_os = "macos")]
+fn native_command(title: &str, message: &str) -> Command {
+    let mut command = user_command("osascript");
+    command
+        .args([
+            "-e",
+            "on run argv",
+            "-e",
+            "display notification (item 2 of argv) with title (item 1 of argv)",
+            "-e",
+            "end run",
+        ])
+        .arg(title)
+        .arg(message);
+    command
+}
+
+/// A toast shown under PowerShell's app ID, since unpackaged programs have none of their own.
+#[cfg(target_os = "windows")]
+fn native_command(title: &str, message: &str) -> Command {
+    const TOAST: &str = "\
+        $manager = [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime]; \
+        $template = $manager::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+        $text = $template.GetElementsByTagName('text'); \
+        [void]$text.Item(0).AppendChild($template.CreateTextNode($env:FOCUS_TITLE)); \
+        [void]$text.Item(1).AppendChild($template.CreateTextNode($env:FOCUS_MESSAGE)); \
+        $manager::CreateToastNotifier('{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\\WindowsPowerShell\\v1.0\\powershell.exe').Show([Windows.UI.Notifications.ToastNotification]::new($template))";
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-NonInteractive", "-Command", TOAST])
+        .env("FOCUS_TITLE", title)
+        .env("FOCUS_MESSAGE", message);
+    command
+}
+
+/// Shows a notification with buttons and returns the one clicked before the timeout.
+pub fn ask(
+    config: &Config,
+    title: &str,
+    message: &str,
+    allow_end: bool,
+    timeout: Duration,
+) -> Option<Choice> {
+    let extend_minutes = config.extend_prompt_minutes;
+    companion::ask(title, message, extend_minutes, allow_end, timeout)
+        .unwrap_or_else(|| show_ask(title, message, extend_minutes, allow_end, timeout))
+}
+
+pub fn show_ask(
+    title: &str,
+    message: &str,
+    extend_minutes: u64,
+    allow_end: bool,
+    timeout: Duration,
+) -> Option<Choice> {
+    // Only libnotify reports which button was clicked back to the command line
+    if cfg!(any(target_os = "macos", target_os = "windows")) {
+        show(title, message);
+        return None;
+    }
+    let mut command = user_command("notify-send");
+    command
+        .arg("--app-name=focus")
+        .arg("--wait")
+        .arg(format!("--expire-time={}", timeout.as_millis()))
+        .arg(format!("--action=extend=Extend {} min", extend_minutes));
+    if allow_end {
+        command.arg("--action=end=End now");
+    }
+    let mut child = command
+        .arg(title)
+        .arg(message)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if Instant::now() < deadline => thread::sleep(POLL_INTERVAL),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    }
+    let mut output = String::new();
+    child.stdout.take()?.read_to_string(&mut output).ok()?;
+    Choice::from_action(&output)
+}
+
+/// Offers extend/end buttons for a milestone without blocking the session.
+pub fn offer_actions(config: Arc<Config>, timer: Arc<Timer>, title: String, message: String) {
+    thread::spawn(move || {
+        let timeout = timer.remaining();
+        match ask(&config, &title, &message, true, timeout) {
+            Some(Choice::Extend) => {
+                util::extend_session(
+                    &config,
+                    &timer,
+                    Duration::from_mins(config.extend_prompt_minutes),
+                );
+                audit::record(&config, "session", "extended");
+            }
+            // Goes through the Ctrl-C path so admin and rate-limit checks still apply
+            Some(Choice::End) => platform::interrupt(),
+            None => {}
+        }
+    });
+}
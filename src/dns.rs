@@ -0,0 +1,352 @@
+use crate::{
+    certificate, notify, onboarding, stats,
+    util::{self, Config},
+};
+use colored::Colorize;
+use rustls::{ClientConnection, StreamOwned, pki_types::ServerName};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+const MAX_PACKET: usize = 4096;
+const HEADER_LENGTH: usize = 12;
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+const ANSWER_TTL: u32 = 60;
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(1);
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(3);
+/// How often the interceptors pick up sites added live, passes and the soft start ending
+pub const SITES_REFRESH: Duration = Duration::from_secs(1);
+const FORWARD_WORKERS: usize = 8;
+const FORWARD_QUEUE: usize = 64;
+const DNS_MESSAGE_TYPE: &str = "application/dns-message";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UpstreamProtocol {
+    #[default]
+    Udp,
+    Tls,
+    Https,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DnsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_listen")]
+    pub listen: String,
+    #[serde(default = "default_upstream")]
+    pub upstream: String,
+    #[serde(default)]
+    pub upstream_protocol: UpstreamProtocol,
+    #[serde(default)]
+    pub tls_server_name: Option<String>,
+    #[serde(default = "util::default_true")]
+    pub log_queries: bool,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        DnsConfig {
+            enabled: false,
+            listen: default_listen(),
+            upstream: default_upstream(),
+            upstream_protocol: UpstreamProtocol::Udp,
+            tls_server_name: None,
+            log_queries: true,
+        }
+    }
+}
+
+fn default_listen() -> String {
+    String::from("127.0.0.1:53")
+}
+
+fn default_upstream() -> String {
+    String::from("1.1.1.1:53")
+}
+
+struct Question {
+    name: String,
+    qtype: u16,
+    end: usize,
+}
+
+pub fn start_server(config: Arc<Config>, running: Arc<AtomicBool>) {
+    let socket = match UdpSocket::bind(&config.dns.listen) {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                format!(
+                    "[!] Failed to start DNS sinkhole on {}: {}",
+                    config.dns.listen, e
+                )
+                .bold()
+                .red()
+            );
+            return;
+        }
+    };
+    let _ = socket.set_read_timeout(Some(RECEIVE_TIMEOUT));
+    println!(
+        "{}",
+        format!("[>] DNS sinkhole listening on {}", config.dns.listen)
+            .bold()
+            .cyan()
+    );
+
+    let (queries, receiver) = mpsc::sync_channel::<(Vec<u8>, SocketAddr)>(FORWARD_QUEUE);
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..FORWARD_WORKERS {
+        let Ok(reply_socket) = socket.try_clone() else {
+            break;
+        };
+        let receiver = Arc::clone(&receiver);
+        let config = Arc::clone(&config);
+        // Ends once the listener drops the sender
+        thread::spawn(move || {
+            loop {
+                let Ok((query, client)) = receiver.lock().unwrap().recv() else {
+                    return;
+                };
+                if let Some(response) = forward(&config.dns, &query) {
+                    let _ = reply_socket.send_to(&response, client);
+                }
+            }
+        });
+    }
+
+    thread::spawn(move || {
+        // Refreshed as sites are added live, passes handed out and the soft start ends
+        let mut sites = util::blocked_sites(&config);
+        let mut refreshed = Instant::now();
+        let mut buffer = [0u8; MAX_PACKET];
+
+        while running.load(Ordering::SeqCst) {
+            if refreshed.elapsed() >= SITES_REFRESH {
+                sites = util::blocked_sites(&config);
+                refreshed = Instant::now();
+            }
+            let Ok((length, client)) = socket.recv_from(&mut buffer) else {
+                continue;
+            };
+            let query = buffer[..length].to_vec();
+            let Some(question) = parse_question(&query) else {
+                continue;
+            };
+
+            let blocked = is_blocked(&sites, &question.name);
+            // Onboarding needs the visits for its report
+            if config.dns.log_queries || config.warn_only {
+                stats::record_query(&config, &question.name, blocked);
+            }
+
+            if blocked && config.warn_only {
+                onboarding::notice(&config, &question.name);
+            }
+            if blocked && !config.warn_only {
+                let response = build_blocked_response(&query, &question, &config.block_ip);
+                let _ = socket.send_to(&response, client);
+            } else {
+                // With every worker waiting on a slow upstream the query is dropped, and the
+                // client asks again
+                let _ = queries.try_send((query, client));
+            }
+        }
+    });
+}
+
+pub fn is_blocked(sites: &[String], name: &str) -> bool {
+    let name = name.trim_end_matches('.').to_lowercase();
+    sites.iter().any(|site| {
+        let site = site.to_lowercase();
+        name == site || name.ends_with(&format!(".{}", site))
+    })
+}
+
+fn forward(config: &DnsConfig, query: &[u8]) -> Option<Vec<u8>> {
+    match config.upstream_protocol {
+        UpstreamProtocol::Udp => forward_udp(&config.upstream, query),
+        UpstreamProtocol::Tls => forward_tls(config, query),
+        UpstreamProtocol::Https => forward_https(&config.upstream, query),
+    }
+}
+
+fn forward_udp(upstream: &str, query: &[u8]) -> Option<Vec<u8>> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.set_read_timeout(Some(UPSTREAM_TIMEOUT)).ok()?;
+    socket.send_to(query, upstream).ok()?;
+
+    let mut buffer = [0u8; MAX_PACKET];
+    let (length, _) = socket.recv_from(&mut buffer).ok()?;
+    Some(buffer[..length].to_vec())
+}
+
+fn forward_tls(config: &DnsConfig, query: &[u8]) -> Option<Vec<u8>> {
+    let address: SocketAddr = config.upstream.parse().ok()?;
+    let server_name = config
+        .tls_server_name
+        .clone()
+        .unwrap_or_else(|| address.ip().to_string());
+    let server_name = ServerName::try_from(server_name).ok()?;
+
+    let connection = ClientConnection::new(certificate::client_config(), server_name).ok()?;
+    let stream = TcpStream::connect_timeout(&address, UPSTREAM_TIMEOUT).ok()?;
+    stream.set_read_timeout(Some(UPSTREAM_TIMEOUT)).ok()?;
+    let mut tls = StreamOwned::new(connection, stream);
+
+    let mut message = (query.len() as u16).to_be_bytes().to_vec();
+    message.extend_from_slice(query);
+    tls.write_all(&message).ok()?;
+
+    let mut length = [0u8; 2];
+    tls.read_exact(&mut length).ok()?;
+    let mut response = vec![0u8; u16::from_be_bytes(length) as usize];
+    tls.read_exact(&mut response).ok()?;
+    Some(response)
+}
+
+fn forward_https(url: &str, query: &[u8]) -> Option<Vec<u8>> {
+    notify::http_agent_with_timeout(UPSTREAM_TIMEOUT)
+        .post(url)
+        .header("Content-Type", DNS_MESSAGE_TYPE)
+        .header("Accept", DNS_MESSAGE_TYPE)
+        .send(query)
+        .and_then(|mut response| response.body_mut().read_to_vec())
+        .ok()
+}
+
+/// Resolves A records through the configured upstream, bypassing the hosts file.
+pub fn resolve_ipv4(config: &DnsConfig, name: &str) -> Vec<Ipv4Addr> {
+    resolve(config, name, TYPE_A)
+        .into_iter()
+        .filter_map(|data| Some(Ipv4Addr::from(<[u8; 4]>::try_from(data).ok()?)))
+        .collect()
+}
+
+/// Like `resolve_ipv4`, for AAAA records.
+pub fn resolve_ipv6(config: &DnsConfig, name: &str) -> Vec<Ipv6Addr> {
+    resolve(config, name, TYPE_AAAA)
+        .into_iter()
+        .filter_map(|data| Some(Ipv6Addr::from(<[u8; 16]>::try_from(data).ok()?)))
+        .collect()
+}
+
+fn resolve(config: &DnsConfig, name: &str, qtype: u16) -> Vec<Vec<u8>> {
+    let mut query = vec![0x46, 0x4f, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in name.trim_end_matches('.').split('.') {
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&qtype.to_be_bytes());
+    query.extend_from_slice(&CLASS_IN.to_be_bytes());
+
+    forward(config, &query)
+        .and_then(|response| parse_answers(&response, qtype))
+        .unwrap_or_default()
+}
+
+/// The data of every answer of type `rtype`, skipping CNAMEs and the like.
+fn parse_answers(packet: &[u8], rtype: u16) -> Option<Vec<Vec<u8>>> {
+    let question = parse_question(packet)?;
+    let answers = u16::from_be_bytes([*packet.get(6)?, *packet.get(7)?]);
+    let mut position = question.end;
+    let mut records = Vec::new();
+
+    for _ in 0..answers {
+        position = skip_name(packet, position)?;
+        let header = packet.get(position..position + 10)?;
+        let answer_type = u16::from_be_bytes([header[0], header[1]]);
+        let length = u16::from_be_bytes([header[8], header[9]]) as usize;
+        position += 10;
+        let data = packet.get(position..position + length)?;
+        if answer_type == rtype {
+            records.push(data.to_vec());
+        }
+        position += length;
+    }
+    Some(records)
+}
+
+fn skip_name(packet: &[u8], mut position: usize) -> Option<usize> {
+    loop {
+        let length = *packet.get(position)?;
+        if length & 0xC0 == 0xC0 {
+            return Some(position + 2);
+        }
+        position += 1 + length as usize;
+        if length == 0 {
+            return Some(position);
+        }
+    }
+}
+
+fn parse_question(packet: &[u8]) -> Option<Question> {
+    if packet.len() < HEADER_LENGTH || u16::from_be_bytes([packet[4], packet[5]]) == 0 {
+        return None;
+    }
+
+    let mut labels = Vec::new();
+    let mut position = HEADER_LENGTH;
+    loop {
+        let length = *packet.get(position)? as usize;
+        position += 1;
+        if length == 0 {
+            break;
+        }
+        if length & 0xC0 != 0 {
+            return None;
+        }
+        let label = packet.get(position..position + length)?;
+        labels.push(String::from_utf8_lossy(label).to_string());
+        position += length;
+    }
+
+    let qtype = u16::from_be_bytes([*packet.get(position)?, *packet.get(position + 1)?]);
+    Some(Question {
+        name: labels.join("."),
+        qtype,
+        end: position + 4,
+    })
+}
+
+fn build_blocked_response(query: &[u8], question: &Question, block_ip: &str) -> Vec<u8> {
+    let address = block_ip.parse::<IpAddr>().ok();
+    let answer = match (question.qtype, address) {
+        (TYPE_A, Some(IpAddr::V4(ip))) => Some((TYPE_A, ip.octets().to_vec())),
+        (TYPE_AAAA, Some(IpAddr::V6(ip))) => Some((TYPE_AAAA, ip.octets().to_vec())),
+        (TYPE_AAAA, Some(IpAddr::V4(ip))) if ip.is_unspecified() => {
+            Some((TYPE_AAAA, [0u8; 16].to_vec()))
+        }
+        _ => None,
+    };
+
+    let mut response = query[..question.end.min(query.len())].to_vec();
+    // QR and RA set, opcode and RD copied from the query, RCODE 0
+    response[2] = 0x80 | (query[2] & 0x79);
+    response[3] = 0x80;
+    response[6..12].copy_from_slice(&[0, answer.is_some() as u8, 0, 0, 0, 0]);
+
+    if let Some((rtype, data)) = answer {
+        response.extend_from_slice(&[0xC0, HEADER_LENGTH as u8]);
+        response.extend_from_slice(&rtype.to_be_bytes());
+        response.extend_from_slice(&CLASS_IN.to_be_bytes());
+        response.extend_from_slice(&ANSWER_TTL.to_be_bytes());
+        response.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        response.extend_from_slice(&data);
+    }
+    response
+}
@@ -0,0 +1,370 @@
+use crate::util::Config;
+#[cfg(target_os = "linux")]
+use crate::{dns, util};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, atomic::AtomicBool};
+#[cfg(target_os = "linux")]
+use std::{
+    collections::HashSet,
+    ffi::CString,
+    io,
+    net::IpAddr,
+    sync::atomic::Ordering,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(target_os = "linux")]
+const BPF_MAP_CREATE: libc::c_long = 0;
+#[cfg(target_os = "linux")]
+const BPF_MAP_UPDATE_ELEM: libc::c_long = 2;
+#[cfg(target_os = "linux")]
+const BPF_MAP_DELETE_ELEM: libc::c_long = 3;
+#[cfg(target_os = "linux")]
+const BPF_PROG_LOAD: libc::c_long = 5;
+#[cfg(target_os = "linux")]
+const BPF_LINK_CREATE: libc::c_long = 28;
+#[cfg(target_os = "linux")]
+const BPF_MAP_TYPE_HASH: u32 = 1;
+#[cfg(target_os = "linux")]
+const BPF_PROG_TYPE_CGROUP_SKB: u32 = 8;
+#[cfg(target_os = "linux")]
+const BPF_CGROUP_INET_EGRESS: u32 = 1;
+#[cfg(target_os = "linux")]
+const BPF_PSEUDO_MAP_FD: u8 = 1;
+#[cfg(target_os = "linux")]
+const BPF_FUNC_MAP_LOOKUP_ELEM: i32 = 1;
+#[cfg(target_os = "linux")]
+const BPF_FUNC_SKB_LOAD_BYTES: i32 = 26;
+#[cfg(target_os = "linux")]
+const ETH_P_IP: u16 = 0x0800;
+#[cfg(target_os = "linux")]
+const ETH_P_IPV6: u16 = 0x86DD;
+#[cfg(target_os = "linux")]
+const MAX_ADDRESSES: u32 = 16384;
+#[cfg(target_os = "linux")]
+const LOG_SIZE: usize = 64 * 1024;
+#[cfg(target_os = "linux")]
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EbpfConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_cgroup")]
+    pub cgroup: String,
+    #[serde(default = "default_resolve_interval")]
+    pub resolve_interval: u64,
+}
+
+impl Default for EbpfConfig {
+    fn default() -> Self {
+        EbpfConfig {
+            enabled: false,
+            cgroup: default_cgroup(),
+            resolve_interval: default_resolve_interval(),
+        }
+    }
+}
+
+fn default_cgroup() -> String {
+    String::from("/sys/fs/cgroup")
+}
+
+fn default_resolve_interval() -> u64 {
+    300
+}
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Instruction {
+    code: u8,
+    registers: u8,
+    offset: i16,
+    immediate: i32,
+}
+
+#[cfg(target_os = "linux")]
+fn instruction(code: u8, dst: u8, src: u8, offset: i16, immediate: i32) -> Instruction {
+    Instruction {
+        code,
+        registers: (src << 4) | dst,
+        offset,
+        immediate,
+    }
+}
+
+/// Drops egress packets whose destination address is a key in the map for its IP version.
+#[cfg(target_os = "linux")]
+fn program(ipv4_map_fd: i32, ipv6_map_fd: i32) -> Vec<Instruction> {
+    const MOV64_REG: u8 = 0xbf;
+    const MOV64_IMM: u8 = 0xb7;
+    const LDX_WORD: u8 = 0x61;
+    const JEQ_IMM: u8 = 0x15;
+    const EXIT: u8 = 0x95;
+
+    let mut instructions = vec![
+        instruction(MOV64_REG, 6, 1, 0, 0),
+        // skb->protocol, stored in network byte order
+        instruction(LDX_WORD, 2, 6, 16, 0),
+        instruction(JEQ_IMM, 2, 0, 3, ETH_P_IP.to_be() as i32),
+        instruction(JEQ_IMM, 2, 0, 19, ETH_P_IPV6.to_be() as i32),
+        instruction(MOV64_IMM, 0, 0, 0, 1),
+        instruction(EXIT, 0, 0, 0, 0),
+    ];
+    // The destination address sits at offset 16 of the IPv4 header and 24 of the IPv6 one
+    instructions.extend(lookup(16, 4, ipv4_map_fd));
+    instructions.extend(lookup(24, 16, ipv6_map_fd));
+    instructions
+}
+
+/// Copies `length` bytes at `offset` into the packet to the stack and drops the packet if
+/// they are a key in the map; 17 instructions, so the jumps above can skip over it.
+#[cfg(target_os = "linux")]
+fn lookup(offset: i32, length: i32, map_fd: i32) -> [Instruction; 17] {
+    const MOV64_REG: u8 = 0xbf;
+    const MOV64_IMM: u8 = 0xb7;
+    const ADD64_IMM: u8 = 0x07;
+    const LD_DWORD_IMM: u8 = 0x18;
+    const JEQ_IMM: u8 = 0x15;
+    const JNE_IMM: u8 = 0x55;
+    const CALL: u8 = 0x85;
+    const EXIT: u8 = 0x95;
+
+    [
+        instruction(MOV64_IMM, 2, 0, 0, offset),
+        instruction(MOV64_REG, 3, 10, 0, 0),
+        instruction(ADD64_IMM, 3, 0, 0, -length),
+        instruction(MOV64_IMM, 4, 0, 0, length),
+        instruction(MOV64_REG, 1, 6, 0, 0),
+        instruction(CALL, 0, 0, 0, BPF_FUNC_SKB_LOAD_BYTES),
+        instruction(JNE_IMM, 0, 0, 8, 0),
+        instruction(LD_DWORD_IMM, 1, BPF_PSEUDO_MAP_FD, 0, map_fd),
+        instruction(0, 0, 0, 0, 0),
+        instruction(MOV64_REG, 2, 10, 0, 0),
+        instruction(ADD64_IMM, 2, 0, 0, -length),
+        instruction(CALL, 0, 0, 0, BPF_FUNC_MAP_LOOKUP_ELEM),
+        instruction(JEQ_IMM, 0, 0, 2, 0),
+        instruction(MOV64_IMM, 0, 0, 0, 0),
+        instruction(EXIT, 0, 0, 0, 0),
+        instruction(MOV64_IMM, 0, 0, 0, 1),
+        instruction(EXIT, 0, 0, 0, 0),
+    ]
+}
+
+#[cfg(target_os = "linux")]
+struct Attributes([u8; 128]);
+
+#[cfg(target_os = "linux")]
+impl Attributes {
+    fn new() -> Self {
+        Attributes([0; 128])
+    }
+
+    fn u32(mut self, offset: usize, value: u32) -> Self {
+        self.0[offset..offset + 4].copy_from_slice(&value.to_ne_bytes());
+        self
+    }
+
+    fn u64(mut self, offset: usize, value: u64) -> Self {
+        self.0[offset..offset + 8].copy_from_slice(&value.to_ne_bytes());
+        self
+    }
+
+    fn call(&self, command: libc::c_long) -> io::Result<i32> {
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_bpf,
+                command,
+                self.0.as_ptr(),
+                self.0.len() as libc::c_uint,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(result as i32)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn create_map(key_size: u32) -> io::Result<i32> {
+    Attributes::new()
+        .u32(0, BPF_MAP_TYPE_HASH)
+        .u32(4, key_size)
+        .u32(8, 1)
+        .u32(12, MAX_ADDRESSES)
+        .call(BPF_MAP_CREATE)
+}
+
+#[cfg(target_os = "linux")]
+struct Filter {
+    ipv4_map_fd: i32,
+    ipv6_map_fd: i32,
+    fds: Vec<i32>,
+}
+
+#[cfg(target_os = "linux")]
+impl Filter {
+    fn load(cgroup: &str) -> Result<Self, String> {
+        let ipv4_map_fd = create_map(4).map_err(|e| format!("map creation failed: {}", e))?;
+        let mut filter = Filter {
+            ipv4_map_fd,
+            ipv6_map_fd: -1,
+            fds: vec![ipv4_map_fd],
+        };
+        filter.ipv6_map_fd = create_map(16).map_err(|e| format!("map creation failed: {}", e))?;
+        filter.fds.push(filter.ipv6_map_fd);
+
+        let instructions = program(filter.ipv4_map_fd, filter.ipv6_map_fd);
+        let license = CString::new("GPL").expect("[!] Invalid license string");
+        let mut log = vec![0u8; LOG_SIZE];
+        let mut name = [0u8; 16];
+        name[..5].copy_from_slice(b"focus");
+        let mut attributes = Attributes::new()
+            .u32(0, BPF_PROG_TYPE_CGROUP_SKB)
+            .u32(4, instructions.len() as u32)
+            .u64(8, instructions.as_ptr() as u64)
+            .u64(16, license.as_ptr() as u64)
+            .u32(24, 1)
+            .u32(28, LOG_SIZE as u32)
+            .u64(32, log.as_mut_ptr() as u64)
+            .u32(68, BPF_CGROUP_INET_EGRESS);
+        attributes.0[48..64].copy_from_slice(&name);
+        let program_fd = attributes.call(BPF_PROG_LOAD).map_err(|e| {
+            let log = String::from_utf8_lossy(&log);
+            format!(
+                "program load failed: {} {}",
+                e,
+                log.trim_end_matches('\0').trim()
+            )
+        })?;
+        filter.fds.push(program_fd);
+
+        let path = CString::new(cgroup).map_err(|e| e.to_string())?;
+        let cgroup_fd = unsafe { libc::open(path.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY) };
+        if cgroup_fd < 0 {
+            return Err(format!(
+                "could not open cgroup {}: {}",
+                cgroup,
+                io::Error::last_os_error()
+            ));
+        }
+        filter.fds.push(cgroup_fd);
+
+        // The link detaches automatically when this process exits
+        let link_fd = Attributes::new()
+            .u32(0, program_fd as u32)
+            .u32(4, cgroup_fd as u32)
+            .u32(8, BPF_CGROUP_INET_EGRESS)
+            .call(BPF_LINK_CREATE)
+            .map_err(|e| format!("attaching to {} failed: {}", cgroup, e))?;
+        filter.fds.push(link_fd);
+        Ok(filter)
+    }
+
+    fn map_key(&self, address: IpAddr) -> (i32, Vec<u8>) {
+        match address {
+            IpAddr::V4(address) => (self.ipv4_map_fd, address.octets().to_vec()),
+            IpAddr::V6(address) => (self.ipv6_map_fd, address.octets().to_vec()),
+        }
+    }
+
+    fn insert(&self, address: IpAddr) {
+        let (map_fd, key) = self.map_key(address);
+        let value = 1u8;
+        let _ = Attributes::new()
+            .u32(0, map_fd as u32)
+            .u64(8, key.as_ptr() as u64)
+            .u64(16, &value as *const u8 as u64)
+            .call(BPF_MAP_UPDATE_ELEM);
+    }
+
+    fn remove(&self, address: IpAddr) {
+        let (map_fd, key) = self.map_key(address);
+        let _ = Attributes::new()
+            .u32(0, map_fd as u32)
+            .u64(8, key.as_ptr() as u64)
+            .call(BPF_MAP_DELETE_ELEM);
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for Filter {
+    fn drop(&mut self) {
+        for fd in self.fds.iter().rev() {
+            unsafe { libc::close(*fd) };
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn start_filter(config: Arc<Config>, running: Arc<AtomicBool>) {
+    let filter = match Filter::load(&config.ebpf.cgroup) {
+        Ok(filter) => filter,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                format!("[!] Failed to load eBPF filter: {}", e)
+                    .bold()
+                    .red()
+            );
+            return;
+        }
+    };
+    println!(
+        "{}",
+        format!("[>] eBPF filter attached to {}", config.ebpf.cgroup)
+            .bold()
+            .cyan()
+    );
+
+    thread::spawn(move || {
+        let mut sites = util::blocked_sites(&config);
+        let mut refreshed = Instant::now();
+        let interval = Duration::from_secs(config.ebpf.resolve_interval.max(1));
+        let mut blocked: HashSet<IpAddr> = HashSet::new();
+        let mut last_resolve: Option<Instant> = None;
+
+        while running.load(Ordering::SeqCst) {
+            if refreshed.elapsed() >= dns::SITES_REFRESH {
+                let current = util::blocked_sites(&config);
+                // A changed list is resolved now rather than at the next interval
+                if current != sites {
+                    sites = current;
+                    last_resolve = None;
+                }
+                refreshed = Instant::now();
+            }
+            if last_resolve.is_none_or(|last| last.elapsed() >= interval) {
+                let resolved: HashSet<IpAddr> = sites
+                    .iter()
+                    .flat_map(|site| {
+                        let ipv4 = dns::resolve_ipv4(&config.dns, site).into_iter();
+                        let ipv6 = dns::resolve_ipv6(&config.dns, site).into_iter();
+                        ipv4.map(IpAddr::V4).chain(ipv6.map(IpAddr::V6))
+                    })
+                    .filter(|address| !address.is_unspecified() && !address.is_loopback())
+                    .collect();
+                for address in resolved.difference(&blocked) {
+                    filter.insert(*address);
+                }
+                for address in blocked.difference(&resolved) {
+                    filter.remove(*address);
+                }
+                blocked = resolved;
+                last_resolve = Some(Instant::now());
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        drop(filter);
+    });
+}
+
+/// cgroup socket filters are a Linux feature; other systems block with the hosts file alone.
+#[cfg(not(target_os = "linux"))]
+pub fn start_filter(_config: Arc<Config>, _running: Arc<AtomicBool>) {
+    eprintln!("{}", "[!] The eBPF filter only works on Linux".bold().red());
+}
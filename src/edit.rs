@@ -0,0 +1,173 @@
+use crate::{
+    admin, hosts,
+    privileged::CONFIG_PATH,
+    schedule,
+    util::{self, Config},
+};
+use colored::Colorize;
+use std::{
+    env, fs,
+    io::{self, Write},
+    process::Command,
+};
+
+/// Opens the config in `$VISUAL`/`$EDITOR` and only saves it once it loads cleanly,
+/// so a typo can't leave the next run unable to start.
+pub fn edit_config() -> &'static str {
+    let original = match fs::read_to_string(CONFIG_PATH) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                format!("[!] Could not read {}: {}", CONFIG_PATH, e)
+                    .bold()
+                    .red()
+            );
+            return "failed";
+        }
+    };
+    // A broken config has no admin password to check yet; that is what this is for
+    if let Ok(config) = toml::from_str::<Config>(&original)
+        && !admin::require_admin(&config, "Editing the config")
+    {
+        return "denied";
+    }
+
+    let draft = format!("{}.edit", CONFIG_PATH);
+    if let Err(e) = util::write_private(&draft, &original) {
+        eprintln!(
+            "{}",
+            format!("[!] Could not create {}: {}", draft, e)
+                .bold()
+                .red()
+        );
+        return "failed";
+    }
+
+    let outcome = loop {
+        if !open_editor(&draft) {
+            break "failed";
+        }
+        let Ok(edited) = fs::read_to_string(&draft) else {
+            break "failed";
+        };
+        if edited == original {
+            println!("{}", "[>] No changes".bold().cyan());
+            break "unchanged";
+        }
+        match validate(&edited) {
+            Ok(()) => match save(&edited) {
+                Ok(()) => {
+                    println!("{}", "[+] Config saved".bold().green());
+                    break "ok";
+                }
+                Err(e) => {
+                    eprintln!(
+                        "{}",
+                        format!("[!] Could not write {}: {}", CONFIG_PATH, e)
+                            .bold()
+                            .red()
+                    );
+                    break "failed";
+                }
+            },
+            Err(e) => {
+                eprintln!("{}", format!("[!] Invalid config: {}", e).bold().red());
+                if !ask_again() {
+                    println!(
+                        "{}",
+                        "[>] Discarded your changes; the config is unchanged"
+                            .bold()
+                            .cyan()
+                    );
+                    break "invalid";
+                }
+            }
+        }
+    };
+    let _ = fs::remove_file(&draft);
+    outcome
+}
+
+/// Runs the editor through the shell so values like `code --wait` work.
+fn open_editor(path: &str) -> bool {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| String::from("vi"));
+    match Command::new("sh")
+        .args(["-c", &format!("{} \"$1\"", editor), "sh", path])
+        .status()
+    {
+        Ok(status) if status.success() => true,
+        Ok(status) => {
+            eprintln!(
+                "{}",
+                format!("[!] {} exited with {}", editor, status)
+                    .bold()
+                    .red()
+            );
+            false
+        }
+        Err(e) => {
+            eprintln!(
+                "{}",
+                format!("[!] Could not run {}: {}", editor, e).bold().red()
+            );
+            false
+        }
+    }
+}
+
+/// Writes the edited config, announced first so a strict session's guard keeps it.
+fn save(content: &str) -> io::Result<()> {
+    if let Ok(config) = toml::from_str::<Config>(content) {
+        util::announce_config(&config, content);
+    }
+    fs::write(CONFIG_PATH, content)
+}
+
+/// Checks what serde can't: settings that only fail once a session uses them.
+fn validate(content: &str) -> Result<(), String> {
+    let config: Config = toml::from_str(content).map_err(|e| e.to_string())?;
+    if !config.markers.is_valid() {
+        return Err(String::from(
+            "markers must be distinct single-line comments starting with #",
+        ));
+    }
+    if let Some(zone) = &config.timezone {
+        schedule::parse_zone(zone)?;
+    }
+    schedule::parse_time(&config.summary.time)?;
+    if let Some(group) = config
+        .groups
+        .keys()
+        .find(|group| !hosts::is_valid_scope(group))
+    {
+        return Err(format!(
+            "group '{}' may only use letters, digits, - and _",
+            group
+        ));
+    }
+    if let Some(group) = config
+        .groups
+        .keys()
+        .find(|group| !config.markers.allows_scope(group))
+    {
+        return Err(format!(
+            "group '{}' can't be told apart from the end marker",
+            group
+        ));
+    }
+    Ok(())
+}
+
+/// Reads stdin directly: the shared prompt reader would keep consuming keys meant for the editor.
+fn ask_again() -> bool {
+    print!("{}", "[?] Edit again? [Y/n] ".bold().yellow());
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .is_ok_and(|length| length > 0)
+        && !matches!(answer.trim().to_lowercase().as_str(), "n" | "no")
+}
@@ -0,0 +1,368 @@
+use crate::{
+    apps, audit,
+    backend::{self, BlockingBackend},
+    clock, dns, ebpf, events, firewall, hosts,
+    notify::Event,
+    power, privileged, proxy, resolver,
+    session::{self, Timer},
+    sni,
+    timeline::{Due, Tick, Timeline},
+    util::{self, Config},
+};
+use clap::ValueEnum;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// Called with every session event, on the session's own thread.
+type Hook = Arc<dyn Fn(&Event) + Send + Sync>;
+
+/// How blocked sites are enforced; the hosts file is always written.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Hosts,
+    /// Reject the sites' addresses with nftables, see `blocking_backend`
+    Firewall,
+    /// Also answer DNS for blocked sites, see `[dns]` in the config
+    Dns,
+    /// Also reset TLS connections by server name, see `[sni]`
+    #[value(alias = "nftables")]
+    Sni,
+    /// Also drop packets in the kernel, see `[ebpf]`
+    Ebpf,
+    /// Also refuse blocked sites in the HTTP proxy, see `[proxy]`
+    Proxy,
+}
+
+/// Turns on exactly the given backends for this run, whatever the config says.
+pub fn apply_backends(config: &mut Config, backends: &[Backend]) {
+    config.blocking_backend = match (
+        backends.contains(&Backend::Hosts),
+        backends.contains(&Backend::Firewall),
+    ) {
+        (true, true) => BlockingBackend::Both,
+        (false, true) => BlockingBackend::Firewall,
+        _ => BlockingBackend::Hosts,
+    };
+    config.dns.enabled = backends.contains(&Backend::Dns);
+    config.sni.enabled = backends.contains(&Backend::Sni);
+    config.ebpf.enabled = backends.contains(&Backend::Ebpf);
+    config.proxy.enabled = backends.contains(&Backend::Proxy);
+}
+
+/// The backends `config` enforces with, starting with the hosts file.
+pub fn enabled_backends(config: &Config) -> Vec<Backend> {
+    [
+        (Backend::Hosts, backend::uses_hosts(config)),
+        (Backend::Firewall, backend::uses_firewall(config)),
+        (Backend::Dns, config.dns.enabled),
+        (Backend::Sni, config.sni.enabled),
+        (Backend::Ebpf, config.ebpf.enabled),
+        (Backend::Proxy, config.proxy.enabled),
+    ]
+    .into_iter()
+    .filter_map(|(backend, enabled)| enabled.then_some(backend))
+    .collect()
+}
+
+/// Sets up a session; start it with [`SessionBuilder::start`].
+pub struct SessionBuilder {
+    config: Config,
+    duration: Duration,
+    backends: Vec<Backend>,
+    tag: Option<String>,
+    hooks: Vec<Hook>,
+}
+
+impl SessionBuilder {
+    /// Starts from the given config, with its sites and duration.
+    pub fn new(config: Config) -> Self {
+        SessionBuilder {
+            duration: Duration::from_mins(config.duration),
+            config,
+            backends: vec![Backend::Hosts],
+            tag: None,
+            hooks: Vec::new(),
+        }
+    }
+
+    /// Starts from the installed `config.toml`, as the CLI does.
+    pub fn load() -> Result<Self, String> {
+        // `util::load_config` panics on a missing config; a library returns the error instead
+        let content = fs::read_to_string(privileged::CONFIG_PATH)
+            .map_err(|e| format!("Could not read {}: {}", privileged::CONFIG_PATH, e))?;
+        toml::from_str(&content)
+            .map(SessionBuilder::new)
+            .map_err(|e| format!("Error parsing config.toml: {}", e))
+    }
+
+    /// Blocks these sites instead of the configured list; subscriptions still apply.
+    pub fn sites<I, S>(mut self, sites: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.scope = None;
+        self.config.blocked_sites = sites.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Adds an enforcement backend on top of the hosts file.
+    pub fn backend(mut self, backend: Backend) -> Self {
+        if !self.backends.contains(&backend) {
+            self.backends.push(backend);
+        }
+        self
+    }
+
+    /// Labels the session in the history, like `--tag`.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    /// Strict sessions refuse [`SessionHandle::stop`].
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.config.strict = strict;
+        self
+    }
+
+    /// Runs `hook` for every event the session sends to the configured notifiers.
+    pub fn on_event(mut self, hook: impl Fn(&Event) + Send + Sync + 'static) -> Self {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Blocks the sites and starts the timer. Like the CLI, this needs root or
+    /// `focus-helper`, and only one session may run per process.
+    pub fn start(self) -> Result<SessionHandle, String> {
+        let mut config = self.config;
+        config.duration = self.duration.as_secs().div_ceil(60).max(1);
+        apply_backends(&mut config, &self.backends);
+
+        let content = privileged::resolve_hosts(&config.hosts_path)
+            .and_then(|target| fs::read_to_string(target).map_err(|e| e.to_string()))?;
+        if hosts::has_block(&content, &config.markers, config.scope.as_deref()) {
+            return Err(String::from("A focus session is already running"));
+        }
+        if util::effective_sites(&config).is_empty() {
+            return Err(String::from("No sites to block"));
+        }
+        util::begin_block(&config, false);
+        if let Err(e) = util::write_block(&config) {
+            let _ = util::unblock_sites(&config);
+            return Err(e);
+        }
+
+        let config = Arc::new(config);
+        let running = Arc::new(AtomicBool::new(true));
+        let timer = Arc::new(Timer::new(self.duration));
+        let session = Arc::new(Session {
+            config: Arc::clone(&config),
+            timer: Arc::clone(&timer),
+            running: Arc::clone(&running),
+            hooks: self.hooks,
+            ended: Mutex::new(false),
+        });
+        session::save_state(&config, self.tag, None);
+        session.send(Event::SessionStarted {
+            minutes: config.duration,
+            resumed: false,
+        });
+
+        if config.dns.enabled {
+            dns::start_server(Arc::clone(&config), Arc::clone(&running));
+        }
+        if config.sni.enabled {
+            sni::start_proxy(Arc::clone(&config), Arc::clone(&running));
+        }
+        if config.ebpf.enabled {
+            ebpf::start_filter(Arc::clone(&config), Arc::clone(&running));
+        }
+        if backend::uses_firewall(&config) {
+            firewall::start_refresh(Arc::clone(&config), Arc::clone(&running));
+        }
+        if config.proxy.enabled {
+            proxy::start_proxy(Arc::clone(&config), Arc::clone(&running));
+        }
+
+        let timeline = Timeline::new(Arc::clone(&timer));
+        timeline.schedule(Due::At(Instant::now()), Tick::TamperCheck);
+        if config.duration >= 2 {
+            timeline.schedule(Due::Remaining(self.duration / 2), Tick::Halfway);
+        }
+        if config.grace_minutes > 0 && config.grace_minutes < config.duration {
+            timeline.schedule(
+                Due::Remaining(Duration::from_mins(config.grace_minutes)),
+                Tick::WrappingUp,
+            );
+        }
+        timeline.schedule(Due::Remaining(Duration::ZERO), Tick::Deadline);
+
+        let thread_session = Arc::clone(&session);
+        let thread = thread::spawn(move || thread_session.run(&timeline));
+        Ok(SessionHandle {
+            session,
+            thread: Mutex::new(Some(thread)),
+        })
+    }
+}
+
+/// A snapshot of a running or finished session.
+#[derive(Debug, Clone)]
+pub struct Status {
+    pub running: bool,
+    pub paused: bool,
+    pub remaining: Duration,
+    pub sites: Vec<String>,
+}
+
+/// Controls a session started with [`SessionBuilder::start`]. Dropping it leaves
+/// the session running until its deadline.
+pub struct SessionHandle {
+    session: Arc<Session>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl SessionHandle {
+    pub fn status(&self) -> Status {
+        let running = self.session.running.load(Ordering::SeqCst);
+        Status {
+            running,
+            paused: self.session.timer.is_paused(),
+            remaining: if running {
+                self.session.timer.remaining()
+            } else {
+                Duration::ZERO
+            },
+            sites: util::effective_sites(&self.session.config),
+        }
+    }
+
+    /// Moves the deadline back, both in the timer and in the hosts block.
+    pub fn extend(&self, by: Duration) -> Result<(), String> {
+        if !self.session.running.load(Ordering::SeqCst) {
+            return Err(String::from("The session has already ended"));
+        }
+        util::extend_session(&self.session.config, &self.session.timer, by);
+        audit::record(&self.session.config, "session", "extended");
+        Ok(())
+    }
+
+    /// Ends the session early and unblocks the sites, unless it is strict.
+    pub fn stop(&self) -> Result<(), String> {
+        if self.session.config.strict {
+            audit::record(
+                &self.session.config,
+                "session",
+                "early stop refused (strict)",
+            );
+            return Err(String::from(
+                "This is a strict session and can't be stopped early",
+            ));
+        }
+        if !self.session.finish(Event::SessionStopped {
+            outcome: "interrupted",
+        }) {
+            return Err(String::from("The session has already ended"));
+        }
+        self.wait();
+        Ok(())
+    }
+
+    /// Blocks until the session has ended, the sites are unblocked and the session
+    /// is recorded.
+    pub fn wait(&self) {
+        if let Some(thread) = self.thread.lock().unwrap().take() {
+            let _ = thread.join();
+        }
+        events::flush();
+    }
+}
+
+struct Session {
+    config: Arc<Config>,
+    timer: Arc<Timer>,
+    running: Arc<AtomicBool>,
+    hooks: Vec<Hook>,
+    /// Set by whichever of the deadline and `stop` gets there first
+    ended: Mutex<bool>,
+}
+
+impl Session {
+    fn send(&self, event: Event) {
+        for hook in &self.hooks {
+            hook(&event);
+        }
+        events::publish(&self.config, event);
+    }
+
+    fn run(&self, timeline: &Timeline) {
+        while let Some(tick) = timeline.next(&self.running) {
+            match tick {
+                Tick::TamperCheck => {
+                    util::check_tamper(&self.config);
+                    apps::enforce(&self.config);
+                    resolver::check(&self.config);
+                    firewall::check(&self.config);
+                    clock::check(&self.config, &self.timer);
+                    timeline.set_slack(power::slack(&self.config));
+                    timeline.schedule(
+                        Due::At(Instant::now() + power::check_interval(&self.config)),
+                        Tick::TamperCheck,
+                    );
+                }
+                Tick::Halfway => self.send(Event::Halfway {
+                    minutes: self.config.duration / 2,
+                }),
+                Tick::WrappingUp => self.send(Event::WrappingUp {
+                    minutes: self.config.grace_minutes,
+                }),
+                Tick::Deadline => {
+                    self.finish(Event::SessionEnded { journal: None });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Unblocks the session and sends `event` once; false if it had already ended.
+    fn finish(&self, event: Event) -> bool {
+        let mut ended = self.ended.lock().unwrap();
+        if *ended {
+            return false;
+        }
+        *ended = true;
+        self.running.store(false, Ordering::SeqCst);
+        self.timer.notify_change();
+        if self.config.sni.enabled {
+            sni::remove_rules(&self.config);
+        }
+        if let Err(e) = util::unblock_sites(&self.config) {
+            eprintln!(
+                "{}",
+                format!("[!] Failed to restore hosts file: {}", e)
+                    .bold()
+                    .red()
+            );
+            audit::record(&self.config, "session", "restore failed");
+        } else {
+            self.send(event);
+        }
+        true
+    }
+}
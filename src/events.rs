@@ -0,0 +1,69 @@
+use crate::{audit, notify, notify::Event, stats, util::Config};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        Arc, Condvar, Mutex, Once,
+        mpsc::{self, Sender},
+    },
+    thread,
+};
+
+struct Envelope {
+    config: Config,
+    event: Event,
+}
+
+static SUBSCRIBERS: Mutex<Vec<Sender<Arc<Envelope>>>> = Mutex::new(Vec::new());
+static BUILTIN: Once = Once::new();
+/// Deliveries not yet handled, so the process can wait for them before exiting
+static PENDING: Mutex<usize> = Mutex::new(0);
+static HANDLED: Condvar = Condvar::new();
+
+/// Adds a consumer for every event published from now on. Each consumer gets the
+/// events in order on a thread of its own, so session code never needs to know about it.
+pub fn subscribe(consumer: impl Fn(&Config, &Event) + Send + 'static) {
+    let (sender, receiver) = mpsc::channel::<Arc<Envelope>>();
+    thread::spawn(move || {
+        for envelope in receiver {
+            // A failing consumer must not stall flush() or the others
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+                consumer(&envelope.config, &envelope.event)
+            }));
+            handled();
+        }
+    });
+    SUBSCRIBERS.lock().unwrap().push(sender);
+}
+
+/// Sends a lifecycle event to the notifiers, the history and the audit log.
+pub fn publish(config: &Config, event: Event) {
+    BUILTIN.call_once(|| {
+        subscribe(notify::send);
+        subscribe(stats::record_event);
+        subscribe(audit::record_event);
+    });
+    let envelope = Arc::new(Envelope {
+        config: config.clone(),
+        event,
+    });
+    let subscribers = SUBSCRIBERS.lock().unwrap();
+    *PENDING.lock().unwrap() += subscribers.len();
+    for subscriber in subscribers.iter() {
+        if subscriber.send(Arc::clone(&envelope)).is_err() {
+            handled();
+        }
+    }
+}
+
+/// Blocks until every event published so far has been handled.
+pub fn flush() {
+    let mut pending = PENDING.lock().unwrap();
+    while *pending > 0 {
+        pending = HANDLED.wait(pending).unwrap();
+    }
+}
+
+fn handled() {
+    *PENDING.lock().unwrap() -= 1;
+    HANDLED.notify_all();
+}
@@ -0,0 +1,184 @@
+use crate::{
+    audit,
+    backend::Blocker,
+    dns, events,
+    notify::Event,
+    platform,
+    privileged::{self, Request},
+    util::{self, Config},
+};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap},
+    net::IpAddr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Sites resolved at once; big subscription lists would otherwise take minutes
+const RESOLVERS: usize = 16;
+
+/// Addresses each site resolved to, until the next refresh.
+static RESOLVED: Mutex<Option<HashMap<String, Vec<IpAddr>>>> = Mutex::new(None);
+/// Whether this session's rules should be in place, for the tamper check.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FirewallConfig {
+    /// Seconds between looking the sites up again, as their addresses change
+    #[serde(default = "default_resolve_interval")]
+    pub resolve_interval: u64,
+}
+
+impl Default for FirewallConfig {
+    fn default() -> Self {
+        FirewallConfig {
+            resolve_interval: default_resolve_interval(),
+        }
+    }
+}
+
+fn default_resolve_interval() -> u64 {
+    300
+}
+
+/// Rejects traffic to the blocked sites' addresses with nftables.
+pub struct Firewall;
+
+impl Blocker for Firewall {
+    fn block(&self, config: &Config, _in_place: bool) -> Result<(), String> {
+        util::privileged(
+            config,
+            Request::Firewall {
+                scope: config.scope.clone(),
+                addresses: addresses(config),
+            },
+        )?;
+        ACTIVE.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn unblock(&self, config: &Config) -> Result<(), String> {
+        ACTIVE.store(false, Ordering::SeqCst);
+        util::privileged(
+            config,
+            Request::ClearFirewall {
+                scope: config.scope.clone(),
+            },
+        )
+    }
+}
+
+/// The addresses of every blocked site, resolving those not looked up since the last refresh.
+fn addresses(config: &Config) -> Vec<IpAddr> {
+    let sites = util::blocked_sites(config);
+    let mut resolved = RESOLVED.lock().unwrap();
+    let resolved = resolved.get_or_insert_with(HashMap::new);
+    let missing: Vec<&String> = sites
+        .iter()
+        .filter(|site| !resolved.contains_key(*site))
+        .collect();
+    let chunk = missing.len().div_ceil(RESOLVERS).max(1);
+    thread::scope(|scope| {
+        let workers: Vec<_> = missing
+            .chunks(chunk)
+            .map(|sites| {
+                scope.spawn(|| {
+                    sites
+                        .iter()
+                        .map(|site| {
+                            let addresses = dns::resolve_ipv4(&config.dns, site)
+                                .into_iter()
+                                .map(IpAddr::V4)
+                                .chain(
+                                    dns::resolve_ipv6(&config.dns, site)
+                                        .into_iter()
+                                        .map(IpAddr::V6),
+                                )
+                                .collect();
+                            (site.to_string(), addresses)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for worker in workers {
+            resolved.extend(worker.join().unwrap_or_default());
+        }
+    });
+    sites
+        .iter()
+        .filter_map(|site| resolved.get(site))
+        .flatten()
+        .filter(|address| privileged::is_blockable(address))
+        .copied()
+        .collect::<BTreeSet<IpAddr>>()
+        .into_iter()
+        .collect()
+}
+
+/// Looks the sites up again every `resolve_interval` and updates the rules to match.
+pub fn start_refresh(config: Arc<Config>, running: Arc<AtomicBool>) {
+    let interval = Duration::from_secs(config.firewall.resolve_interval.max(1));
+    println!(
+        "{}",
+        format!(
+            "[>] Blocking sites in the firewall too, looking them up again every {} minutes",
+            interval.as_secs().div_ceil(60)
+        )
+        .bold()
+        .cyan()
+    );
+    thread::spawn(move || {
+        let mut last_resolve = Instant::now();
+        while running.load(Ordering::SeqCst) {
+            thread::sleep(POLL_INTERVAL);
+            if last_resolve.elapsed() < interval || !ACTIVE.load(Ordering::SeqCst) {
+                continue;
+            }
+            RESOLVED.lock().unwrap().take();
+            if let Err(e) = Firewall.block(&config, false) {
+                eprintln!(
+                    "{}",
+                    format!("[!] Failed to update the firewall rules: {}", e)
+                        .bold()
+                        .red()
+                );
+            }
+            last_resolve = Instant::now();
+        }
+    });
+}
+
+/// Puts the rules back if they were deleted during the session; runs with the tamper check.
+pub fn check(config: &Config) {
+    // Listing rules needs root, which sessions through focus-helper don't have
+    if !ACTIVE.load(Ordering::SeqCst)
+        || !platform::is_root()
+        || privileged::has_firewall(&config.scope)
+    {
+        return;
+    }
+    println!(
+        "{}",
+        "[!] Tamper detected! The firewall rules were removed; restoring them"
+            .bold()
+            .red()
+    );
+    audit::record(config, "session", "firewall rules restored");
+    events::publish(config, Event::TamperDetected);
+    if let Err(e) = Firewall.block(config, false) {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to restore the firewall rules: {}", e)
+                .bold()
+                .red()
+        );
+    }
+}
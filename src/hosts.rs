@@ -0,0 +1,441 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, net::IpAddr};
+
+/// Markers every earlier version wrote; always recognized so stale blocks get cleaned up.
+pub const LEGACY_BEGIN: &str = "# BEGIN FOCUS BLOCK";
+pub const LEGACY_END: &str = "# END FOCUS BLOCK";
+const INFO_PREFIX: &str = "# focus ";
+
+/// Written as a comment inside the block so any later invocation knows what it was for.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BlockInfo {
+    pub session: String,
+    pub start: DateTime<Local>,
+    /// `None` for blocks that last until `focus stop`
+    pub deadline: Option<DateTime<Local>>,
+}
+
+impl BlockInfo {
+    fn render(&self) -> String {
+        format!(
+            "{}session={} start={} deadline={}",
+            INFO_PREFIX,
+            self.session,
+            self.start
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            self.deadline
+                .map(|deadline| deadline.to_rfc3339_opts(chrono::SecondsFormat::Secs, false))
+                .unwrap_or_else(|| String::from("none"))
+        )
+    }
+
+    fn parse(line: &str) -> Option<BlockInfo> {
+        let fields: Vec<(&str, &str)> = words_after(line, INFO_PREFIX)?
+            .into_iter()
+            .filter_map(|field| field.split_once('='))
+            .collect();
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| *value)
+        };
+        let time = |value: &str| {
+            DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|time| time.with_timezone(&Local))
+        };
+        Some(BlockInfo {
+            session: field("session")?.to_string(),
+            start: time(field("start")?)?,
+            deadline: match field("deadline")? {
+                "none" => None,
+                deadline => Some(time(deadline)?),
+            },
+        })
+    }
+}
+
+/// A block found in the hosts file, with its metadata when it has any.
+#[derive(Debug, Clone)]
+pub struct FoundBlock {
+    pub scope: Option<String>,
+    pub info: Option<BlockInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Markers {
+    #[serde(default = "default_begin")]
+    pub begin: String,
+    #[serde(default = "default_end")]
+    pub end: String,
+}
+
+impl Default for Markers {
+    fn default() -> Self {
+        Markers {
+            begin: default_begin(),
+            end: default_end(),
+        }
+    }
+}
+
+fn default_begin() -> String {
+    String::from(LEGACY_BEGIN)
+}
+
+fn default_end() -> String {
+    String::from(LEGACY_END)
+}
+
+impl Markers {
+    /// Markers must stay single comment lines that can't be mistaken for each other.
+    pub fn is_valid(&self) -> bool {
+        [&self.begin, &self.end]
+            .iter()
+            .all(|marker| marker.starts_with('#') && !marker.contains(['\n', '\r']))
+            && self.begin != self.end
+    }
+
+    /// Whether a group's begin marker stays distinct from the end marker; with `# focus`
+    /// and `# focus end`, group `end` would begin its block with an end marker.
+    pub fn allows_scope(&self, scope: &str) -> bool {
+        words_after(&marker_line(&self.begin, Some(scope), None), &self.end).is_none()
+    }
+
+    /// The configured markers followed by the legacy ones when they differ.
+    fn known(&self) -> Vec<Markers> {
+        let mut known = vec![self.clone()];
+        if *self != Markers::default() {
+            known.push(Markers::default());
+        }
+        known
+    }
+}
+
+fn marker_line(marker: &str, scope: Option<&str>, session: Option<&str>) -> String {
+    let mut line = marker.to_string();
+    if let Some(group) = scope {
+        line.push_str(&format!(" {}", group));
+    }
+    if let Some(session) = session {
+        line.push_str(&format!(" session={}", session));
+    }
+    line
+}
+
+pub fn render_block(
+    markers: &Markers,
+    scope: Option<&str>,
+    info: Option<&BlockInfo>,
+    block_ip: &str,
+    sites: &[String],
+) -> String {
+    let session = info.map(|info| info.session.as_str());
+    let mut content = format!("\n{}\n", marker_line(&markers.begin, scope, session));
+    if let Some(info) = info {
+        content.push_str(&format!("{}\n", info.render()));
+    }
+    for site in sites {
+        content.push_str(&format!("{}\t{}\n", block_ip, site));
+    }
+    content.push_str(&marker_line(&markers.end, scope, session));
+    content
+}
+
+pub fn has_block(content: &str, markers: &Markers, scope: Option<&str>) -> bool {
+    scan(content, markers)
+        .iter()
+        .any(|span| span.complete && span.scope == scope)
+}
+
+/// Whether `block`, as rendered by `render_block`, is still in the file unchanged.
+/// Whitespace and line endings don't count, since editors on dual-boot systems change them.
+pub fn has_intact_block(content: &str, block: &str) -> bool {
+    let block: Vec<String> = normalized_lines(block)
+        .skip_while(String::is_empty)
+        .collect();
+    let content: Vec<String> = normalized_lines(content).collect();
+    block.is_empty() || content.windows(block.len()).any(|lines| lines == block)
+}
+
+/// Lines with runs of spaces and tabs collapsed and CRs dropped, for comparing.
+fn normalized_lines(content: &str) -> impl Iterator<Item = String> {
+    content
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// The words of `line` after those of `prefix`, whatever whitespace separates them.
+fn words_after<'a>(line: &'a str, prefix: &str) -> Option<Vec<&'a str>> {
+    let mut words = line.split_whitespace();
+    prefix
+        .split_whitespace()
+        .all(|expected| words.next() == Some(expected))
+        .then(|| words.collect())
+}
+
+/// Every block in the file, for any scope, in the current or a legacy format. Blocks
+/// that lost their end marker are included so they can be cleaned up.
+pub fn find_blocks(content: &str, markers: &Markers) -> Vec<FoundBlock> {
+    scan(content, markers)
+        .into_iter()
+        .filter(|span| span.complete || span.kind == MarkerKind::Begin)
+        .map(|span| {
+            let end = if span.complete {
+                span.end
+            } else {
+                written_entries_end(content, markers, span.end)
+            };
+            FoundBlock {
+                scope: span.scope.map(str::to_string),
+                info: content[span.start..end].lines().find_map(BlockInfo::parse),
+            }
+        })
+        .collect()
+}
+
+/// Removes every block for the scope along with the line break written before it, and
+/// repairs the rest of the file so no leftover markers can pair up afterwards.
+pub fn remove_block(content: &str, markers: &Markers, scope: Option<&str>) -> String {
+    cut(
+        content,
+        &damaged_or(content, markers, |span| span.scope == scope),
+    )
+}
+
+/// Drops what interrupted writes and hand edits leave behind: begin markers without an
+/// end, together with the entries focus wrote after them, and end markers without a begin.
+pub fn repair(content: &str, markers: &Markers) -> String {
+    cut(content, &damaged_or(content, markers, |_| false))
+}
+
+/// Unpaired markers, plus the complete blocks `remove` picks.
+fn damaged_or<'a>(
+    content: &'a str,
+    markers: &Markers,
+    remove: impl Fn(&Span) -> bool,
+) -> Vec<Span<'a>> {
+    scan(content, markers)
+        .into_iter()
+        .filter_map(|span| match (span.complete, span.kind) {
+            (true, _) => remove(&span).then_some(span),
+            (false, MarkerKind::Begin) => Some(Span {
+                end: written_entries_end(content, markers, span.end),
+                ..span
+            }),
+            (false, MarkerKind::End) => Some(span),
+        })
+        .collect()
+}
+
+/// Replaces any existing block for the scope with a fresh one at the end of the file.
+pub fn set_block(
+    content: &str,
+    markers: &Markers,
+    scope: Option<&str>,
+    info: Option<&BlockInfo>,
+    block_ip: &str,
+    sites: &[String],
+) -> String {
+    let mut content = remove_block(content, markers, scope);
+    content.push_str(&render_block(markers, scope, info, block_ip, sites));
+    content
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MarkerKind {
+    Begin,
+    End,
+}
+
+/// A complete block, or a single marker line that has no partner.
+struct Span<'a> {
+    kind: MarkerKind,
+    scope: Option<&'a str>,
+    complete: bool,
+    /// Byte offsets from the begin marker to the end of the last line, without its line break
+    start: usize,
+    end: usize,
+}
+
+/// Lines with their byte offsets, without line breaks.
+fn lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    content.split_inclusive('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len();
+        Some((start, line.trim_end_matches(['\n', '\r'])))
+    })
+}
+
+/// Parses `<marker>[ <scope>][ session=<id>]` for any known markers. End markers are
+/// tried first, since custom markers like `# focus` and `# focus end` overlap.
+fn parse_marker<'a>(
+    line: &'a str,
+    known: &[Markers],
+) -> Option<(MarkerKind, usize, Option<&'a str>)> {
+    known.iter().enumerate().find_map(|(set, markers)| {
+        [
+            (MarkerKind::End, &markers.end),
+            (MarkerKind::Begin, &markers.begin),
+        ]
+        .into_iter()
+        .find_map(|(kind, marker)| {
+            let words = words_after(line, marker)?;
+            // An empty ID is what a write cut off mid-line leaves
+            let session = |word: &str| word.starts_with("session=");
+            let group = |word: &str| !word.is_empty() && !session(word);
+            let scope = match words.as_slice() {
+                [] => None,
+                [id] if session(id) => None,
+                [name] if group(name) => Some(*name),
+                [name, id] if group(name) && session(id) => Some(*name),
+                _ => return None,
+            };
+            Some((kind, set, scope))
+        })
+    })
+}
+
+/// Pairs each begin marker with the next end marker of the same markers and scope.
+/// Any other marker line in between leaves the begin marker unpaired, so one damaged
+/// block can never swallow the lines up to somebody else's end marker.
+fn scan<'a>(content: &'a str, markers: &Markers) -> Vec<Span<'a>> {
+    let known = markers.known();
+    let mut spans = Vec::new();
+    let mut open: Option<(Span, usize)> = None;
+    for (start, line) in lines(content) {
+        let Some((kind, set, scope)) = parse_marker(line, &known) else {
+            continue;
+        };
+        let marker = Span {
+            kind,
+            scope,
+            complete: false,
+            start,
+            end: start + line.len(),
+        };
+        match (kind, open.take()) {
+            (MarkerKind::End, Some((begin, open_set)))
+                if open_set == set && begin.scope == scope =>
+            {
+                spans.push(Span {
+                    complete: true,
+                    end: marker.end,
+                    ..begin
+                });
+            }
+            (MarkerKind::Begin, previous) => {
+                spans.extend(previous.map(|(begin, _)| begin));
+                open = Some((marker, set));
+            }
+            (MarkerKind::End, previous) => {
+                spans.extend(previous.map(|(begin, _)| begin));
+                spans.push(marker);
+            }
+        }
+    }
+    spans.extend(open.map(|(begin, _)| begin));
+    spans
+}
+
+/// Extends a dangling begin marker over the info and entry lines focus wrote after it.
+fn written_entries_end(content: &str, markers: &Markers, marker_end: usize) -> usize {
+    let known = markers.known();
+    lines(content)
+        .skip_while(|(start, _)| *start < marker_end)
+        .take_while(|(_, line)| parse_marker(line, &known).is_none())
+        .take_while(|(_, line)| {
+            // Only the exact shape focus writes, so none of the user's own entries go with it
+            words_after(line, INFO_PREFIX).is_some()
+                || line.trim_end().split_once('\t').is_some_and(|(ip, host)| {
+                    ip.parse::<IpAddr>().is_ok() && is_valid_hostname(host)
+                })
+        })
+        .last()
+        .map(|(start, line)| start + line.len())
+        .unwrap_or(marker_end)
+}
+
+/// Removes the spans, each with the line break before it, as `render_block` adds one.
+fn cut(content: &str, spans: &[Span]) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut kept = 0;
+    for span in spans {
+        let mut start = span.start;
+        // In a file with Windows line breaks the span ends in one as well
+        if content[..start].ends_with("\r\n") && content[span.end..].starts_with("\r\n") {
+            start -= 2;
+        } else if content[..start].ends_with('\n') {
+            start -= 1;
+        }
+        let start = start.max(kept);
+        result.push_str(&content[kept..start]);
+        kept = span.end;
+    }
+    result.push_str(&content[kept..]);
+    result
+}
+
+/// Like `set_block`, but keeps an existing block where it is in the file.
+pub fn replace_block(
+    content: &str,
+    markers: &Markers,
+    scope: Option<&str>,
+    info: Option<&BlockInfo>,
+    block_ip: &str,
+    sites: &[String],
+) -> String {
+    let Some(span) = scan(content, markers)
+        .into_iter()
+        .find(|span| span.complete && span.scope == scope)
+    else {
+        return set_block(content, markers, scope, info, block_ip, sites);
+    };
+    let block = render_block(markers, scope, info, block_ip, sites);
+    format!(
+        "{}{}{}",
+        repair(&content[..span.start], markers),
+        block.trim_start_matches('\n'),
+        remove_block(&content[span.end..], markers, scope)
+    )
+}
+
+/// Sites no line in the file points at `block_ip`, wherever in the file that line is.
+pub fn missing_sites<'a>(content: &str, block_ip: &str, sites: &'a [String]) -> Vec<&'a str> {
+    let mapped: HashSet<&str> = content
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split('#').next()?.split_whitespace();
+            (words.next()? == block_ip).then_some(words)
+        })
+        .flatten()
+        .collect();
+    sites
+        .iter()
+        .map(String::as_str)
+        .filter(|site| !mapped.contains(site))
+        .collect()
+}
+
+/// Names embedded in marker lines: groups and session IDs.
+pub fn is_valid_scope(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Hostnames only, so nothing written into the hosts file can add extra entries.
+pub fn is_valid_hostname(host: &str) -> bool {
+    !host.is_empty()
+        && host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
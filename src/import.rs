@@ -0,0 +1,123 @@
+use crate::util::{self, Config};
+use colored::Colorize;
+use std::{collections::HashSet, process};
+
+/// Reads domains from a CSV export and adds the valid, new ones to the blocklist.
+pub fn import_csv(config: &Config, file: &str, column: Option<&str>) {
+    let mut reader = match csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_path(file)
+    {
+        Ok(reader) => reader,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                format!("[!] Could not open {}: {}", file, e).bold().red()
+            );
+            process::exit(1);
+        }
+    };
+    let mut rows: Vec<(u64, csv::StringRecord)> = Vec::new();
+    for (index, row) in reader.records().enumerate() {
+        match row {
+            Ok(row) => rows.push((index as u64 + 1, row)),
+            Err(e) => eprintln!(
+                "{}",
+                format!("[!] Row {}: unreadable: {}", index + 1, e)
+                    .bold()
+                    .yellow()
+            ),
+        }
+    }
+    let Some((_, first)) = rows.first() else {
+        eprintln!("{}", format!("[!] {} is empty", file).bold().red());
+        process::exit(1);
+    };
+
+    let named_column = column.and_then(|name| {
+        first
+            .iter()
+            .position(|cell| cell.eq_ignore_ascii_case(name))
+    });
+    // A first row without a single domain in it is taken to be a header
+    let has_header = named_column.is_some() || first.iter().all(|cell| normalize(cell).is_none());
+    let index = match (column, named_column) {
+        (_, Some(index)) => index,
+        (Some(column), None) => match column.parse::<usize>() {
+            Ok(number) if number >= 1 => number - 1,
+            _ => {
+                eprintln!(
+                    "{}",
+                    format!("[!] No column named {} in {}", column, file)
+                        .bold()
+                        .red()
+                );
+                process::exit(1);
+            }
+        },
+        (None, None) => rows
+            .iter()
+            .skip(has_header as usize)
+            .find_map(|(_, row)| row.iter().position(|cell| normalize(cell).is_some()))
+            .unwrap_or(0),
+    };
+
+    let mut known: HashSet<String> = util::effective_sites(config).into_iter().collect();
+    let mut added = Vec::new();
+    let (mut duplicates, mut invalid) = (0, 0);
+    for (number, row) in rows.iter().skip(has_header as usize) {
+        let cell = row.get(index).unwrap_or_default();
+        match normalize(cell) {
+            Some(domain) if known.insert(domain.clone()) => added.push(domain),
+            Some(_) => duplicates += 1,
+            None => {
+                invalid += 1;
+                eprintln!(
+                    "{}",
+                    format!("[!] Row {}: '{}' is not a valid domain", number, cell)
+                        .bold()
+                        .yellow()
+                );
+            }
+        }
+    }
+
+    if !added.is_empty() {
+        util::add_urls(&added, config.clone());
+    }
+    println!(
+        "{}",
+        format!(
+            "[+] Imported {} sites ({} already blocked, {} invalid)",
+            added.len(),
+            duplicates,
+            invalid
+        )
+        .bold()
+        .green()
+    );
+}
+
+/// Accepts bare domains as well as URLs, returning the lowercase host.
+fn normalize(cell: &str) -> Option<String> {
+    let cell = cell.trim().to_lowercase();
+    let host = cell
+        .split_once("://")
+        .map_or(cell.as_str(), |(_, rest)| rest);
+    let host = host.split(['/', '?', '#']).next()?;
+    let host = host.split(':').next()?.trim_end_matches('.');
+
+    let valid = host.len() <= 253
+        && host.contains('.')
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        && !host.rsplit('.').next()?.chars().all(|c| c.is_ascii_digit());
+    valid.then(|| host.to_string())
+}
@@ -0,0 +1,88 @@
+//! focus blocks distracting sites for a fixed session by rewriting the hosts file.
+//!
+//! The `focus` binary is built on this crate; GUI wrappers and bots can run sessions
+//! directly through [`SessionBuilder`] instead of shelling out to the CLI:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! let session = focus::SessionBuilder::load()?
+//!     .sites(["news.ycombinator.com", "reddit.com"])
+//!     .duration(Duration::from_secs(25 * 60))
+//!     .on_event(|event| println!("{}", event.message()))
+//!     .start()?;
+//! println!("{:?} left", session.status().remaining);
+//! session.wait();
+//! # Ok::<(), String>(())
+//! ```
+//!
+//! The other modules make up the CLI and may change between releases.
+
+pub mod admin;
+pub mod apps;
+pub mod audio;
+pub mod audit;
+pub mod backend;
+pub mod blockpage;
+pub mod breakscreen;
+pub mod bundle;
+pub mod bypass;
+pub mod capabilities;
+pub mod certificate;
+pub mod clock;
+pub mod companion;
+pub mod controls;
+pub mod dbus;
+pub mod desktop;
+pub mod dns;
+pub mod ebpf;
+pub mod edit;
+pub mod embed;
+pub mod events;
+pub mod firewall;
+pub mod hosts;
+pub mod import;
+pub mod limits;
+pub mod matrix;
+pub mod mdns;
+pub mod microbreak;
+pub mod mqtt;
+pub mod native;
+pub mod notify;
+pub mod ntfy;
+pub mod onboarding;
+pub mod overlay;
+pub mod packs;
+pub mod pkglock;
+pub mod platform;
+pub mod power;
+pub mod privileged;
+pub mod profile;
+pub mod project;
+pub mod prompt;
+pub mod protect;
+pub mod proxy;
+pub mod readonly;
+pub mod resolver;
+pub mod ritual;
+pub mod schedule;
+pub mod session;
+pub mod sni;
+pub mod socket;
+pub mod stats;
+pub mod subscriptions;
+pub mod summary;
+pub mod sync;
+pub mod telegram;
+pub mod timeline;
+pub mod top;
+pub mod update;
+pub mod user;
+pub mod util;
+pub mod vacation;
+pub mod watchdog;
+pub mod webhook;
+
+pub use embed::{Backend, SessionBuilder, SessionHandle, Status};
+pub use notify::Event;
+pub use util::Config;
@@ -0,0 +1,114 @@
+use crate::util::Config;
+use chrono::Local;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+
+const COUNTERS_FILE: &str = "focus.counters";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Limits {
+    pub max_stops_per_day: Option<u32>,
+    pub max_pauses_per_day: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Counters {
+    pub date: String,
+    pub stops: u32,
+    pub pauses: u32,
+    pub refused_stops: u32,
+    pub refused_pauses: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Stop,
+    Pause,
+}
+
+impl Action {
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Stop => "early stop",
+            Action::Pause => "pause",
+        }
+    }
+}
+
+pub fn counters_path(config: &Config) -> String {
+    format!("{}/{}", config.log_directory, COUNTERS_FILE)
+}
+
+pub fn load_counters(config: &Config) -> Counters {
+    let today = Local::now().date_naive().to_string();
+    let counters: Counters = fs::read_to_string(counters_path(config))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+
+    if counters.date == today {
+        counters
+    } else {
+        Counters {
+            date: today,
+            ..Counters::default()
+        }
+    }
+}
+
+fn save_counters(config: &Config, counters: &Counters) {
+    let content = serde_json::to_string(counters).expect("[!] Could not encode counters");
+    if let Err(e) = fs::write(counters_path(config), content) {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to save rate-limit counters: {}", e)
+                .bold()
+                .yellow()
+        );
+    }
+}
+
+/// Counts an attempt against today's allowance and returns whether it may proceed.
+pub fn try_consume(config: &Config, action: Action) -> bool {
+    // Held through the write, so concurrent stops can't both take the last one left
+    let _lock = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(counters_path(config))
+        .and_then(|file| file.lock().map(|()| file));
+    let mut counters = load_counters(config);
+    let (used, limit) = match action {
+        Action::Stop => (counters.stops, config.limits.max_stops_per_day),
+        Action::Pause => (counters.pauses, config.limits.max_pauses_per_day),
+    };
+
+    if let Some(limit) = limit
+        && used >= limit
+    {
+        match action {
+            Action::Stop => counters.refused_stops += 1,
+            Action::Pause => counters.refused_pauses += 1,
+        }
+        save_counters(config, &counters);
+        eprintln!(
+            "{}",
+            format!(
+                "[!] Refused: daily {} limit reached ({} of {} used)",
+                action.name(),
+                used,
+                limit
+            )
+            .bold()
+            .red()
+        );
+        return false;
+    }
+
+    match action {
+        Action::Stop => counters.stops += 1,
+        Action::Pause => counters.pauses += 1,
+    }
+    save_counters(config, &counters);
+    true
+}
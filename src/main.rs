@@ -1,23 +1,68 @@
 use clap::Parser;
 use colored::Colorize;
-use daemonize::Daemonize;
+use focus::{
+    admin, apps, audio, audit, backend, blockpage, breakscreen, bundle, bypass, capabilities,
+    certificate, clock, companion, controls, desktop, dns, ebpf, edit, embed, events, firewall,
+    hosts, import, microbreak, native, notify::Event, onboarding, overlay, packs, pkglock,
+    platform, power, privileged, profile, project, prompt, protect, proxy, readonly, resolver,
+    ritual, schedule, session, sni, socket, stats, subscriptions, summary, sync, telegram,
+    timeline, top, update, util, vacation, watchdog,
+};
 use std::{
     fs::{self, File},
-    path,
-    process::{self, Command},
+    io::{self, IsTerminal, Write},
+    path, process,
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering},
+        mpsc::{self, Sender},
     },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
+use timeline::{Due, Tick, Timeline};
 
-mod audio;
-mod util;
+const EXTEND_PROMPT_TIMEOUT: Duration = Duration::from_secs(30);
 
 fn main() {
-    let args = util::Args::parse();
+    let mut args = util::Args::parse();
+
+    // Before loading the config, since fixing a broken one is what this is for
+    if args.command == Some(util::Commands::Edit) {
+        let outcome = edit::edit_config();
+        if let Ok(config) = util::load_config() {
+            audit::record(&config, "edit", outcome);
+        }
+        return;
+    }
+
+    // Runs as the desktop user, who may not be able to read the config
+    if let Some(util::Commands::Companion { unit }) = args.command {
+        if unit {
+            companion::print_unit();
+        } else {
+            companion::run();
+        }
+        return;
+    }
+
+    if let Some(util::Commands::PlaySound { path, repeat }) = &args.command {
+        audio::play_in_process(path, *repeat);
+        return;
+    }
+
+    if let Some(util::Commands::BreakScreen { seconds }) = args.command {
+        if let Err(e) = breakscreen::show(seconds) {
+            eprintln!(
+                "{}",
+                format!("[!] Could not cover the screen: {}", e)
+                    .bold()
+                    .red()
+            );
+            process::exit(1);
+        }
+        return;
+    }
 
     let mut config = match util::load_config() {
         Ok(config) => config,
@@ -30,57 +75,413 @@ fn main() {
         }
     };
 
-    if let Some(path) = args.path {
-        config.hosts_path = path;
+    if let Some(path) = &args.path {
+        config.hosts_path = path.clone();
     }
 
     if let Some(duration) = args.duration {
         config.duration = duration;
     }
 
-    util::save_config(&config).expect("[!] Failed to save configuration");
+    if args.path.is_some() || args.duration.is_some() {
+        util::save_config(&config).expect("[!] Failed to save configuration");
+    }
 
-    let running = Arc::new(AtomicBool::new(true));
-    let thread_running = Arc::clone(&running);
+    if let Some(shortcut) = args.shortcut.take() {
+        profile::apply_shortcut(&mut config, &mut args, &shortcut);
+    }
 
-    let original_content = match fs::read_to_string(&config.hosts_path) {
-        Ok(content) => Arc::new(content),
-        Err(e) => {
+    if let Some(zone) = &config.timezone
+        && let Err(e) = schedule::parse_zone(zone)
+    {
+        eprintln!("{}", format!("[!] Invalid timezone: {}", e).bold().red());
+        process::exit(1);
+    }
+
+    if let Some(group) = &args.group {
+        if !hosts::is_valid_scope(group) {
+            eprintln!(
+                "{}",
+                "[!] Group names may only contain letters, digits, '-' and '_'"
+                    .bold()
+                    .red()
+            );
+            process::exit(1);
+        }
+        if !config.markers.allows_scope(group) {
             eprintln!(
                 "{}",
                 format!(
-                    "[!] Failed to read hosts file. Are you running as sudo? Error: {}",
-                    e
+                    "[!] Group '{}' can't be told apart from the end marker '{}'",
+                    group, config.markers.end
                 )
                 .bold()
                 .red()
             );
             process::exit(1);
         }
-    };
+        config.scope = Some(group.clone());
+    }
+
+    // Only sessions pick up project files; commands that save the config must not
+    if matches!(args.command, None | Some(util::Commands::Start)) {
+        project::apply(&mut config, &mut args);
+    }
 
     match &args.command {
-        Some(util::Commands::Add { urls }) => {
-            util::add_urls(urls, config);
-            return;
-        }
-        Some(util::Commands::Remove { urls }) => {
-            util::remove_urls(urls, config);
-            return;
-        }
-        Some(util::Commands::Start) => {
-            util::block_sites(&config, true);
-            return;
-        }
-        Some(util::Commands::Status) => {
-            util::check_status();
-            return;
+        None => session::clean_stale(&config),
+        Some(command) if command.cleans_stale() => session::clean_stale(&config),
+        Some(command) if command.is_audited(args.json) => session::hint_stale(&config),
+        Some(_) => {}
+    }
+
+    if let Some(command) = &args.command {
+        let outcome = match command {
+            util::Commands::Add { urls } => {
+                util::add_urls(urls, config.clone());
+                "ok"
+            }
+            util::Commands::Remove { urls } => {
+                if admin::require_admin(&config, "Removing sites") {
+                    util::remove_urls(urls, config.clone());
+                    "ok"
+                } else {
+                    "denied"
+                }
+            }
+            util::Commands::Start => {
+                if let Some(name) = &args.profile {
+                    profile::apply(&mut config, name);
+                }
+                if !args.no_ritual {
+                    ritual::run(&config);
+                }
+                warm_up(&config, false);
+                util::block_sites(&config, true);
+                "ok"
+            }
+            util::Commands::Status => {
+                if args.json {
+                    println!("{}", util::status_json(&config));
+                } else {
+                    util::check_status(&config);
+                }
+                "ok"
+            }
+            util::Commands::List => {
+                util::list_sites(&config, args.json);
+                "ok"
+            }
+            util::Commands::Background => {
+                if session::send_to_background(&config) {
+                    "ok"
+                } else {
+                    "refused"
+                }
+            }
+            util::Commands::Extend { minutes } => {
+                match socket::ask(
+                    &config,
+                    serde_json::json!({ "op": "extend", "minutes": minutes }),
+                ) {
+                    Ok(reply) => {
+                        println!(
+                            "{}",
+                            format!(
+                                "[+] Extended by {} minutes; {} left",
+                                minutes,
+                                controls::format_clock(reply["remaining"].as_u64().unwrap_or(0))
+                            )
+                            .bold()
+                            .green()
+                        );
+                        "ok"
+                    }
+                    Err(e) => {
+                        eprintln!(
+                            "{}",
+                            format!("[!] Could not extend the session: {}", e)
+                                .bold()
+                                .red()
+                        );
+                        process::exit(1);
+                    }
+                }
+            }
+            util::Commands::Top => {
+                top::run(&config);
+                "ok"
+            }
+            util::Commands::Attach => {
+                if socket::attach(&config) {
+                    "ok"
+                } else {
+                    "failed"
+                }
+            }
+            util::Commands::Events { follow } => {
+                if socket::stream_events(&config, *follow) {
+                    "ok"
+                } else {
+                    "failed"
+                }
+            }
+            util::Commands::Stop => {
+                // The admin password only guards stopping during scheduled hours
+                if schedule::active_until(&config).is_some()
+                    && !admin::require_admin(&config, "Stopping the session")
+                {
+                    "denied"
+                } else if util::stop_daemon(&config) {
+                    "ok"
+                } else {
+                    "refused"
+                }
+            }
+            util::Commands::Audit => {
+                audit::show_audit(&config);
+                "ok"
+            }
+            util::Commands::Stats {
+                action,
+                today,
+                heatmap,
+                queries,
+                weeks,
+            } => {
+                if let Some(util::StatsAction::Prune { before }) = action {
+                    stats::prune_before(&config, *before);
+                } else if *queries {
+                    stats::show_queries(&config);
+                } else if *heatmap {
+                    stats::show_heatmap(&config, *weeks);
+                } else if args.json {
+                    println!("{}", stats::stats_json(&config));
+                } else {
+                    stats::show_stats(&config, *today);
+                }
+                "ok"
+            }
+            util::Commands::ExportState { file } => {
+                bundle::export_state(&config, file);
+                "ok"
+            }
+            util::Commands::ImportState { file } => {
+                if admin::require_admin(&config, "Importing state") {
+                    bundle::import_state(file);
+                    "ok"
+                } else {
+                    "denied"
+                }
+            }
+            util::Commands::Sync => {
+                sync::sync(&config);
+                "ok"
+            }
+            util::Commands::Refresh => {
+                subscriptions::refresh(&config);
+                vacation::refresh(&config);
+                "ok"
+            }
+            util::Commands::AdminPassword => {
+                if admin::set_admin_password(&config) {
+                    "ok"
+                } else {
+                    "denied"
+                }
+            }
+            util::Commands::Import { file, column } => {
+                import::import_csv(&config, file, column.as_deref());
+                "ok"
+            }
+            util::Commands::Lists => {
+                packs::show_lists(&config);
+                "ok"
+            }
+            util::Commands::Log { limit } => {
+                stats::show_log(&config, *limit);
+                "ok"
+            }
+            util::Commands::Summary { week } => {
+                summary::send(&config, *week);
+                "ok"
+            }
+            util::Commands::Certificate { remove: false } => {
+                if certificate::create(&config) {
+                    "ok"
+                } else {
+                    "failed"
+                }
+            }
+            util::Commands::Certificate { remove: true } => {
+                certificate::remove(&config);
+                "ok"
+            }
+            util::Commands::Edit
+            | util::Commands::Companion { .. }
+            | util::Commands::PlaySound { .. }
+            | util::Commands::BreakScreen { .. } => {
+                unreachable!("handled before the config is loaded")
+            }
+            util::Commands::Remaining { clock, seconds } => {
+                if !session::print_remaining(&config, *clock, *seconds) {
+                    process::exit(1);
+                }
+                "ok"
+            }
+            util::Commands::Schedule { action } => match action {
+                None | Some(util::ScheduleAction::List) => {
+                    schedule::list(&config);
+                    "ok"
+                }
+                Some(util::ScheduleAction::Add { rule }) => {
+                    schedule::add(&config, &rule.join(" "));
+                    "ok"
+                }
+                Some(util::ScheduleAction::Remove { rule }) => {
+                    if !admin::require_admin(&config, "Removing scheduled blocks") {
+                        "denied"
+                    } else if schedule::remove(&config, &rule.join(" ")) {
+                        "ok"
+                    } else {
+                        "not found"
+                    }
+                }
+                Some(util::ScheduleAction::Run) => {
+                    schedule::run(&config);
+                    "ok"
+                }
+            },
+            util::Commands::App { action } => match action {
+                None | Some(util::AppAction::List) => {
+                    apps::list(&config);
+                    "ok"
+                }
+                Some(util::AppAction::Add { apps }) => {
+                    apps::add(&config, apps);
+                    "ok"
+                }
+                Some(util::AppAction::Remove { apps }) => {
+                    if !admin::require_admin(&config, "Unblocking apps") {
+                        "denied"
+                    } else if apps::remove(&config, apps) {
+                        "ok"
+                    } else {
+                        "not found"
+                    }
+                }
+            },
+            util::Commands::Onboarding { end } => {
+                if *end {
+                    onboarding::end(&config);
+                } else {
+                    onboarding::status(&config);
+                }
+                "ok"
+            }
+            util::Commands::Capabilities => {
+                capabilities::report(&config);
+                "ok"
+            }
+            util::Commands::Why => {
+                if !session::print_why(&config) {
+                    process::exit(1);
+                }
+                "ok"
+            }
+            util::Commands::Watchdog { pid } => {
+                watchdog::run(&config, *pid);
+                "ok"
+            }
+            util::Commands::NativeHost {
+                manifest,
+                extension,
+                ..
+            } => {
+                match (manifest, extension) {
+                    (Some(browser), Some(extension)) => native::print_manifest(*browser, extension),
+                    _ => native::run(),
+                }
+                "ok"
+            }
+        };
+        if command.is_audited(args.json) {
+            audit::record(&config, command.name(), outcome);
         }
-        Some(util::Commands::Stop) => {
-            util::stop_daemon(&config);
-            return;
+        events::flush();
+        return;
+    }
+
+    // A foreground session handing itself over; its block, tag and profile carry on here
+    let takeover = args.takeover.map(|pid| {
+        let Some(state) = session::load_state(&config).filter(|state| state.pid == pid) else {
+            eprintln!("{}", "[!] No foreground session to take over".bold().red());
+            process::exit(1);
+        };
+        args.profile = state.profile.clone();
+        args.tag = state.tag.clone();
+        args.backends = state.backends.clone().unwrap_or_default();
+        args.background = true;
+        args.no_ritual = true;
+        (pid, state)
+    });
+
+    if let Some(name) = &args.profile {
+        profile::apply(&mut config, name);
+        // An explicit --duration still wins over the profile's
+        if let Some(duration) = args.duration {
+            config.duration = duration;
         }
-        None => {}
+    }
+    if let Some(until) = &args.until {
+        config.duration = minutes_until(&config, until);
+    }
+    if !args.backends.is_empty() {
+        embed::apply_backends(&mut config, &args.backends);
+    }
+    onboarding::apply(&mut config);
+    readonly::check_hosts(&mut config);
+
+    let running = Arc::new(AtomicBool::new(true));
+    let thread_running = Arc::clone(&running);
+
+    if let Err(e) = privileged::resolve_hosts(&config.hosts_path)
+        .and_then(|target| fs::read_to_string(target).map_err(|e| e.to_string()))
+    {
+        eprintln!(
+            "{}",
+            format!(
+                "[!] Failed to read hosts file. Are you running as sudo? Error: {}",
+                e
+            )
+            .bold()
+            .red()
+        );
+        process::exit(1);
+    }
+    let resume = if takeover.is_some() {
+        None
+    } else {
+        args.resume.or_else(|| adopt_orphan(&config, args.orphan))
+    };
+    if let Some(minutes) = resume {
+        config.duration = minutes;
+    }
+    if let Some((_, state)) = &takeover {
+        config.duration = state.planned_minutes;
+        config.strict = state.strict;
+    }
+    let fresh = resume.is_none() && takeover.is_none();
+    if let Some(group) = &config.scope
+        && util::effective_sites(&config).is_empty()
+    {
+        eprintln!(
+            "{}",
+            format!("[!] Group {} has no sites to block", group)
+                .bold()
+                .red()
+        );
+        process::exit(1);
     }
 
     let config = Arc::new(config);
@@ -88,9 +489,18 @@ fn main() {
     let handler_running = Arc::clone(&running);
     let handler_config = Arc::clone(&config);
 
-    let pid_path = format!("{}/focus.pid", config.log_directory);
-    let out_path = format!("{}/focus.out", config.log_directory);
-    let err_path = format!("{}/focus.err", config.log_directory);
+    if let Err(e) = util::create_runtime_directory() {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to create {}: {}", util::runtime_directory(), e)
+                .bold()
+                .red()
+        );
+        process::exit(1);
+    }
+    let pid_path = util::runtime_path(&config, "pid");
+    let out_path = util::scoped_path(&config, "out");
+    let err_path = util::scoped_path(&config, "err");
 
     if path::Path::new(&pid_path).exists() {
         println!(
@@ -102,27 +512,46 @@ fn main() {
         let _ = fs::remove_file(&pid_path);
     }
 
+    let checklist = if platform::is_detached() {
+        platform::inherited_checklist()
+    } else if args.no_ritual || resume.is_some() {
+        None
+    } else {
+        ritual::run(&config)
+    };
+
+    // A relaunched Windows session would replace the code its launcher already handed out
+    if fresh && !platform::is_detached() {
+        bypass::prepare(&config);
+    }
     if args.background {
         println!("{}", "[>] Moving to background...".bold().cyan());
 
         let stdout = File::create(out_path).unwrap();
         let stderr = File::create(err_path).unwrap();
 
-        let daemonize = Daemonize::new()
-            .pid_file(&pid_path)
-            .chroot("/")
-            .working_directory(&config.log_directory)
-            .stdout(stdout)
-            .stderr(stderr);
-
-        daemonize
-            .start()
-            .expect(&format!("{}", "[!] Error: daemonize failed"));
-    } else {
-        audio::play_audio(format!("{}/{}", config.data_directory, config.start_audio));
+        platform::background(
+            &pid_path,
+            &config.log_directory,
+            stdout,
+            stderr,
+            checklist.as_ref(),
+        );
+    }
+    if fresh {
+        if config.wait_for_updates {
+            pkglock::wait_until_idle();
+        }
+        warm_up(&config, args.background);
+        audio::play_cue(
+            &config,
+            &config.start_audio,
+            "Session started",
+            args.background,
+        );
     }
 
-    ctrlc::set_handler(move || {
+    platform::on_interrupt(move || {
         util::ctrlc_handler(
             &handler_running,
             &handler_config,
@@ -131,24 +560,176 @@ fn main() {
         );
     })
     .expect("Error setting Ctrl-C handler");
+    controls::catch_detach_signal();
 
-    util::block_sites(&config, false);
+    if !config.offline {
+        subscriptions::refresh(&config);
+    }
+    update::notify_if_outdated(&config);
+    let mut remaining = Duration::from_mins(config.duration);
+    if let Some((pid, _)) = &takeover {
+        let Some(left) = util::adopt_block(&config) else {
+            eprintln!(
+                "{}",
+                "[!] The session's block is gone; nothing to take over"
+                    .bold()
+                    .red()
+            );
+            process::exit(1);
+        };
+        remaining = left;
+        session::take_over(&config, *pid);
+    } else if resume.is_some() {
+        // The killed session's block is still in place; take it over with a new deadline
+        println!(
+            "{}",
+            format!("[>] Resuming session with {} minutes left", config.duration)
+                .bold()
+                .cyan()
+        );
+        util::begin_block(&config, false);
+        util::reblock_sites(&config);
+    } else {
+        util::block_sites(&config, false);
+    }
+    if takeover.is_none() {
+        session::save_state(&config, args.tag.clone(), checklist);
+    }
+    if config.strict {
+        watchdog::guard(Arc::clone(&config), Arc::clone(&running));
+        protect::guard(Arc::clone(&config), Arc::clone(&running));
+    }
+    if takeover.is_none() {
+        events::publish(
+            &config,
+            Event::SessionStarted {
+                minutes: config.duration,
+                resumed: resume.is_some(),
+            },
+        );
+    }
 
     println!("{}", "[>] Flushing DNS cache".bold().cyan());
-    Command::new("resolvectl")
-        .arg("flush-caches")
-        .output()
-        .expect(&format!("{}", "[!] Failed to flush DNS cache"));
+    platform::flush_dns().expect("[!] Failed to flush DNS cache");
+
+    if config.dns.enabled {
+        dns::start_server(Arc::clone(&config), Arc::clone(&running));
+    }
+    if config.sni.enabled {
+        sni::start_proxy(Arc::clone(&config), Arc::clone(&running));
+    }
+    if config.ebpf.enabled {
+        ebpf::start_filter(Arc::clone(&config), Arc::clone(&running));
+    }
+    if backend::uses_firewall(&config) {
+        firewall::start_refresh(Arc::clone(&config), Arc::clone(&running));
+    }
+    if config.proxy.enabled {
+        proxy::start_proxy(Arc::clone(&config), Arc::clone(&running));
+    }
 
     let thread_config = Arc::clone(&config);
-    util::start_checker_thead(thread_config, thread_running);
-    thread::sleep(Duration::from_mins(config.duration));
+    let timer = Arc::new(session::Timer::new(remaining));
+    telegram::start_command_thread(
+        Arc::clone(&config),
+        Arc::clone(&timer),
+        Arc::clone(&thread_running),
+    );
+    audio::play_session_ambient(&config, Arc::clone(&running), args.background);
+    if config.block_page.enabled || config.enforcement == util::Enforcement::Nag {
+        blockpage::start_server(
+            Arc::clone(&config),
+            Arc::clone(&running),
+            Arc::clone(&timer),
+        );
+    }
+    if config.socket.enabled {
+        socket::start_server(
+            Arc::clone(&config),
+            Arc::clone(&running),
+            Arc::clone(&timer),
+            args.tag.clone(),
+        );
+    }
+    if config.overlay.enabled {
+        overlay::start_server(
+            Arc::clone(&config),
+            Arc::clone(&running),
+            Arc::clone(&timer),
+            args.tag.clone(),
+        );
+    }
+    let timeline = Arc::new(Timeline::new(Arc::clone(&timer)));
+    timeline.schedule(Due::At(Instant::now()), Tick::TamperCheck);
+    if let Some(due) = util::soft_start_end() {
+        timeline.schedule(Due::At(due), Tick::SoftStartEnd);
+    }
+    if let Some(due) = summary::next_due(&config) {
+        timeline.schedule(Due::At(due), Tick::Summary);
+    }
+    if config.duration >= 2 && remaining > Duration::from_mins(config.duration / 2) {
+        timeline.schedule(
+            Due::Remaining(Duration::from_mins(config.duration / 2)),
+            Tick::Halfway,
+        );
+    }
+    if let Some(due) = microbreak::next_due(&config) {
+        timeline.schedule(Due::At(due), Tick::MicroBreak);
+    }
+    if config.grace_minutes > 0
+        && config.grace_minutes < config.duration
+        && remaining > Duration::from_mins(config.grace_minutes)
+    {
+        timeline.schedule(
+            Due::Remaining(Duration::from_mins(config.grace_minutes)),
+            Tick::WrappingUp,
+        );
+    }
+    timeline.schedule(Due::Remaining(Duration::ZERO), Tick::Deadline);
+    let (deadline_sender, deadline) = mpsc::channel();
+    start_timeline_thread(
+        thread_config,
+        thread_running,
+        Arc::clone(&timeline),
+        deadline_sender,
+        args.background,
+    );
+    let controls_active = Arc::new(AtomicBool::new(true));
+    let controls = if args.background {
+        None
+    } else {
+        controls::start(
+            Arc::clone(&config),
+            Arc::clone(&timer),
+            Arc::clone(&controls_active),
+        )
+    };
+
+    let _ = deadline.recv();
+    controls_active.store(false, Ordering::SeqCst);
+    if let Some(controls) = controls {
+        let _ = controls.join();
+        prompt::restore_terminal();
+    }
+
+    while offer_extension(&config, args.background) {
+        util::extend_session(
+            &config,
+            &timer,
+            Duration::from_mins(config.extend_prompt_minutes),
+        );
+        timeline.schedule(Due::Remaining(Duration::ZERO), Tick::Deadline);
+        let _ = deadline.recv();
+    }
 
     running.store(false, Ordering::SeqCst);
     thread::sleep(Duration::from_millis(100));
+    if config.sni.enabled {
+        sni::remove_rules(&config);
+    }
 
     println!("{}", "[>] Time's up! Unblocking sites".bold().cyan());
-    if let Err(e) = fs::write(&config.hosts_path, &*original_content) {
+    if let Err(e) = util::unblock_sites(&config) {
         eprintln!(
             "{}",
             format!(
@@ -159,8 +740,284 @@ fn main() {
             .red()
         );
         eprintln!("{}", format!("Error: {}", e).bold().red());
+        audit::record(&config, "session", "restore failed");
+    } else {
+        let journal = if !args.background && config.journal_prompt {
+            ask_journal()
+        } else {
+            None
+        };
+        events::publish(&config, Event::SessionEnded { journal });
+    }
+    audio::play_cue(
+        &config,
+        &config.end_audio,
+        "Session complete",
+        args.background,
+    );
+    events::flush();
+}
+
+fn ask_journal() -> Option<String> {
+    let question = "[?] What did you accomplish? (Enter to skip) ";
+    prompt::read_line(&format!("{}", question.bold().yellow()), None)
+        .map(|answer| answer.trim().to_string())
+        .filter(|answer| !answer.is_empty())
+}
+
+/// Session length for `--until`, following DST in the configured or given zone.
+fn minutes_until(config: &util::Config, until: &str) -> u64 {
+    let (time, zone) = match until.split_once(' ') {
+        Some((time, zone)) => (time, schedule::parse_zone(zone.trim())),
+        None => (until, Ok(schedule::default_zone(config))),
+    };
+    let end = zone.and_then(|zone| {
+        schedule::parse_time(time)
+            .map(|time| schedule::next_occurrence(zone, time, chrono::Utc::now()))
+    });
+    match end {
+        Ok(end) => ((end - chrono::Utc::now()).num_seconds().max(0) as u64).div_ceil(60),
+        Err(e) => {
+            eprintln!("{}", format!("[!] Invalid --until: {}", e).bold().red());
+            process::exit(1);
+        }
+    }
+}
+
+/// Offers to take over a block whose session died; returns the minutes left to run.
+fn adopt_orphan(config: &util::Config, action: Option<session::OrphanAction>) -> Option<u64> {
+    let orphan = session::find_orphan(config)?;
+    let session_name = orphan
+        .info
+        .as_ref()
+        .map(|info| format!("session {}", info.session))
+        .unwrap_or_else(|| String::from("an earlier session"));
+    println!(
+        "{}",
+        format!(
+            "[!] Found a block left by {}, which is no longer running",
+            session_name
+        )
+        .bold()
+        .yellow()
+    );
+
+    let seconds_left = orphan
+        .info
+        .and_then(|info| info.deadline)
+        .map(|deadline| (deadline - chrono::Local::now()).num_seconds())
+        .filter(|seconds| *seconds > 0);
+    let action = match (seconds_left, action) {
+        (None, _) => session::OrphanAction::Clean,
+        (Some(_), Some(action)) => action,
+        // Without a terminal to ask on, keep enforcing what was already promised
+        (Some(_), None) if !io::stdin().is_terminal() => session::OrphanAction::Adopt,
+        (Some(seconds), None) => {
+            let question = format!(
+                "[?] Adopt it and finish its remaining {} minutes? [y/N] ",
+                (seconds as u64).div_ceil(60)
+            );
+            if prompt::confirm(&format!("{}", question.bold().yellow()), None) {
+                session::OrphanAction::Adopt
+            } else {
+                session::OrphanAction::Clean
+            }
+        }
+    };
+
+    match (action, seconds_left) {
+        (session::OrphanAction::Adopt, Some(seconds)) => {
+            audit::record(config, "session", "orphan adopted");
+            Some((seconds as u64).div_ceil(60))
+        }
+        _ => {
+            println!("{}", "[>] Removing the old block".bold().cyan());
+            let _ = util::unblock_sites(config);
+            session::clear_state(config);
+            audit::record(config, "session", "orphan cleaned");
+            None
+        }
+    }
+}
+
+/// Gives time to wrap up before the block applies; Ctrl-C here simply cancels.
+fn warm_up(config: &util::Config, background: bool) {
+    if config.warmup_minutes == 0 {
+        return;
+    }
+    let deadline = Instant::now() + Duration::from_mins(config.warmup_minutes);
+    println!(
+        "{}",
+        format!(
+            "[>] Warming up: blocking starts in {} minutes",
+            config.warmup_minutes
+        )
+        .bold()
+        .cyan()
+    );
+    if background {
+        thread::sleep(deadline - Instant::now());
+        return;
+    }
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        print!(
+            "{}{}",
+            controls::CLEAR_LINE,
+            format!(
+                "[>] Blocking starts in {}",
+                controls::format_clock(remaining.as_secs_f64().ceil() as u64)
+            )
+            .bold()
+            .cyan()
+        );
+        let _ = io::stdout().flush();
+        thread::sleep(remaining.min(Duration::from_secs(1)));
+    }
+    print!("{}", controls::CLEAR_LINE);
+    let _ = io::stdout().flush();
+}
+
+/// Handles every timed session event on one thread; the deadline is passed back to main.
+fn start_timeline_thread(
+    config: Arc<util::Config>,
+    running: Arc<AtomicBool>,
+    timeline: Arc<Timeline>,
+    deadline: Sender<()>,
+    background: bool,
+) {
+    thread::spawn(move || {
+        let timer = timeline.timer();
+        while let Some(tick) = timeline.next(&running) {
+            match tick {
+                Tick::SoftStartEnd => util::end_soft_start(&config),
+                Tick::TamperCheck => {
+                    if !timer.is_paused() {
+                        util::check_tamper(&config);
+                        apps::enforce(&config);
+                        resolver::check(&config);
+                        firewall::check(&config);
+                    }
+                    clock::check(&config, &timer);
+                    timeline.set_slack(power::slack(&config));
+                    timeline.schedule(
+                        Due::At(Instant::now() + power::check_interval(&config)),
+                        Tick::TamperCheck,
+                    );
+                }
+                Tick::Summary => {
+                    summary::send_due(&config);
+                    if let Some(due) = summary::next_due(&config) {
+                        timeline.schedule(Due::At(due), Tick::Summary);
+                    }
+                }
+                Tick::Halfway => announce_halfway(&config, &timer),
+                Tick::MicroBreak => {
+                    if !timer.is_paused() {
+                        microbreak::prompt(&config, background);
+                    }
+                    if let Some(due) = microbreak::next_due(&config) {
+                        timeline.schedule(Due::At(due), Tick::MicroBreak);
+                    }
+                }
+                Tick::WrappingUp => announce_grace(&config, &timer, background),
+                Tick::Deadline => {
+                    let _ = deadline.send(());
+                }
+            }
+        }
+    });
+}
+
+fn announce_halfway(config: &Arc<util::Config>, timer: &Arc<session::Timer>) {
+    let event = Event::Halfway {
+        minutes: config.duration / 2,
+    };
+    if config.desktop.actions {
+        desktop::offer_actions(
+            Arc::clone(config),
+            Arc::clone(timer),
+            event.title().to_string(),
+            event.message(),
+        );
+    }
+    events::publish(config, event);
+}
+
+fn announce_grace(config: &Arc<util::Config>, timer: &Arc<session::Timer>, background: bool) {
+    println!(
+        "{}",
+        format!(
+            "[>] Wrapping up: sites unblock in {} minutes",
+            config.grace_minutes
+        )
+        .bold()
+        .cyan()
+    );
+    let event = Event::WrappingUp {
+        minutes: config.grace_minutes,
+    };
+    if config.desktop.actions {
+        desktop::offer_actions(
+            Arc::clone(config),
+            Arc::clone(timer),
+            event.title().to_string(),
+            event.message(),
+        );
+    }
+    events::publish(config, event);
+
+    if let Some(grace_audio) = &config.grace_audio {
+        audio::play_cue(config, grace_audio, "Wrapping up", background);
+    }
+    if config.grace_speech {
+        audio::speak_cue(
+            config,
+            &format!(
+                "Wrapping up. {} minutes left in this session.",
+                config.grace_minutes
+            ),
+            background,
+        );
     }
-    if !args.background {
-        audio::play_audio(format!("{}/{}", config.data_directory, config.end_audio));
+}
+
+fn offer_extension(config: &util::Config, background: bool) -> bool {
+    let extend = if config.desktop.actions {
+        desktop::ask(
+            config,
+            "Focus complete",
+            "Sites are about to unblock",
+            false,
+            EXTEND_PROMPT_TIMEOUT,
+        ) == Some(desktop::Choice::Extend)
+    } else if background || !config.extend_prompt {
+        false
+    } else {
+        let question = format!(
+            "[?] Extend by {} minutes? [y/N] ",
+            config.extend_prompt_minutes
+        );
+        prompt::confirm(
+            &format!("{}", question.bold().yellow()),
+            Some(EXTEND_PROMPT_TIMEOUT),
+        )
+    };
+    if extend {
+        println!(
+            "{}",
+            format!(
+                "[>] Extending session by {} minutes",
+                config.extend_prompt_minutes
+            )
+            .bold()
+            .cyan()
+        );
+        audit::record(config, "session", "extended");
     }
+    extend
 }
@@ -0,0 +1,63 @@
+use crate::notify::{self, Event, Notifier};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MatrixConfig {
+    pub homeserver: String,
+    pub room_id: String,
+    pub access_token: String,
+}
+
+impl Notifier for MatrixConfig {
+    fn notify(&self, event: &Event) {
+        send_message(self, &event.message());
+    }
+}
+
+pub fn send_message(config: &MatrixConfig, text: &str) {
+    let txn_id = format!(
+        "focus-{}-{}",
+        process::id(),
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos())
+            .unwrap_or_default()
+    );
+    let url = format!(
+        "{}/_matrix/client/v3/rooms/{}/send/m.room.message/{}",
+        config.homeserver.trim_end_matches('/'),
+        encode_path_segment(&config.room_id),
+        txn_id
+    );
+    let body = serde_json::json!({ "msgtype": "m.text", "body": text });
+
+    let result = notify::http_agent()
+        .put(&url)
+        .header("Authorization", &format!("Bearer {}", config.access_token))
+        .send_json(&body);
+    if let Err(e) = result {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to send Matrix message: {}", e)
+                .bold()
+                .yellow()
+        );
+    }
+}
+
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
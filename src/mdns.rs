@@ -0,0 +1,353 @@
+//! A minimal mDNS responder announcing the overlay as `_focus._tcp.local`, so dashboards on
+//! the LAN find the countdown without being told an address. Only the records of this one
+//! service are answered; host names stay with Avahi or whatever else runs on the machine.
+
+use crate::util::Config;
+use colored::Colorize;
+#[cfg(unix)]
+use std::os::fd::FromRawFd;
+use std::{
+    fs, io,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+const GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const PORT: u16 = 5353;
+const SERVICE: &str = "_focus._tcp.local";
+const SERVICES: &str = "_services._dns-sd._udp.local";
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// Set on records only this host answers, so caches replace rather than add to them
+const CACHE_FLUSH: u16 = 0x8000;
+const TTL: u32 = 120;
+const MAX_PACKET: usize = 9000;
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Pointers followed while reading one name, against loops in malformed packets
+const MAX_POINTERS: usize = 16;
+
+/// What gets announced: the instance name, where it listens and the TXT entries.
+struct Service {
+    instance: String,
+    host: String,
+    address: Ipv4Addr,
+    port: u16,
+    txt: Vec<String>,
+}
+
+/// Announces the overlay listening on `listen` until the session ends, then withdraws it.
+pub fn advertise(config: Arc<Config>, running: Arc<AtomicBool>, listen: SocketAddr) {
+    let SocketAddr::V4(listen) = listen else {
+        eprintln!(
+            "{}",
+            "[!] Overlay is only advertised on IPv4 addresses"
+                .bold()
+                .yellow()
+        );
+        return;
+    };
+    let address = if listen.ip().is_unspecified() {
+        outgoing_address()
+    } else {
+        Some(*listen.ip())
+    };
+    let Some(address) = address.filter(|address| !address.is_loopback()) else {
+        eprintln!(
+            "{}",
+            "[!] Overlay listens on loopback only; set [overlay] listen = \"0.0.0.0:7878\" so it can be advertised"
+                .bold()
+                .yellow()
+        );
+        return;
+    };
+    let socket = match bind() {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                format!("[!] Failed to advertise overlay over mDNS: {}", e)
+                    .bold()
+                    .red()
+            );
+            return;
+        }
+    };
+    let host = hostname();
+    let service = Service {
+        instance: match &config.scope {
+            Some(group) => format!("Focus {} on {}.{}", group, host, SERVICE),
+            None => format!("Focus on {}.{}", host, SERVICE),
+        },
+        host: format!("{}.local", host),
+        address,
+        port: listen.port(),
+        txt: vec![
+            String::from("txtvers=1"),
+            String::from("path=/"),
+            String::from("state=/state"),
+        ],
+    };
+    println!(
+        "{}",
+        format!("[>] Overlay advertised over mDNS as {}", SERVICE)
+            .bold()
+            .cyan()
+    );
+
+    thread::spawn(move || {
+        let group = SocketAddr::from((GROUP, PORT));
+        // Announced twice, a second apart, as RFC 6762 asks
+        for _ in 0..2 {
+            let _ = socket.send_to(&response(&service, TTL), group);
+            thread::sleep(RECEIVE_TIMEOUT);
+        }
+        let mut buffer = [0u8; MAX_PACKET];
+        while running.load(Ordering::SeqCst) {
+            let Ok((length, _)) = socket.recv_from(&mut buffer) else {
+                continue;
+            };
+            if asks_for(&buffer[..length], &service) {
+                let _ = socket.send_to(&response(&service, TTL), group);
+            }
+        }
+        // A zero TTL tells listeners the countdown is gone
+        let _ = socket.send_to(&response(&service, 0), group);
+    });
+}
+
+/// Binds the mDNS port shared with any responder already running, in the multicast group.
+fn bind() -> Result<UdpSocket, String> {
+    let socket = bind_shared().map_err(|e| e.to_string())?;
+    socket
+        .join_multicast_v4(&GROUP, &Ipv4Addr::UNSPECIFIED)
+        .map_err(|e| e.to_string())?;
+    socket
+        .set_multicast_ttl_v4(255)
+        .map_err(|e| e.to_string())?;
+    socket
+        .set_read_timeout(Some(RECEIVE_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    Ok(socket)
+}
+
+#[cfg(unix)]
+fn bind_shared() -> io::Result<UdpSocket> {
+    #[cfg(target_os = "linux")]
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+    // macOS can't set close-on-exec while creating the socket
+    #[cfg(target_os = "macos")]
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let socket = unsafe { UdpSocket::from_raw_fd(fd) };
+    #[cfg(target_os = "macos")]
+    unsafe {
+        libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC)
+    };
+    let on: libc::c_int = 1;
+    for option in [libc::SO_REUSEADDR, libc::SO_REUSEPORT] {
+        unsafe {
+            libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                option,
+                &on as *const libc::c_int as *const libc::c_void,
+                size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+    }
+    let address = libc::sockaddr_in {
+        #[cfg(target_os = "macos")]
+        sin_len: size_of::<libc::sockaddr_in>() as u8,
+        sin_family: libc::AF_INET as libc::sa_family_t,
+        sin_port: PORT.to_be(),
+        sin_addr: libc::in_addr { s_addr: 0 },
+        sin_zero: [0; 8],
+    };
+    let bound = unsafe {
+        libc::bind(
+            fd,
+            &address as *const libc::sockaddr_in as *const libc::sockaddr,
+            size_of::<libc::sockaddr_in>() as libc::socklen_t,
+        )
+    };
+    if bound != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(socket)
+}
+
+/// std can't set `SO_REUSEADDR` before binding, so this only works while no other responder
+/// holds the port.
+#[cfg(target_os = "windows")]
+fn bind_shared() -> io::Result<UdpSocket> {
+    UdpSocket::bind((Ipv4Addr::UNSPECIFIED, PORT))
+}
+
+/// The address other devices reach this one on: the one multicast leaves from.
+fn outgoing_address() -> Option<Ipv4Addr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect((GROUP, PORT)).ok()?;
+    match socket.local_addr().ok()? {
+        SocketAddr::V4(address) if !address.ip().is_unspecified() => Some(*address.ip()),
+        _ => None,
+    }
+}
+
+fn hostname() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|name| name.trim().to_string())
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| String::from("focus"))
+}
+
+/// Whether a query asks for the service type, the list of types or this instance.
+fn asks_for(packet: &[u8], service: &Service) -> bool {
+    // Responses have QR set; only queries are answered
+    if packet.len() < 12 || packet[2] & 0x80 != 0 {
+        return false;
+    }
+    let questions = u16::from_be_bytes([packet[4], packet[5]]);
+    let mut position = 12;
+    for _ in 0..questions {
+        let Some((name, end)) = read_name(packet, position) else {
+            return false;
+        };
+        let Some(qtype) = packet.get(end..end + 2) else {
+            return false;
+        };
+        let qtype = u16::from_be_bytes([qtype[0], qtype[1]]);
+        position = end + 4;
+        let wanted = match qtype {
+            TYPE_PTR => name.eq_ignore_ascii_case(SERVICE) || name.eq_ignore_ascii_case(SERVICES),
+            TYPE_SRV | TYPE_TXT => name.eq_ignore_ascii_case(&service.instance),
+            TYPE_ANY => {
+                name.eq_ignore_ascii_case(SERVICE) || name.eq_ignore_ascii_case(&service.instance)
+            }
+            _ => false,
+        };
+        if wanted {
+            return true;
+        }
+    }
+    false
+}
+
+/// Reads a possibly compressed name, returning it and the position after it.
+fn read_name(packet: &[u8], mut position: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    for _ in 0..MAX_POINTERS {
+        loop {
+            let length = *packet.get(position)? as usize;
+            if length & 0xC0 == 0xC0 {
+                let pointer = (length & 0x3F) << 8 | *packet.get(position + 1)? as usize;
+                end.get_or_insert(position + 2);
+                position = pointer;
+                break;
+            }
+            position += 1;
+            if length == 0 {
+                return Some((labels.join("."), end.unwrap_or(position)));
+            }
+            labels.push(
+                String::from_utf8_lossy(packet.get(position..position + length)?).into_owned(),
+            );
+            position += length;
+        }
+    }
+    None
+}
+
+fn write_name(packet: &mut Vec<u8>, name: &str) {
+    // Instance names may contain dots of their own, so only the type suffix is split
+    let (instance, suffix) = match name.strip_suffix(SERVICE) {
+        Some(instance) if !instance.is_empty() => (Some(instance.trim_end_matches('.')), SERVICE),
+        _ => (None, name),
+    };
+    if let Some(instance) = instance {
+        packet.push(instance.len().min(63) as u8);
+        packet.extend_from_slice(&instance.as_bytes()[..instance.len().min(63)]);
+    }
+    for label in suffix.split('.') {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+}
+
+fn write_record(packet: &mut Vec<u8>, name: &str, rtype: u16, class: u16, ttl: u32, data: &[u8]) {
+    write_name(packet, name);
+    packet.extend_from_slice(&rtype.to_be_bytes());
+    packet.extend_from_slice(&class.to_be_bytes());
+    packet.extend_from_slice(&ttl.to_be_bytes());
+    packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    packet.extend_from_slice(data);
+}
+
+/// Every record of the service as one unsolicited response, the way responders announce.
+/// The host's address rides along as an additional record, never flushing or withdrawing
+/// what Avahi announces for the host name, so a goodbye leaves it out.
+fn response(service: &Service, ttl: u32) -> Vec<u8> {
+    let additional = u8::from(ttl > 0);
+    // ID 0, QR and AA set, no questions, four answers
+    let mut packet = vec![0, 0, 0x84, 0, 0, 0, 0, 4, 0, 0, 0, additional];
+
+    let mut data = Vec::new();
+    write_name(&mut data, SERVICE);
+    write_record(&mut packet, SERVICES, TYPE_PTR, CLASS_IN, ttl, &data);
+
+    data.clear();
+    write_name(&mut data, &service.instance);
+    write_record(&mut packet, SERVICE, TYPE_PTR, CLASS_IN, ttl, &data);
+
+    data.clear();
+    data.extend_from_slice(&[0, 0, 0, 0]);
+    data.extend_from_slice(&service.port.to_be_bytes());
+    write_name(&mut data, &service.host);
+    write_record(
+        &mut packet,
+        &service.instance,
+        TYPE_SRV,
+        CLASS_IN | CACHE_FLUSH,
+        ttl,
+        &data,
+    );
+
+    data.clear();
+    for entry in &service.txt {
+        data.push(entry.len() as u8);
+        data.extend_from_slice(entry.as_bytes());
+    }
+    write_record(
+        &mut packet,
+        &service.instance,
+        TYPE_TXT,
+        CLASS_IN | CACHE_FLUSH,
+        ttl,
+        &data,
+    );
+
+    if additional > 0 {
+        write_record(
+            &mut packet,
+            &service.host,
+            TYPE_A,
+            CLASS_IN,
+            TTL,
+            &service.address.octets(),
+        );
+    }
+    packet
+}
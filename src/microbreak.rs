@@ -0,0 +1,118 @@
+use crate::{audio, events, notify::Event, user::SessionUser, util::Config};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::hash_map::RandomState,
+    env,
+    hash::{BuildHasher, Hasher},
+    process::{Command, Stdio},
+    sync::atomic::{AtomicU32, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Breaks prompted in this session, for the session history.
+static TAKEN: AtomicU32 = AtomicU32::new(0);
+
+/// Short eye and stretch breaks at random intervals; unlike pomodoro breaks, sites stay blocked.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MicroBreakConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_min_minutes")]
+    pub min_minutes: u64,
+    #[serde(default = "default_max_minutes")]
+    pub max_minutes: u64,
+    #[serde(default = "default_seconds")]
+    pub seconds: u64,
+    /// A chime from the data directory
+    #[serde(default)]
+    pub audio: Option<String>,
+    /// Dims the whole screen with the break countdown until the break is over
+    #[serde(default)]
+    pub overlay: bool,
+}
+
+impl Default for MicroBreakConfig {
+    fn default() -> Self {
+        MicroBreakConfig {
+            enabled: false,
+            min_minutes: default_min_minutes(),
+            max_minutes: default_max_minutes(),
+            seconds: default_seconds(),
+            audio: None,
+            overlay: false,
+        }
+    }
+}
+
+fn default_min_minutes() -> u64 {
+    20
+}
+
+fn default_max_minutes() -> u64 {
+    30
+}
+
+fn default_seconds() -> u64 {
+    20
+}
+
+/// Each `RandomState` hashes with fresh keys drawn from the system's random source.
+fn random_below(bound: u64) -> u64 {
+    RandomState::new().build_hasher().finish() % bound.max(1)
+}
+
+/// A random point between `min_minutes` and `max_minutes` from now, for the session timeline.
+pub fn next_due(config: &Config) -> Option<Instant> {
+    let breaks = &config.micro_breaks;
+    if !breaks.enabled {
+        return None;
+    }
+    let min = breaks.min_minutes.max(1) * 60;
+    let max = breaks.max_minutes.max(breaks.min_minutes).max(1) * 60;
+    Some(Instant::now() + Duration::from_secs(min + random_below(max - min + 1)))
+}
+
+pub fn prompt(config: &Config, background: bool) {
+    TAKEN.fetch_add(1, Ordering::SeqCst);
+    let seconds = config.micro_breaks.seconds;
+    println!(
+        "{}",
+        format!(
+            "[>] Micro-break: look away from the screen and stretch for {} seconds",
+            seconds
+        )
+        .bold()
+        .cyan()
+    );
+    events::publish(config, Event::MicroBreak { seconds });
+    if config.micro_breaks.overlay {
+        cover_screen(seconds);
+    }
+    if let Some(chime) = &config.micro_breaks.audio {
+        audio::play_cue(config, chime, "Micro-break", background);
+    }
+}
+
+/// Shows the break screen as the desktop user, whose display a root session can't open.
+fn cover_screen(seconds: u64) {
+    let Ok(executable) = env::current_exe() else {
+        return;
+    };
+    let spawned = SessionUser::from_sudo()
+        .map(|user| user.command(&executable))
+        .unwrap_or_else(|| Command::new(&executable))
+        .args(["break-screen", &seconds.to_string()])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if let Ok(mut child) = spawned {
+        thread::spawn(move || child.wait());
+    }
+}
+
+pub fn taken() -> u32 {
+    TAKEN.load(Ordering::SeqCst)
+}
@@ -0,0 +1,133 @@
+use crate::notify::{Event, Notifier};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const KEEP_ALIVE_SECONDS: u16 = 30;
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const RETAIN: u8 = 0x01;
+const DISCONNECT: u8 = 0xe0;
+const FLAG_USERNAME: u8 = 0x80;
+const FLAG_PASSWORD: u8 = 0x40;
+const FLAG_CLEAN_SESSION: u8 = 0x02;
+
+/// Publishes each event as JSON to an MQTT 3.1.1 broker with QoS 0, over plain TCP.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MqttConfig {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default = "default_topic")]
+    pub topic: String,
+    #[serde(default = "default_client_id")]
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Keeps the last event on the broker for clients that subscribe later
+    #[serde(default)]
+    pub retain: bool,
+}
+
+fn default_port() -> u16 {
+    1883
+}
+
+fn default_topic() -> String {
+    String::from("focus/events")
+}
+
+fn default_client_id() -> String {
+    String::from("focus")
+}
+
+impl Notifier for MqttConfig {
+    fn notify(&self, event: &Event) {
+        if let Err(e) = publish(self, event.payload().to_string().as_bytes()) {
+            eprintln!(
+                "{}",
+                format!("[!] Failed to publish MQTT message: {}", e)
+                    .bold()
+                    .yellow()
+            );
+        }
+    }
+}
+
+fn publish(config: &MqttConfig, payload: &[u8]) -> io::Result<()> {
+    let address = (config.host.as_str(), config.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::other(format!("could not resolve {}", config.host)))?;
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    let mut flags = FLAG_CLEAN_SESSION;
+    let mut connect = Vec::new();
+    put_string(&mut connect, b"MQTT");
+    connect.push(4);
+    let flags_at = connect.len();
+    connect.push(0);
+    connect.extend_from_slice(&KEEP_ALIVE_SECONDS.to_be_bytes());
+    put_string(&mut connect, config.client_id.as_bytes());
+    if let Some(username) = &config.username {
+        flags |= FLAG_USERNAME;
+        put_string(&mut connect, username.as_bytes());
+    }
+    if let Some(password) = &config.password {
+        flags |= FLAG_PASSWORD;
+        put_string(&mut connect, password.as_bytes());
+    }
+    connect[flags_at] = flags;
+    stream.write_all(&packet(CONNECT, &connect))?;
+
+    let mut connack = [0u8; 4];
+    stream.read_exact(&mut connack)?;
+    if connack[0] != CONNACK || connack[3] != 0 {
+        return Err(io::Error::other(format!(
+            "broker refused the connection (code {})",
+            connack[3]
+        )));
+    }
+
+    let mut publish = Vec::new();
+    put_string(&mut publish, config.topic.as_bytes());
+    publish.extend_from_slice(payload);
+    let header = if config.retain {
+        PUBLISH | RETAIN
+    } else {
+        PUBLISH
+    };
+    stream.write_all(&packet(header, &publish))?;
+    stream.write_all(&[DISCONNECT, 0])
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    let mut length = body.len();
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+fn put_string(buffer: &mut Vec<u8>, value: &[u8]) {
+    buffer.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    buffer.extend_from_slice(value);
+}
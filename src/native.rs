@@ -0,0 +1,177 @@
+use crate::{
+    dns, platform, proxy, session,
+    util::{self, Config},
+};
+use clap::ValueEnum;
+use colored::Colorize;
+use serde::Deserialize;
+use serde_json::{Value, json};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    process,
+};
+
+const HOST_NAME: &str = "io.github.gabriel_sparkes.focus";
+const WRAPPER_FILE: &str = "native-host";
+const MAX_MESSAGE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Browser {
+    Chrome,
+    Firefox,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Request {
+    Status,
+    Blocklist,
+    Check { url: String },
+}
+
+/// Serves length-prefixed JSON messages on stdin/stdout until the browser closes the pipe.
+pub fn run() {
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+
+    while let Some(message) = read_message(&mut stdin) {
+        let response = match serde_json::from_slice::<Request>(&message) {
+            Ok(request) => match util::load_config() {
+                Ok(config) => handle(&config, request),
+                Err(e) => json!({ "error": format!("could not load config: {}", e) }),
+            },
+            Err(e) => json!({ "error": format!("invalid request: {}", e) }),
+        };
+        if write_message(&mut stdout, &response).is_err() {
+            break;
+        }
+    }
+}
+
+fn handle(config: &Config, request: Request) -> Value {
+    match request {
+        Request::Status => status(config),
+        Request::Blocklist => json!({
+            "sites": util::effective_sites(config),
+            "rules": config.proxy.rules,
+        }),
+        Request::Check { url } => json!({ "blocked": is_url_blocked(config, &url) }),
+    }
+}
+
+fn status(config: &Config) -> Value {
+    let state = session::load_state(config).filter(|state| platform::is_alive(state.pid));
+    match state {
+        Some(state) => {
+            let remaining = session::remaining_seconds(config, &state);
+            json!({
+                "active": true,
+                "start": state.start,
+                "planned_minutes": state.planned_minutes,
+                "remaining_seconds": remaining,
+                "tag": state.tag,
+            })
+        }
+        None => json!({ "active": false }),
+    }
+}
+
+fn is_url_blocked(config: &Config, url: &str) -> bool {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = host.rsplit_once(':').map_or(host, |(host, _)| host);
+
+    if dns::is_blocked(&util::effective_sites(config), host) {
+        return true;
+    }
+    proxy::matching_rule(&config.proxy, host).is_some_and(|rule| {
+        !rule
+            .allow_paths
+            .iter()
+            .any(|allowed| path.starts_with(allowed.as_str()))
+    })
+}
+
+fn read_message(reader: &mut impl Read) -> Option<Vec<u8>> {
+    let mut length = [0u8; 4];
+    reader.read_exact(&mut length).ok()?;
+    let length = u32::from_ne_bytes(length) as usize;
+    if length > MAX_MESSAGE {
+        return None;
+    }
+    let mut message = vec![0u8; length];
+    reader.read_exact(&mut message).ok()?;
+    Some(message)
+}
+
+fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = serde_json::to_vec(message)?;
+    writer.write_all(&(content.len() as u32).to_ne_bytes())?;
+    writer.write_all(&content)?;
+    writer.flush()
+}
+
+/// Writes the launcher script browsers execute and prints the host manifest for the extension.
+pub fn print_manifest(browser: Browser, extension: &str) {
+    let wrapper = format!("{}/{}", util::config_directory(), WRAPPER_FILE);
+    let executable = env::current_exe().expect("[!] Could not locate the focus executable");
+    let script = format!(
+        "#!/bin/sh\nexec \"{}\" native-host \"$@\"\n",
+        executable.display()
+    );
+    let written = fs::write(&wrapper, script);
+    #[cfg(unix)]
+    let written =
+        written.and_then(|_| fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)));
+    if let Err(e) = written {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to write {}: {}", wrapper, e)
+                .bold()
+                .red()
+        );
+        process::exit(1);
+    }
+
+    let (allowed_key, allowed, directory) = match browser {
+        Browser::Chrome => (
+            "allowed_origins",
+            format!("chrome-extension://{}/", extension),
+            "~/.config/google-chrome/NativeMessagingHosts",
+        ),
+        Browser::Firefox => (
+            "allowed_extensions",
+            extension.to_string(),
+            "~/.mozilla/native-messaging-hosts",
+        ),
+    };
+    let manifest = json!({
+        "name": HOST_NAME,
+        "description": "focus session state and blocklist",
+        "path": wrapper,
+        "type": "stdio",
+        allowed_key: [allowed],
+    });
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&manifest).expect("[!] Could not encode manifest")
+    );
+    eprintln!(
+        "{}",
+        format!(
+            "[>] Save the manifest above as {}/{}.json",
+            directory, HOST_NAME
+        )
+        .bold()
+        .cyan()
+    );
+}
@@ -0,0 +1,239 @@
+use crate::{
+    dbus, desktop::DesktopNotifier, matrix::MatrixConfig, mqtt::MqttConfig, ntfy::NtfyConfig,
+    stats, telegram::TelegramConfig, util::Config, webhook::WebhookConfig,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub enum Event {
+    SessionStarted {
+        minutes: u64,
+        /// Picked up again after a restart rather than newly started
+        resumed: bool,
+    },
+    Halfway {
+        minutes: u64,
+    },
+    WrappingUp {
+        minutes: u64,
+    },
+    SessionEnded {
+        journal: Option<String>,
+    },
+    SessionStopped {
+        /// `interrupted` from the session itself, `stopped` from `focus stop`
+        outcome: &'static str,
+    },
+    TamperDetected,
+    /// A blocked site was visited while onboarding only warns
+    WouldBlock {
+        site: String,
+    },
+    MicroBreak {
+        seconds: u64,
+    },
+    Summary {
+        weekly: bool,
+        focused_minutes: u64,
+        sessions: usize,
+        /// Most blocked domains with their attempt counts
+        top_blocked: Vec<(String, usize)>,
+    },
+}
+
+/// A channel session events are delivered to. New channels implement this and add
+/// a `NotifierConfig` variant; nothing that sends events needs to change.
+pub trait Notifier {
+    fn notify(&self, event: &Event);
+}
+
+/// One entry of `notifiers` in the config, e.g. `{ type = "ntfy", topic = "focus" }`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum NotifierConfig {
+    Desktop,
+    Webhook(WebhookConfig),
+    Telegram(TelegramConfig),
+    Ntfy(NtfyConfig),
+    Mqtt(MqttConfig),
+    Matrix(MatrixConfig),
+}
+
+impl NotifierConfig {
+    fn notifier(&self) -> &dyn Notifier {
+        match self {
+            NotifierConfig::Desktop => &DesktopNotifier,
+            NotifierConfig::Webhook(webhook) => webhook,
+            NotifierConfig::Telegram(telegram) => telegram,
+            NotifierConfig::Ntfy(ntfy) => ntfy,
+            NotifierConfig::Mqtt(mqtt) => mqtt,
+            NotifierConfig::Matrix(matrix) => matrix,
+        }
+    }
+}
+
+impl Event {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::SessionStarted { .. } => "session-started",
+            Event::Halfway { .. } => "halfway",
+            Event::WrappingUp { .. } => "wrapping-up",
+            Event::SessionEnded { .. } => "session-ended",
+            Event::SessionStopped { .. } => "session-stopped",
+            Event::TamperDetected => "tamper-detected",
+            Event::WouldBlock { .. } => "would-block",
+            Event::MicroBreak { .. } => "micro-break",
+            Event::Summary { weekly: false, .. } => "daily-summary",
+            Event::Summary { weekly: true, .. } => "weekly-summary",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Event::SessionStarted { .. } => "Focus started",
+            Event::Halfway { .. } => "Focus halfway",
+            Event::WrappingUp { .. } => "Focus wrapping up",
+            Event::SessionEnded { .. } => "Focus complete",
+            Event::SessionStopped { .. } => "Focus stopped",
+            Event::TamperDetected => "Focus tamper detected",
+            Event::WouldBlock { .. } => "Focus onboarding",
+            Event::MicroBreak { .. } => "Focus micro-break",
+            Event::Summary { weekly: false, .. } => "Focus today",
+            Event::Summary { weekly: true, .. } => "Focus this week",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Event::SessionStarted { minutes, .. } => {
+                format!(
+                    "Focus session started: sites blocked for {} minutes",
+                    minutes
+                )
+            }
+            Event::Halfway { minutes } => {
+                format!("Halfway there: {} minutes to go", minutes)
+            }
+            Event::WrappingUp { minutes } => {
+                format!("Wrapping up: sites unblock in {} minutes", minutes)
+            }
+            Event::SessionEnded { .. } => String::from("Focus session complete: sites unblocked"),
+            Event::SessionStopped { .. } => String::from("Focus session stopped early"),
+            Event::TamperDetected => String::from("Tamper detected: sites were re-blocked"),
+            Event::WouldBlock { site } => {
+                format!("{} will be blocked once onboarding is over", site)
+            }
+            Event::MicroBreak { seconds } => format!(
+                "Look away from the screen and stretch for {} seconds",
+                seconds
+            ),
+            Event::Summary {
+                weekly,
+                focused_minutes,
+                sessions,
+                top_blocked,
+            } => {
+                let mut message = format!(
+                    "{} focused across {} session{} {}",
+                    stats::format_minutes(*focused_minutes),
+                    sessions,
+                    if *sessions == 1 { "" } else { "s" },
+                    if *weekly { "this week" } else { "today" }
+                );
+                if !top_blocked.is_empty() {
+                    let domains: Vec<String> = top_blocked
+                        .iter()
+                        .map(|(domain, count)| format!("{} ({})", domain, count))
+                        .collect();
+                    message.push_str(&format!(". Most blocked: {}", domains.join(", ")));
+                }
+                message
+            }
+        }
+    }
+
+    /// The block starting and ending and tampering, shown on the desktop whenever
+    /// `notifications` is on.
+    fn is_lifecycle(&self) -> bool {
+        matches!(
+            self,
+            Event::SessionStarted { .. }
+                | Event::SessionEnded { .. }
+                | Event::SessionStopped { .. }
+                | Event::TamperDetected
+        )
+    }
+
+    /// The JSON body for machine-readable channels.
+    pub fn payload(&self) -> serde_json::Value {
+        let minutes = match self {
+            Event::SessionStarted { minutes, .. }
+            | Event::Halfway { minutes }
+            | Event::WrappingUp { minutes } => Some(*minutes),
+            Event::Summary {
+                focused_minutes, ..
+            } => Some(*focused_minutes),
+            _ => None,
+        };
+        serde_json::json!({
+            "event": self.kind(),
+            "title": self.title(),
+            "message": self.message(),
+            "minutes": minutes,
+        })
+    }
+}
+
+/// Delivers an event to D-Bus and the notifiers; publish through `events` instead.
+pub fn send(config: &Config, event: &Event) {
+    if config.dbus.enabled {
+        dbus::emit(config, event);
+    }
+    if !config.notifications {
+        return;
+    }
+    // A `desktop` entry in `notifiers` asks for every event there instead
+    if event.is_lifecycle()
+        && !config
+            .notifiers
+            .iter()
+            .any(|notifier| matches!(notifier, NotifierConfig::Desktop))
+    {
+        DesktopNotifier.notify(event);
+    }
+    for notifier in notifiers(config) {
+        notifier.notify(event);
+    }
+}
+
+/// Everything in `notifiers`, plus the single-channel sections that predate it.
+fn notifiers(config: &Config) -> Vec<&dyn Notifier> {
+    let mut notifiers: Vec<&dyn Notifier> = config
+        .notifiers
+        .iter()
+        .map(NotifierConfig::notifier)
+        .collect();
+    if let Some(telegram) = &config.telegram {
+        notifiers.push(telegram);
+    }
+    if let Some(ntfy) = &config.ntfy {
+        notifiers.push(ntfy);
+    }
+    if let Some(matrix) = &config.matrix {
+        notifiers.push(matrix);
+    }
+    notifiers
+}
+
+pub fn http_agent() -> ureq::Agent {
+    http_agent_with_timeout(HTTP_TIMEOUT)
+}
+
+pub fn http_agent_with_timeout(timeout: Duration) -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .build()
+        .into()
+}
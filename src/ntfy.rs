@@ -0,0 +1,48 @@
+use crate::notify::{self, Event, Notifier};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NtfyConfig {
+    #[serde(default = "default_server")]
+    pub server: String,
+    pub topic: String,
+    #[serde(default = "default_priority")]
+    pub priority: String,
+    pub access_token: Option<String>,
+}
+
+fn default_server() -> String {
+    String::from("https://ntfy.sh")
+}
+
+fn default_priority() -> String {
+    String::from("default")
+}
+
+impl Notifier for NtfyConfig {
+    fn notify(&self, event: &Event) {
+        publish(self, event.title(), &event.message());
+    }
+}
+
+pub fn publish(config: &NtfyConfig, title: &str, message: &str) {
+    let url = format!("{}/{}", config.server.trim_end_matches('/'), config.topic);
+    let mut request = notify::http_agent()
+        .post(&url)
+        .header("Title", title)
+        .header("Priority", &config.priority)
+        .header("Tags", "hourglass");
+    if let Some(token) = &config.access_token {
+        request = request.header("Authorization", &format!("Bearer {}", token));
+    }
+
+    if let Err(e) = request.send(message) {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to publish ntfy notification: {}", e)
+                .bold()
+                .yellow()
+        );
+    }
+}
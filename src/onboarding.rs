@@ -0,0 +1,271 @@
+use crate::{
+    embed::{self, Backend},
+    events,
+    notify::Event,
+    stats::{self, QueryRecord},
+    util::{self, Config},
+};
+use chrono::{Days, Local, NaiveDate};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    sync::Mutex,
+};
+
+const STATE_FILE: &str = "focus.onboarding";
+const REPORT_DOMAINS: usize = 10;
+
+/// Sites already announced this session, so each visit doesn't notify again.
+static NOTIFIED: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+/// A first stretch of sessions that only log and notify about blocked sites, to tune the
+/// list before anything is blocked.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct OnboardingConfig {
+    /// Days of warn-only sessions, counted from the first one; 0 blocks from the start
+    #[serde(default)]
+    pub days: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct State {
+    started: NaiveDate,
+    /// Set once the report was shown and sessions block
+    #[serde(default)]
+    finished: bool,
+}
+
+pub fn state_path(config: &Config) -> String {
+    format!("{}/{}", config.log_directory, STATE_FILE)
+}
+
+fn load(config: &Config) -> Option<State> {
+    fs::read_to_string(state_path(config))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+/// The first day sessions block again, or None if that is past the end of the calendar.
+fn end_date(config: &Config, state: &State) -> Option<NaiveDate> {
+    state
+        .started
+        .checked_add_days(Days::new(config.onboarding.days))
+}
+
+fn save(config: &Config, state: &State) {
+    let content = serde_json::to_string(state).expect("[!] Could not encode onboarding state");
+    if let Err(e) = fs::write(state_path(config), content) {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to save onboarding state: {}", e)
+                .bold()
+                .red()
+        );
+    }
+}
+
+/// Turns the session into a warn-only one while onboarding lasts, and shows the report and
+/// starts blocking once it is over.
+pub fn apply(config: &mut Config) {
+    if config.onboarding.days == 0 {
+        return;
+    }
+    let today = Local::now().date_naive();
+    let state = load(config).unwrap_or_else(|| {
+        let state = State {
+            started: today,
+            finished: false,
+        };
+        save(config, &state);
+        state
+    });
+    if state.finished {
+        return;
+    }
+    if end_date(config, &state).is_some_and(|end| today >= end) {
+        finish(config, state);
+        return;
+    }
+    config.warn_only = true;
+    // Only the sinkhole sees visits without blocking them
+    embed::apply_backends(config, &[Backend::Dns]);
+    println!(
+        "{}",
+        format!(
+            "[>] Onboarding, day {} of {}: visits to blocked sites are logged, not blocked",
+            (today - state.started).num_days() + 1,
+            config.onboarding.days
+        )
+        .bold()
+        .cyan()
+    );
+    println!(
+        "{}",
+        format!(
+            "[>] Visits are seen through the DNS sinkhole on {}; the system resolver must use it",
+            config.dns.listen
+        )
+        .bold()
+        .cyan()
+    );
+}
+
+/// Called by the sinkhole for each visit it lets through during onboarding.
+pub fn notice(config: &Config, site: &str) {
+    let site = site.trim_end_matches('.').to_lowercase();
+    let first = NOTIFIED
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashSet::new)
+        .insert(site.clone());
+    if first {
+        println!(
+            "{}",
+            format!("[!] {} would have been blocked", site)
+                .bold()
+                .yellow()
+        );
+        events::publish(config, Event::WouldBlock { site });
+    }
+}
+
+/// Shows the report and blocks from the next session on; `focus onboarding --end`.
+pub fn end(config: &Config) {
+    match load(config) {
+        Some(state) if !state.finished => finish(config, state),
+        _ => println!(
+            "{}",
+            "[>] Not onboarding; sessions already block".bold().cyan()
+        ),
+    }
+}
+
+fn finish(config: &Config, mut state: State) {
+    report(config, state.started);
+    state.finished = true;
+    save(config, &state);
+    println!(
+        "{}",
+        "[+] Onboarding is over; sessions block from now on"
+            .bold()
+            .green()
+    );
+}
+
+/// Prints how onboarding is going; `focus onboarding`.
+pub fn status(config: &Config) {
+    let Some(state) = load(config) else {
+        if config.onboarding.days == 0 {
+            println!(
+                "{}",
+                "[>] Onboarding is off; set [onboarding] days to try the blocklist first"
+                    .bold()
+                    .cyan()
+            );
+        } else {
+            println!(
+                "{}",
+                format!(
+                    "[>] Onboarding starts with the next session and lasts {} days",
+                    config.onboarding.days
+                )
+                .bold()
+                .cyan()
+            );
+        }
+        return;
+    };
+    if state.finished {
+        println!(
+            "{}",
+            format!(
+                "[>] Onboarding ended; it started on {}",
+                state.started.format("%Y-%m-%d")
+            )
+            .bold()
+            .cyan()
+        );
+        return;
+    }
+    println!(
+        "{}",
+        format!(
+            "[>] Onboarding since {}; blocking starts on {}",
+            state.started.format("%Y-%m-%d"),
+            end_date(config, &state).map_or_else(
+                || String::from("never"),
+                |end| end.format("%Y-%m-%d").to_string()
+            )
+        )
+        .bold()
+        .cyan()
+    );
+    report(config, state.started);
+}
+
+/// What onboarding saw: blocked sites by visits, listed sites nobody visited and the most
+/// visited sites that aren't blocked, the candidates for adding.
+fn report(config: &Config, since: NaiveDate) {
+    let queries: Vec<QueryRecord> = stats::load_queries(config)
+        .into_iter()
+        .filter(|query| query.timestamp.date_naive() >= since)
+        .collect();
+    let count = |blocked: bool| {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for query in queries.iter().filter(|query| query.blocked == blocked) {
+            *counts.entry(&query.domain).or_default() += 1;
+        }
+        let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        counts
+    };
+
+    let visited = count(true);
+    println!(
+        "{}",
+        format!(
+            "[+] Blocked sites visited during onboarding ({} visits)",
+            visited.iter().map(|(_, visits)| visits).sum::<usize>()
+        )
+        .bold()
+        .green()
+    );
+    for (domain, visits) in visited.iter().take(REPORT_DOMAINS) {
+        println!("    {:>6}  {}", visits, domain);
+    }
+
+    let unvisited: Vec<String> = util::effective_sites(config)
+        .into_iter()
+        .filter(|site| {
+            !visited
+                .iter()
+                .any(|(domain, _)| *domain == site || domain.ends_with(&format!(".{}", site)))
+        })
+        .collect();
+    if !unvisited.is_empty() {
+        println!(
+            "{}",
+            "[>] Blocked but never visited; keep them or remove them with `focus remove`"
+                .bold()
+                .cyan()
+        );
+        for site in unvisited {
+            println!("    {}", site);
+        }
+    }
+
+    let unblocked = count(false);
+    if !unblocked.is_empty() {
+        println!(
+            "{}",
+            "[>] Most visited sites that aren't blocked; add any with `focus add`"
+                .bold()
+                .cyan()
+        );
+        for (domain, visits) in unblocked.iter().take(REPORT_DOMAINS) {
+            println!("    {:>6}  {}", visits, domain);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use crate::{blockpage, mdns, session::Timer, util::Config};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Write,
+    net::{TcpListener, TcpStream},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(500);
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+const STATE_PATH: &str = "/state";
+
+/// A countdown page for streaming software, e.g. an OBS browser source.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OverlayConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_listen")]
+    pub listen: String,
+    /// Extra CSS appended to the page, e.g. to change the font or colours
+    #[serde(default)]
+    pub style: String,
+    /// Announces the overlay over mDNS so dashboards on the LAN find it; `listen` must
+    /// then be reachable from them, e.g. `0.0.0.0:7878`
+    #[serde(default)]
+    pub advertise: bool,
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        OverlayConfig {
+            enabled: false,
+            listen: default_listen(),
+            style: String::new(),
+            advertise: false,
+        }
+    }
+}
+
+fn default_listen() -> String {
+    String::from("127.0.0.1:7878")
+}
+
+#[derive(Serialize)]
+struct OverlayState<'a> {
+    remaining: u64,
+    paused: bool,
+    tag: Option<&'a str>,
+}
+
+pub fn start_server(
+    config: Arc<Config>,
+    running: Arc<AtomicBool>,
+    timer: Arc<Timer>,
+    tag: Option<String>,
+) {
+    let listener = match TcpListener::bind(&config.overlay.listen) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                format!(
+                    "[!] Failed to start overlay on {}: {}",
+                    config.overlay.listen, e
+                )
+                .bold()
+                .red()
+            );
+            return;
+        }
+    };
+    let _ = listener.set_nonblocking(true);
+    println!(
+        "{}",
+        format!("[>] Overlay served on http://{}", config.overlay.listen)
+            .bold()
+            .cyan()
+    );
+    if config.overlay.advertise
+        && let Ok(address) = listener.local_addr()
+    {
+        mdns::advertise(Arc::clone(&config), Arc::clone(&running), address);
+    }
+
+    let tag = Arc::new(tag);
+    thread::spawn(move || {
+        while running.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((client, _)) => {
+                    let config = Arc::clone(&config);
+                    let timer = Arc::clone(&timer);
+                    let tag = Arc::clone(&tag);
+                    thread::spawn(move || handle_client(&config, &timer, tag.as_deref(), client));
+                }
+                Err(_) => thread::sleep(ACCEPT_INTERVAL),
+            }
+        }
+    });
+}
+
+fn handle_client(config: &Config, timer: &Timer, tag: Option<&str>, mut client: TcpStream) {
+    let _ = client.set_nonblocking(false);
+    let _ = client.set_read_timeout(Some(READ_TIMEOUT));
+    let Some((target, _)) = blockpage::read_request(&mut client) else {
+        return;
+    };
+    if target == STATE_PATH {
+        let state = OverlayState {
+            remaining: timer.remaining().as_secs(),
+            paused: timer.is_paused(),
+            tag,
+        };
+        let body = serde_json::to_string(&state).unwrap_or_default();
+        respond(&mut client, "application/json", &body);
+    } else {
+        respond(
+            &mut client,
+            "text/html; charset=utf-8",
+            &overlay_page(config, timer, tag),
+        );
+    }
+}
+
+fn respond(client: &mut TcpStream, content_type: &str, body: &str) {
+    let _ = client.write_all(
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nCache-Control: no-store\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            content_type,
+            body.len(),
+            body
+        )
+        .as_bytes(),
+    );
+}
+
+/// Rendered with the current time so it is right even before the first poll.
+fn overlay_page(config: &Config, timer: &Timer, tag: Option<&str>) -> String {
+    let remaining = timer.remaining().as_secs();
+    format!(
+        "<!doctype html><html><head><meta charset=\"utf-8\"><title>Focus</title><style>\
+body{{margin:0;background:transparent;color:#fff;font-family:sans-serif;text-align:center;text-shadow:0 0 6px #000}}\
+#time{{font-size:20vw;font-variant-numeric:tabular-nums}}#tag{{font-size:6vw}}.paused #time{{opacity:.5}}{style}</style></head>\
+<body><div id=\"time\">{minutes}:{seconds:02}</div><div id=\"tag\">{tag}</div>\
+<script>function show(s){{var r=s.remaining;document.getElementById('time').textContent=Math.floor(r/60)+':'+String(r%60).padStart(2,'0');\
+document.getElementById('tag').textContent=s.tag||'';document.body.className=s.paused?'paused':'';}}\
+setInterval(function(){{fetch('{state}').then(function(r){{return r.json();}}).then(show).catch(function(){{document.getElementById('time').textContent='';}});}},1000);</script>\
+</body></html>",
+        style = config.overlay.style.replace("</", "<\\/"),
+        minutes = remaining / 60,
+        seconds = remaining % 60,
+        tag = blockpage::escape_html(tag.unwrap_or_default()),
+        state = STATE_PATH
+    )
+}
@@ -0,0 +1,84 @@
+use crate::{subscriptions, util::Config};
+use colored::Colorize;
+use std::{fs, process};
+
+const LISTS_DIRECTORY: &str = "lists";
+const EXTENSION: &str = "list";
+
+pub fn lists_path(config: &Config) -> String {
+    format!("{}/{}", config.data_directory, LISTS_DIRECTORY)
+}
+
+fn load(config: &Config, name: &str) -> Option<Vec<String>> {
+    let content =
+        fs::read_to_string(format!("{}/{}.{}", lists_path(config), name, EXTENSION)).ok()?;
+    Some(subscriptions::parse_sites(&content))
+}
+
+/// Every `*.list` in the lists directory with its entry count, sorted by name.
+fn installed(config: &Config) -> Vec<(String, usize)> {
+    let mut packs: Vec<(String, usize)> = fs::read_dir(lists_path(config))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != EXTENSION {
+                return None;
+            }
+            let name = path.file_stem()?.to_str()?.to_string();
+            let count = load(config, &name)?.len();
+            Some((name, count))
+        })
+        .collect();
+    packs.sort();
+    packs
+}
+
+/// Adds the sites of the named packs to this run's blocklist.
+pub fn apply(config: &mut Config, names: &[String]) {
+    for name in names {
+        let Some(sites) = load(config, name) else {
+            let known: Vec<String> = installed(config)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect();
+            eprintln!(
+                "{}",
+                format!(
+                    "[!] Unknown list '{}' (installed: {})",
+                    name,
+                    if known.is_empty() {
+                        String::from("none")
+                    } else {
+                        known.join(", ")
+                    }
+                )
+                .bold()
+                .red()
+            );
+            process::exit(1);
+        };
+        for site in sites {
+            if !config.pack_sites.contains(&site) {
+                config.pack_sites.push(site);
+            }
+        }
+    }
+}
+
+pub fn show_lists(config: &Config) {
+    let packs = installed(config);
+    if packs.is_empty() {
+        println!(
+            "{}",
+            format!("[>] No lists installed in {}", lists_path(config))
+                .bold()
+                .cyan()
+        );
+        return;
+    }
+    for (name, count) in packs {
+        println!("{} {}", name.bold(), format!("({} sites)", count).dimmed());
+    }
+}
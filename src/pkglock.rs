@@ -0,0 +1,75 @@
+use colored::Colorize;
+#[cfg(unix)]
+use std::{fs::File, mem, os::fd::AsRawFd};
+use std::{path::Path, thread, time::Duration};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+enum Lock {
+    /// A file the package manager holds a `fcntl` lock on while it works
+    Held(&'static str),
+    /// A file that only exists while the package manager works
+    Exists(&'static str),
+}
+
+const LOCKS: [(&str, Lock); 5] = [
+    ("apt", Lock::Held("/var/lib/dpkg/lock-frontend")),
+    ("dpkg", Lock::Held("/var/lib/dpkg/lock")),
+    ("rpm", Lock::Held("/var/lib/rpm/.rpm.lock")),
+    ("rpm", Lock::Held("/usr/lib/sysimage/rpm/.rpm.lock")),
+    ("pacman", Lock::Exists("/var/lib/pacman/db.lck")),
+];
+
+impl Lock {
+    fn is_taken(&self) -> bool {
+        match self {
+            Lock::Held(path) => is_held(path),
+            Lock::Exists(path) => Path::new(path).exists(),
+        }
+    }
+}
+
+/// The package manager in the middle of a transaction, if any.
+pub fn busy() -> Option<&'static str> {
+    LOCKS
+        .iter()
+        .find(|(_, lock)| lock.is_taken())
+        .map(|(name, _)| *name)
+}
+
+/// Waits out a running package transaction, so blocking doesn't cut off its downloads.
+pub fn wait_until_idle() {
+    let Some(name) = busy() else {
+        return;
+    };
+    println!(
+        "{}",
+        format!(
+            "[>] {} is installing packages; the session starts once it finishes",
+            name
+        )
+        .bold()
+        .cyan()
+    );
+    while busy().is_some() {
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(unix)]
+fn is_held(path: &str) -> bool {
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+    let mut lock: libc::flock = unsafe { mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as _;
+    lock.l_whence = libc::SEEK_SET as _;
+    let result = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETLK, &mut lock) };
+    result == 0 && lock.l_type != libc::F_UNLCK as libc::c_short
+}
+
+/// None of the package managers above run on Windows.
+#[cfg(target_os = "windows")]
+fn is_held(_path: &str) -> bool {
+    false
+}
@@ -0,0 +1,298 @@
+//! What differs between the systems focus runs on: where the hosts file is, how the DNS cache
+//! is flushed, where per-user runtime files go and how a session moves to the background.
+
+use crate::ritual::ChecklistResult;
+use std::{env, fs::File, io, process::Command};
+#[cfg(target_os = "windows")]
+use std::{
+    sync::{Arc, OnceLock},
+    thread,
+};
+
+/// Set in the session a Windows `--background` start relaunched, which is already detached
+#[cfg(target_os = "windows")]
+const DETACHED_VARIABLE: &str = "FOCUS_DETACHED";
+/// The checklist answered before relaunching, as the detached session has no terminal
+#[cfg(target_os = "windows")]
+const CHECKLIST_VARIABLE: &str = "FOCUS_CHECKLIST";
+
+/// The session's Ctrl-C handler, which `interrupt` runs itself as Windows has no signals
+#[cfg(target_os = "windows")]
+static INTERRUPT: OnceLock<Arc<dyn Fn() + Send + Sync>> = OnceLock::new();
+
+/// The commands flushing the system's DNS cache, run in order.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub const FLUSH_DNS: &[&[&str]] = &[&["resolvectl", "flush-caches"]];
+/// The directory cache, then the resolver daemon's own
+#[cfg(target_os = "macos")]
+pub const FLUSH_DNS: &[&[&str]] = &[
+    &["dscacheutil", "-flushcache"],
+    &["killall", "-HUP", "mDNSResponder"],
+];
+#[cfg(target_os = "windows")]
+pub const FLUSH_DNS: &[&[&str]] = &[&["ipconfig", "/flushdns"]];
+
+/// The hosts file used when the config doesn't set `hosts_path`.
+#[cfg(not(target_os = "windows"))]
+pub fn default_hosts_path() -> String {
+    String::from("/etc/hosts")
+}
+
+#[cfg(target_os = "windows")]
+pub fn default_hosts_path() -> String {
+    let root = env::var("SystemRoot").unwrap_or_else(|_| String::from(r"C:\Windows"));
+    format!(r"{}\System32\drivers\etc\hosts", root)
+}
+
+/// Runs the `FLUSH_DNS` commands, stopping at the first that can't be started.
+pub fn flush_dns() -> io::Result<()> {
+    for command in FLUSH_DNS {
+        Command::new(command[0]).args(&command[1..]).output()?;
+    }
+    Ok(())
+}
+
+/// The user's own runtime directory, for sockets and audio outside `/run/focus`.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn user_runtime_directory() -> String {
+    env::var("XDG_RUNTIME_DIR")
+        .unwrap_or_else(|_| format!("/run/user/{}", unsafe { libc::getuid() }))
+}
+
+/// The per-user `$TMPDIR` under /var/folders, as there is no /run/user.
+#[cfg(target_os = "macos")]
+pub fn user_runtime_directory() -> String {
+    env::temp_dir()
+        .to_string_lossy()
+        .trim_end_matches('/')
+        .to_string()
+}
+
+#[cfg(target_os = "windows")]
+pub fn user_runtime_directory() -> String {
+    env::var("LOCALAPPDATA")
+        .map(|data| format!(r"{}\Temp", data))
+        .unwrap_or_else(|_| env::temp_dir().to_string_lossy().into_owned())
+}
+
+/// Detaches the session from the terminal, writing its pid and output to the given files.
+#[cfg(not(target_os = "windows"))]
+pub fn background(
+    pid_path: &str,
+    working_directory: &str,
+    stdout: File,
+    stderr: File,
+    _checklist: Option<&ChecklistResult>,
+) {
+    #[cfg(target_os = "macos")]
+    if under_launchd() {
+        stay_in_foreground(pid_path, working_directory, stdout, stderr);
+        return;
+    }
+    daemonize::Daemonize::new()
+        .pid_file(pid_path)
+        .chroot("/")
+        .working_directory(working_directory)
+        .stdout(stdout)
+        .stderr(stderr)
+        .start()
+        .expect("[!] Error: daemonize failed");
+}
+
+/// launchd jobs must not fork, or launchd takes the job for finished and kills what is left,
+/// so they keep their process and only take on the pid and output files.
+#[cfg(target_os = "macos")]
+fn stay_in_foreground(pid_path: &str, working_directory: &str, stdout: File, stderr: File) {
+    use std::os::fd::AsRawFd;
+
+    std::fs::write(pid_path, std::process::id().to_string())
+        .expect("[!] Error: could not write the pid file");
+    let _ = env::set_current_dir(working_directory);
+    unsafe {
+        libc::dup2(stdout.as_raw_fd(), libc::STDOUT_FILENO);
+        libc::dup2(stderr.as_raw_fd(), libc::STDERR_FILENO);
+    }
+}
+
+/// Jobs launchd starts are its children, as launchd is pid 1.
+#[cfg(target_os = "macos")]
+fn under_launchd() -> bool {
+    unsafe { libc::getppid() == 1 }
+}
+
+/// Windows can't fork, so the session starts again as a detached process and this one exits.
+#[cfg(target_os = "windows")]
+pub fn background(
+    pid_path: &str,
+    working_directory: &str,
+    stdout: File,
+    stderr: File,
+    checklist: Option<&ChecklistResult>,
+) {
+    use std::{os::windows::process::CommandExt, process, process::Stdio};
+
+    const DETACHED_PROCESS: u32 = 0x0000_0008;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+
+    if is_detached() {
+        // The pid is written here rather than by the launcher, so the relaunched session's
+        // own check for a running one doesn't find itself
+        std::fs::write(pid_path, process::id().to_string())
+            .expect("[!] Error: could not write the pid file");
+        return;
+    }
+    let mut command =
+        Command::new(env::current_exe().expect("[!] Error: could not find the focus executable"));
+    command
+        .args(env::args_os().skip(1))
+        .env(DETACHED_VARIABLE, "1")
+        .current_dir(working_directory)
+        .stdin(Stdio::null())
+        .stdout(stdout)
+        .stderr(stderr)
+        .creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+    if let Some(checklist) = checklist {
+        command.env(
+            CHECKLIST_VARIABLE,
+            serde_json::to_string(checklist).expect("[!] Could not encode the checklist"),
+        );
+    }
+    command
+        .spawn()
+        .expect("[!] Error: could not start the background session");
+    process::exit(0);
+}
+
+/// Whether this is the relaunched half of a Windows `--background` start.
+#[cfg(not(target_os = "windows"))]
+pub fn is_detached() -> bool {
+    false
+}
+
+#[cfg(target_os = "windows")]
+pub fn is_detached() -> bool {
+    env::var_os(DETACHED_VARIABLE).is_some()
+}
+
+/// The checklist the launcher answered, for a relaunched session.
+#[cfg(not(target_os = "windows"))]
+pub fn inherited_checklist() -> Option<ChecklistResult> {
+    None
+}
+
+#[cfg(target_os = "windows")]
+pub fn inherited_checklist() -> Option<ChecklistResult> {
+    serde_json::from_str(&env::var(CHECKLIST_VARIABLE).ok()?).ok()
+}
+
+/// Whether focus runs as root, or elevated on Windows, and can edit the hosts file itself.
+#[cfg(not(target_os = "windows"))]
+pub fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(target_os = "windows")]
+pub fn is_root() -> bool {
+    #[link(name = "shell32")]
+    unsafe extern "system" {
+        fn IsUserAnAdmin() -> i32;
+    }
+    unsafe { IsUserAnAdmin() != 0 }
+}
+
+/// The user running focus, or 0 on Windows, which has no numeric user ids.
+#[cfg(not(target_os = "windows"))]
+pub fn user_id() -> u32 {
+    unsafe { libc::getuid() }
+}
+
+#[cfg(target_os = "windows")]
+pub fn user_id() -> u32 {
+    0
+}
+
+/// Installs the Ctrl-C handler that every way of stopping the session goes through.
+#[cfg(not(target_os = "windows"))]
+pub fn on_interrupt(handler: impl Fn() + Send + Sync + 'static) -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(handler)
+}
+
+#[cfg(target_os = "windows")]
+pub fn on_interrupt(handler: impl Fn() + Send + Sync + 'static) -> Result<(), ctrlc::Error> {
+    let handler: Arc<dyn Fn() + Send + Sync> = Arc::new(handler);
+    let _ = INTERRUPT.set(Arc::clone(&handler));
+    ctrlc::set_handler(move || handler())
+}
+
+/// Stops this session the way Ctrl-C does, so the admin and rate-limit checks still apply.
+#[cfg(not(target_os = "windows"))]
+pub fn interrupt() {
+    unsafe { libc::kill(std::process::id() as libc::pid_t, libc::SIGINT) };
+}
+
+/// A detached session has no console to send Ctrl-C to, so the handler runs directly.
+#[cfg(target_os = "windows")]
+pub fn interrupt() {
+    if let Some(handler) = INTERRUPT.get() {
+        let handler = Arc::clone(handler);
+        thread::spawn(move || handler());
+    }
+}
+
+/// Asks a session in a terminal to move to the background, see `controls::catch_detach_signal`.
+#[cfg(not(target_os = "windows"))]
+pub fn request_detach(pid: u32) -> bool {
+    unsafe { libc::kill(pid as libc::pid_t, libc::SIGUSR1) == 0 }
+}
+
+/// Windows has no signal to send; the session's `b` key still works.
+#[cfg(target_os = "windows")]
+pub fn request_detach(_pid: u32) -> bool {
+    false
+}
+
+/// Asks another process to exit, or kills it outright when `force` is set; false if it is gone.
+#[cfg(not(target_os = "windows"))]
+pub fn terminate(pid: u32, force: bool) -> bool {
+    let signal = if force { libc::SIGKILL } else { libc::SIGTERM };
+    unsafe { libc::kill(pid as libc::pid_t, signal) == 0 }
+}
+
+/// A background session has no window to close, and taskkill can only ask windows to close,
+/// so on Windows every process is killed outright.
+#[cfg(target_os = "windows")]
+pub fn terminate(pid: u32, _force: bool) -> bool {
+    Command::new("taskkill")
+        .args(["/F", "/PID", &pid.to_string()])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Whether a process is running; zombies left for their parent to reap don't count.
+#[cfg(target_os = "linux")]
+pub fn is_alive(pid: u32) -> bool {
+    std::fs::read_to_string(format!("/proc/{}/stat", pid))
+        .ok()
+        .and_then(|stat| {
+            stat.rsplit_once(')')
+                .map(|(_, rest)| !rest.trim_start().starts_with('Z'))
+        })
+        .unwrap_or(false)
+}
+
+/// Without /proc, a process exists when it can be signalled, or exists but belongs to root.
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn is_alive(pid: u32) -> bool {
+    let signalled = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0;
+    signalled || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(target_os = "windows")]
+pub fn is_alive(pid: u32) -> bool {
+    Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+        .output()
+        .is_ok_and(|output| {
+            String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid))
+        })
+}
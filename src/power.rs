@@ -0,0 +1,114 @@
+use crate::util::{CHECK_INTERVAL, Config};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Stdio},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+const POWER_SUPPLY_DIRECTORY: &str = "/sys/class/power_supply";
+/// Tamper checks on battery; a tampered hosts file is still caught within the minute
+const LOW_POWER_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// How early a milestone may fire so it shares a wakeup with others
+const LOW_POWER_SLACK: Duration = Duration::from_secs(30);
+/// Reading sysfs or asking UPower is a wakeup of its own, so the answer is kept a while
+const RECHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+static ON_BATTERY: Mutex<Option<(Instant, bool)>> = Mutex::new(None);
+
+/// When to cut wakeups so a laptop running focus all day stays out of powertop.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LowPower {
+    /// While running on battery
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Whether the session should wake up less right now.
+pub fn saving(config: &Config) -> bool {
+    match config.low_power {
+        LowPower::Auto => on_battery(),
+        LowPower::Always => true,
+        LowPower::Never => false,
+    }
+}
+
+/// How often the hosts file is checked for tampering.
+pub fn check_interval(config: &Config) -> Duration {
+    if saving(config) {
+        LOW_POWER_CHECK_INTERVAL
+    } else {
+        CHECK_INTERVAL
+    }
+}
+
+/// How early timed events may fire to be handled together, see `Timeline::set_slack`.
+pub fn slack(config: &Config) -> Duration {
+    if saving(config) {
+        LOW_POWER_SLACK
+    } else {
+        Duration::ZERO
+    }
+}
+
+pub fn on_battery() -> bool {
+    let mut cached = ON_BATTERY.lock().unwrap();
+    if let Some((checked, on_battery)) = *cached
+        && checked.elapsed() < RECHECK_INTERVAL
+    {
+        return on_battery;
+    }
+    let on_battery = from_sysfs().or_else(from_upower).unwrap_or(false);
+    *cached = Some((Instant::now(), on_battery));
+    on_battery
+}
+
+/// On battery when no charger is online and a battery is discharging; `None` on machines
+/// without power supply entries, such as some VMs.
+fn from_sysfs() -> Option<bool> {
+    let supplies: Vec<_> = fs::read_dir(POWER_SUPPLY_DIRECTORY)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .collect();
+    if supplies.is_empty() {
+        return None;
+    }
+    let read = |supply: &Path, file: &str| {
+        fs::read_to_string(supply.join(file))
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    };
+    let charging = supplies
+        .iter()
+        .any(|supply| read(supply, "type") != "Battery" && read(supply, "online") == "1");
+    let discharging = supplies
+        .iter()
+        .any(|supply| read(supply, "type") == "Battery" && read(supply, "status") == "Discharging");
+    Some(!charging && discharging)
+}
+
+fn from_upower() -> Option<bool> {
+    let output = Command::new("dbus-send")
+        .args([
+            "--system",
+            "--print-reply",
+            "--dest=org.freedesktop.UPower",
+            "/org/freedesktop/UPower",
+            "org.freedesktop.DBus.Properties.Get",
+            "string:org.freedesktop.UPower",
+            "string:OnBattery",
+        ])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).contains("boolean true"))
+}
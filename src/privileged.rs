@@ -0,0 +1,438 @@
+use crate::hosts::{self, BlockInfo, Markers};
+use serde::{Deserialize, Serialize};
+#[cfg(unix)]
+use std::{
+    env,
+    io::{BufRead, BufReader},
+};
+use std::{
+    fs,
+    io::{self, Write},
+    net::IpAddr,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub const CONFIG_PATH: &str = "/usr/local/etc/focus/config.toml";
+/// Where macOS keeps system-wide settings, as /usr/local belongs to Homebrew there
+#[cfg(target_os = "macos")]
+pub const CONFIG_PATH: &str = "/Library/Application Support/focus/config.toml";
+/// Under `%ProgramData%`, where what an administrator writes stays out of users' reach
+#[cfg(target_os = "windows")]
+pub const CONFIG_PATH: &str = r"C:\ProgramData\focus\config.toml";
+pub const HELPER_NAME: &str = "focus-helper";
+pub const NFT_TABLE: &str = "focus_sni";
+/// Firewall blocks get one table per group, named `focus_block` or `focus_block_<group>`
+pub const FIREWALL_TABLE: &str = "focus_block";
+pub const PROXY_MARK: u32 = 0x464f;
+/// nft is never looked up through PATH, which the caller controls
+const NFT_PATHS: [&str; 3] = ["/usr/sbin/nft", "/sbin/nft", "/usr/bin/nft"];
+const WRITE_ATTEMPTS: u32 = 5;
+const WRITE_BACKOFF: Duration = Duration::from_millis(100);
+#[cfg(target_os = "linux")]
+const CAP_NET_ADMIN: u32 = 12;
+#[cfg(target_os = "linux")]
+const CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+
+/// Everything `focus-helper` will do for an unprivileged caller. The caller writes
+/// one JSON request per line to the helper's stdin and reads back `ok` or
+/// `error: <reason>`. Paths and the block IP come from the root-owned config,
+/// never from the request, so the helper can only ever touch the focus block in
+/// the configured hosts file and its own nftables table.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "kebab-case")]
+pub enum Request {
+    /// Replaces the focus block of a scope with these sites, at the end of the file
+    /// or, with `in_place`, where the block already is
+    Block {
+        scope: Option<String>,
+        info: Option<BlockInfo>,
+        sites: Vec<String>,
+        #[serde(default)]
+        in_place: bool,
+    },
+    /// Removes the focus block of a scope
+    Unblock {
+        scope: Option<String>,
+    },
+    /// Redirects outgoing HTTPS to the local SNI proxy
+    Redirect {
+        port: u16,
+    },
+    ClearRedirect,
+    /// Rejects outgoing traffic to these addresses, replacing the scope's earlier set
+    Firewall {
+        scope: Option<String>,
+        addresses: Vec<IpAddr>,
+    },
+    ClearFirewall {
+        scope: Option<String>,
+    },
+}
+
+/// The only settings the helper acts on, read by it from the root-owned config.
+#[derive(Debug, Deserialize)]
+pub struct Settings {
+    pub hosts_path: String,
+    pub block_ip: String,
+    #[serde(default)]
+    pub markers: Markers,
+}
+
+pub fn validate(request: &Request) -> Result<(), String> {
+    match request {
+        Request::Block {
+            scope, info, sites, ..
+        } => {
+            validate_scope(scope)?;
+            if let Some(info) = info
+                .as_ref()
+                .filter(|info| !hosts::is_valid_scope(&info.session))
+            {
+                return Err(format!("invalid session ID {:?}", info.session));
+            }
+            match sites.iter().find(|site| !hosts::is_valid_hostname(site)) {
+                Some(site) => Err(format!("refusing to block {:?}", site)),
+                None => Ok(()),
+            }
+        }
+        Request::Unblock { scope } => validate_scope(scope),
+        Request::Redirect { port: 0 | 443 } => Err(String::from("invalid redirect port")),
+        Request::Redirect { .. } | Request::ClearRedirect => Ok(()),
+        Request::Firewall { scope, addresses } => {
+            validate_scope(scope)?;
+            match addresses.iter().find(|address| !is_blockable(address)) {
+                Some(address) => Err(format!("refusing to block {}", address)),
+                None => Ok(()),
+            }
+        }
+        Request::ClearFirewall { scope } => validate_scope(scope),
+    }
+}
+
+/// Addresses a firewall block may never cover, since the machine itself needs them.
+pub fn is_blockable(address: &IpAddr) -> bool {
+    !(address.is_loopback() || address.is_unspecified() || address.is_multicast())
+}
+
+/// Whether the firewall table of a scope exists, i.e. nobody deleted it.
+pub fn has_firewall(scope: &Option<String>) -> bool {
+    run_nft(&["list", "table", "inet", &firewall_table(scope)], None).is_ok()
+}
+
+fn firewall_table(scope: &Option<String>) -> String {
+    match scope {
+        // nft names are safest without dashes
+        Some(group) => format!("{}_{}", FIREWALL_TABLE, group.replace('-', "_")),
+        None => String::from(FIREWALL_TABLE),
+    }
+}
+
+fn validate_scope(scope: &Option<String>) -> Result<(), String> {
+    match scope {
+        Some(group) if !hosts::is_valid_scope(group) => {
+            Err(format!("invalid group name {:?}", group))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Carries out a request in this process, which must have the needed privileges.
+pub fn execute(request: &Request, settings: &Settings) -> Result<(), String> {
+    validate(request)?;
+    if !settings.markers.is_valid() {
+        return Err(String::from(
+            "block markers must be distinct single-line comments",
+        ));
+    }
+    match request {
+        Request::Block {
+            scope,
+            info,
+            sites,
+            in_place,
+        } => {
+            settings
+                .block_ip
+                .parse::<IpAddr>()
+                .map_err(|_| format!("invalid block_ip {:?}", settings.block_ip))?;
+            let original = read_hosts(settings)?;
+            let write = if *in_place {
+                hosts::replace_block
+            } else {
+                hosts::set_block
+            };
+            let content = write(
+                &original,
+                &settings.markers,
+                scope.as_deref(),
+                info.as_ref(),
+                &settings.block_ip,
+                sites,
+            );
+            write_hosts(settings, &original, &content)
+        }
+        Request::Unblock { scope } => {
+            let original = read_hosts(settings)?;
+            let content = hosts::remove_block(&original, &settings.markers, scope.as_deref());
+            write_hosts(settings, &original, &content)
+        }
+        Request::Redirect { port } => {
+            let _ = run_nft(&["delete", "table", "inet", NFT_TABLE], None);
+            let rules = format!(
+                "table inet {table} {{
+                    chain output {{
+                        type nat hook output priority -100;
+                        meta mark {mark} return
+                        tcp dport 443 redirect to :{port}
+                    }}
+                }}",
+                table = NFT_TABLE,
+                mark = PROXY_MARK,
+                port = port
+            );
+            run_nft(&["-f", "-"], Some(&rules))
+        }
+        Request::ClearRedirect => run_nft(&["delete", "table", "inet", NFT_TABLE], None),
+        Request::Firewall { scope, addresses } => {
+            let table = firewall_table(scope);
+            let set = |version: &str, addresses: Vec<String>| {
+                let elements = if addresses.is_empty() {
+                    String::new()
+                } else {
+                    format!("elements = {{ {} }}", addresses.join(", "))
+                };
+                format!(
+                    "set {version} {{ type {version}_addr; {elements} }}",
+                    version = version,
+                    elements = elements
+                )
+            };
+            let (v4, v6): (Vec<&IpAddr>, Vec<&IpAddr>) =
+                addresses.iter().partition(|address| address.is_ipv4());
+            // Declaring the table first lets the delete succeed on the first block too,
+            // and nft applies the whole script or nothing
+            let rules = format!(
+                "table inet {table} {{}}
+                delete table inet {table}
+                table inet {table} {{
+                    {v4}
+                    {v6}
+                    chain output {{
+                        type filter hook output priority 0;
+                        ip daddr @ipv4 reject
+                        ip6 daddr @ipv6 reject
+                    }}
+                }}",
+                table = table,
+                v4 = set(
+                    "ipv4",
+                    v4.iter().map(|address| address.to_string()).collect()
+                ),
+                v6 = set(
+                    "ipv6",
+                    v6.iter().map(|address| address.to_string()).collect()
+                ),
+            );
+            run_nft(&["-f", "-"], Some(&rules))
+        }
+        Request::ClearFirewall { scope } => {
+            let table = firewall_table(scope);
+            let rules = format!(
+                "table inet {table} {{}}\ndelete table inet {table}",
+                table = table
+            );
+            run_nft(&["-f", "-"], Some(&rules))
+        }
+    }
+}
+
+/// Sends a request to the helper installed next to the current executable.
+#[cfg(unix)]
+pub fn call_helper(request: &Request) -> Result<(), String> {
+    let helper = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|parent| parent.join(HELPER_NAME)))
+        .filter(|helper| helper.exists())
+        .ok_or_else(|| format!("{} is not installed; run focus as root", HELPER_NAME))?;
+    let mut child = Command::new(&helper)
+        .env_clear()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run {}: {}", helper.display(), e))?;
+
+    let line = serde_json::to_string(request).map_err(|e| e.to_string())?;
+    let mut stdin = child.stdin.take().expect("[!] helper stdin unavailable");
+    let reply = writeln!(stdin, "{}", line)
+        .map_err(|e| e.to_string())
+        .and_then(|_| {
+            drop(stdin);
+            let mut reply = String::new();
+            BufReader::new(child.stdout.take().expect("[!] helper stdout unavailable"))
+                .read_line(&mut reply)
+                .map_err(|e| e.to_string())?;
+            Ok(reply)
+        });
+    let _ = child.wait();
+
+    match reply?.trim() {
+        "ok" => Ok(()),
+        reply => Err(reply.strip_prefix("error: ").unwrap_or(reply).to_string()),
+    }
+}
+
+/// Windows has no setuid or file capabilities for a helper to be installed with.
+#[cfg(target_os = "windows")]
+pub fn call_helper(_request: &Request) -> Result<(), String> {
+    Err(String::from(
+        "changing the hosts file needs an elevated prompt; run focus as administrator",
+    ))
+}
+
+/// Where the hosts file really lives when it is a symlink (NixOS, containers). Following
+/// a dangling link would create a new file, so that is refused instead.
+pub fn resolve_hosts(path: &str) -> Result<PathBuf, String> {
+    let metadata = fs::symlink_metadata(path).map_err(|e| format!("{}: {}", path, e))?;
+    if !metadata.file_type().is_symlink() {
+        return Ok(PathBuf::from(path));
+    }
+    fs::canonicalize(path).map_err(|e| format!("{} is a symlink to a missing file: {}", path, e))
+}
+
+fn read_hosts(settings: &Settings) -> Result<String, String> {
+    let target = resolve_hosts(&settings.hosts_path)?;
+    fs::read_to_string(&target).map_err(|e| format!("{}: {}", target.display(), e))
+}
+
+/// Writes the new hosts content, putting the original back if the write never succeeds
+/// so a failure cannot leave a truncated file or half a block behind.
+fn write_hosts(settings: &Settings, original: &str, content: &str) -> Result<(), String> {
+    let target = resolve_hosts(&settings.hosts_path)?;
+    let Err(e) = write_with_retries(&target, content) else {
+        return Ok(());
+    };
+    let e = match e.raw_os_error() {
+        Some(libc::EROFS) if target != Path::new(&settings.hosts_path) => format!(
+            "{} links to a read-only file; point hosts_path at a writable hosts file",
+            settings.hosts_path
+        ),
+        _ => e.to_string(),
+    };
+    match write_with_retries(&target, original) {
+        Ok(()) => Err(format!("{}: {} (left unchanged)", target.display(), e)),
+        Err(restore) => Err(format!(
+            "{}: {}; restoring the original also failed: {}",
+            target.display(),
+            e,
+            restore
+        )),
+    }
+}
+
+/// Rewrites the file in place rather than renaming over it, so a symlink or a
+/// bind-mounted hosts file keeps pointing where it did.
+fn write_with_retries(path: &Path, content: &str) -> io::Result<()> {
+    let mut backoff = WRITE_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match fs::write(path, content) {
+            Err(e) if attempt < WRITE_ATTEMPTS && is_transient(&e) => {
+                thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Errors another program holding the file or a briefly full disk can cause.
+fn is_transient(error: &io::Error) -> bool {
+    matches!(
+        error.raw_os_error(),
+        Some(libc::EBUSY | libc::ETXTBSY | libc::EAGAIN | libc::EINTR | libc::ENOSPC)
+    )
+}
+
+/// Where `nft` is installed, if it is.
+pub fn nft_path() -> Option<&'static str> {
+    NFT_PATHS.into_iter().find(|path| Path::new(path).exists())
+}
+
+fn run_nft(args: &[&str], input: Option<&str>) -> Result<(), String> {
+    let nft = nft_path().ok_or("nft is not installed")?;
+    raise_ambient_net_admin();
+    let output = Command::new(nft)
+        .args(args)
+        .env_clear()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            let mut stdin = child.stdin.take().expect("[!] nft stdin unavailable");
+            if let Some(input) = input {
+                stdin.write_all(input.as_bytes())?;
+            }
+            drop(stdin);
+            child.wait_with_output()
+        })
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct CapabilityHeader {
+    version: u32,
+    pid: libc::c_int,
+}
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct CapabilityData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+/// A helper installed with file capabilities only passes CAP_NET_ADMIN on to nft
+/// once it is ambient; under setuid root this is a no-op.
+#[cfg(target_os = "linux")]
+fn raise_ambient_net_admin() {
+    let mut header = CapabilityHeader {
+        version: CAPABILITY_VERSION_3,
+        pid: 0,
+    };
+    let mut data = [CapabilityData::default(); 2];
+    unsafe {
+        if libc::syscall(libc::SYS_capget, &mut header, data.as_mut_ptr()) != 0
+            || data[0].permitted & (1 << CAP_NET_ADMIN) == 0
+        {
+            return;
+        }
+        data[0].inheritable |= 1 << CAP_NET_ADMIN;
+        if libc::syscall(libc::SYS_capset, &header, data.as_ptr()) != 0 {
+            return;
+        }
+        libc::prctl(
+            libc::PR_CAP_AMBIENT,
+            libc::PR_CAP_AMBIENT_RAISE,
+            CAP_NET_ADMIN as libc::c_ulong,
+            0,
+            0,
+        );
+    }
+}
+
+/// Capabilities are Linux's; nft doesn't run anywhere else.
+#[cfg(not(target_os = "linux"))]
+fn raise_ambient_net_admin() {}
@@ -0,0 +1,135 @@
+use crate::{
+    packs,
+    util::{Args, Config},
+};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::process;
+
+/// Session settings that override the top-level config when started with `--profile`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Profile {
+    pub duration: Option<u64>,
+    /// An empty string plays nothing
+    pub start_audio: Option<String>,
+    pub end_audio: Option<String>,
+    pub ambient_audio: Option<String>,
+    pub notifications: Option<bool>,
+    pub strict: Option<bool>,
+    /// Blocklist packs from `data_directory/lists/`, e.g. ["social", "crypto-news"]
+    pub lists: Vec<String>,
+}
+
+/// A named way to start a session, e.g. `writing = { profile = "deep", duration = 90, tag = "book" }`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Template {
+    pub profile: Option<String>,
+    pub duration: Option<u64>,
+    pub tag: Option<String>,
+}
+
+/// Turns `focus 25` or `focus writing` into the options they stand for; explicit
+/// `--profile` and `--tag` still win. A bare profile name works as a template too.
+pub fn apply_shortcut(config: &mut Config, args: &mut Args, shortcut: &str) {
+    if let Ok(minutes) = shortcut.parse::<u64>() {
+        config.duration = minutes;
+        args.duration = Some(minutes);
+        return;
+    }
+    let template = match config.templates.get(shortcut) {
+        Some(template) => {
+            println!(
+                "{}",
+                format!("[>] Using template '{}'", shortcut).bold().cyan()
+            );
+            template.clone()
+        }
+        None if config.profiles.contains_key(shortcut) => Template {
+            profile: Some(shortcut.to_string()),
+            ..Template::default()
+        },
+        None => {
+            let known: Vec<&str> = config.templates.keys().map(String::as_str).collect();
+            eprintln!(
+                "{}",
+                format!(
+                    "[!] '{}' is not a command, a number of minutes or a template (available: {})",
+                    shortcut,
+                    if known.is_empty() {
+                        String::from("none")
+                    } else {
+                        known.join(", ")
+                    }
+                )
+                .bold()
+                .red()
+            );
+            process::exit(1);
+        }
+    };
+    if let Some(duration) = template.duration {
+        config.duration = duration;
+        args.duration = Some(duration);
+    }
+    if args.profile.is_none() {
+        args.profile = template.profile;
+    }
+    if args.tag.is_none() {
+        args.tag = template.tag;
+    }
+}
+
+/// Applies the named profile to the in-memory config; the saved config is left alone.
+pub fn apply(config: &mut Config, name: &str) {
+    let Some(profile) = config.profiles.get(name).cloned() else {
+        let known: Vec<&str> = config.profiles.keys().map(String::as_str).collect();
+        eprintln!(
+            "{}",
+            format!(
+                "[!] Unknown profile '{}' (available: {})",
+                name,
+                if known.is_empty() {
+                    String::from("none")
+                } else {
+                    known.join(", ")
+                }
+            )
+            .bold()
+            .red()
+        );
+        process::exit(1);
+    };
+
+    if let Some(duration) = profile.duration {
+        config.duration = duration;
+    }
+    if let Some(start_audio) = profile.start_audio {
+        config.start_audio = start_audio;
+    }
+    if let Some(end_audio) = profile.end_audio {
+        config.end_audio = end_audio;
+    }
+    if let Some(ambient_audio) = profile.ambient_audio {
+        config.ambient_audio = Some(ambient_audio).filter(|path| !path.is_empty());
+    }
+    if let Some(notifications) = profile.notifications {
+        config.notifications = notifications;
+    }
+    if let Some(strict) = profile.strict {
+        config.strict = strict;
+    }
+    packs::apply(config, &profile.lists);
+    config.profile = Some(name.to_string());
+    println!(
+        "{}",
+        format!(
+            "[>] Using profile '{}'{}",
+            name,
+            if config.strict { " (strict)" } else { "" }
+        )
+        .bold()
+        .cyan()
+    );
+}
@@ -0,0 +1,87 @@
+use crate::{
+    hosts,
+    util::{Args, Config},
+};
+use colored::Colorize;
+use serde::Deserialize;
+use std::{env, fs, path::PathBuf, process};
+
+const FILE_NAME: &str = ".focus.toml";
+
+/// Per-project settings from the nearest `.focus.toml`, layered over the global config.
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Blocked on top of the global list, never in place of it
+    pub sites: Vec<String>,
+    pub tag: Option<String>,
+    pub duration: Option<u64>,
+    pub profile: Option<String>,
+}
+
+/// The nearest `.focus.toml` in the working directory or one of its parents.
+fn discover() -> Option<PathBuf> {
+    let mut directory = env::current_dir().ok()?;
+    loop {
+        let candidate = directory.join(FILE_NAME);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if !directory.pop() {
+            return None;
+        }
+    }
+}
+
+/// Applies the project file to this run only; command-line options still win.
+pub fn apply(config: &mut Config, args: &mut Args) {
+    let Some(path) = discover() else {
+        return;
+    };
+    let project: ProjectConfig = match fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| toml::from_str(&content).map_err(|e| e.to_string()))
+    {
+        Ok(project) => project,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                format!("[!] Error parsing {}: {}", path.display(), e)
+                    .bold()
+                    .red()
+            );
+            process::exit(1);
+        }
+    };
+
+    for site in project.sites {
+        if !hosts::is_valid_hostname(&site) {
+            eprintln!(
+                "{}",
+                format!("[!] Skipping invalid site in {}: {}", FILE_NAME, site)
+                    .bold()
+                    .yellow()
+            );
+        } else if !config.project_sites.contains(&site) {
+            config.project_sites.push(site);
+        }
+    }
+    if args.duration.is_none()
+        && args.until.is_none()
+        && let Some(duration) = project.duration
+    {
+        config.duration = duration;
+    }
+    if args.tag.is_none() {
+        args.tag = project.tag;
+    }
+    if args.profile.is_none() {
+        args.profile = project.profile;
+    }
+    println!(
+        "{}",
+        format!("[>] Using project settings from {}", path.display())
+            .bold()
+            .cyan()
+    );
+}
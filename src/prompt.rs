@@ -0,0 +1,145 @@
+use std::{
+    io::{self, Read, Write},
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+const POLL_TIMEOUT_MS: libc::c_int = 200;
+
+static STDIN_BYTES: OnceLock<Mutex<Receiver<u8>>> = OnceLock::new();
+static READER_SUSPENDED: AtomicBool = AtomicBool::new(false);
+#[cfg(unix)]
+static ORIGINAL_TERMINAL: Mutex<Option<libc::termios>> = Mutex::new(None);
+
+fn stdin_bytes() -> &'static Mutex<Receiver<u8>> {
+    STDIN_BYTES.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut stdin = io::stdin();
+            let mut byte = [0u8; 1];
+            loop {
+                if !stdin_ready() || READER_SUSPENDED.load(Ordering::SeqCst) {
+                    continue;
+                }
+                match stdin.read(&mut byte) {
+                    Ok(1) => {
+                        if sender.send(byte[0]).is_err() {
+                            break;
+                        }
+                    }
+                    _ => break,
+                }
+            }
+        });
+        Mutex::new(receiver)
+    })
+}
+
+/// Polling lets password prompts read the terminal without racing the reader thread.
+#[cfg(unix)]
+fn stdin_ready() -> bool {
+    let mut descriptor = libc::pollfd {
+        fd: libc::STDIN_FILENO,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    unsafe { libc::poll(&mut descriptor, 1, POLL_TIMEOUT_MS) > 0 }
+}
+
+/// Windows consoles can't be polled like this, so the reader thread blocks in `read`.
+#[cfg(target_os = "windows")]
+fn stdin_ready() -> bool {
+    true
+}
+
+pub fn read_line(question: &str, timeout: Option<Duration>) -> Option<String> {
+    print!("{}", question);
+    let _ = io::stdout().flush();
+
+    let receiver = stdin_bytes().lock().unwrap();
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut line = Vec::new();
+    loop {
+        let byte = match deadline {
+            Some(deadline) => receiver
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                .ok(),
+            None => receiver.recv().ok(),
+        };
+        match byte {
+            Some(b'\n') => return Some(String::from_utf8_lossy(&line).to_string()),
+            Some(byte) => line.push(byte),
+            None => {
+                println!();
+                return None;
+            }
+        }
+    }
+}
+
+pub fn confirm(question: &str, timeout: Option<Duration>) -> bool {
+    read_line(question, timeout)
+        .is_some_and(|answer| matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Waits for a single keypress; only unbuffered while the terminal is in cbreak mode.
+pub fn read_key(timeout: Duration) -> Option<char> {
+    stdin_bytes()
+        .lock()
+        .unwrap()
+        .recv_timeout(timeout)
+        .ok()
+        .map(char::from)
+}
+
+/// Stops the shared reader from consuming input while `f` reads the terminal directly.
+pub fn with_reader_suspended<T>(f: impl FnOnce() -> T) -> T {
+    READER_SUSPENDED.store(true, Ordering::SeqCst);
+    let result = f();
+    READER_SUSPENDED.store(false, Ordering::SeqCst);
+    result
+}
+
+pub fn is_reader_suspended() -> bool {
+    READER_SUSPENDED.load(Ordering::SeqCst)
+}
+
+/// Delivers keypresses immediately without echo while keeping Ctrl-C and output processing.
+#[cfg(unix)]
+pub fn enter_cbreak() -> bool {
+    if unsafe { libc::isatty(libc::STDIN_FILENO) } != 1 {
+        return false;
+    }
+    let mut terminal: libc::termios = unsafe { std::mem::zeroed() };
+    if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut terminal) } != 0 {
+        return false;
+    }
+    ORIGINAL_TERMINAL.lock().unwrap().get_or_insert(terminal);
+
+    terminal.c_lflag &= !(libc::ICANON | libc::ECHO);
+    terminal.c_cc[libc::VMIN] = 1;
+    terminal.c_cc[libc::VTIME] = 0;
+    unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &terminal) == 0 }
+}
+
+#[cfg(unix)]
+pub fn restore_terminal() {
+    if let Some(terminal) = ORIGINAL_TERMINAL.lock().unwrap().take() {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &terminal) };
+    }
+}
+
+/// Windows consoles have no termios, so sessions there run without the single-key controls.
+#[cfg(target_os = "windows")]
+pub fn enter_cbreak() -> bool {
+    false
+}
+
+#[cfg(target_os = "windows")]
+pub fn restore_terminal() {}
@@ -0,0 +1,269 @@
+use crate::{
+    audit, events,
+    notify::Event,
+    privileged::CONFIG_PATH,
+    util::{self, Config},
+};
+use colored::Colorize;
+use sha2::{Digest, Sha256};
+#[cfg(not(target_os = "linux"))]
+use std::time::{Duration, SystemTime};
+use std::{
+    env, fs,
+    path::Path,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+#[cfg(target_os = "linux")]
+use std::{ffi::CString, io, os::unix::ffi::OsStrExt};
+
+/// How long a read waits before checking whether the session ended, in milliseconds
+#[cfg(target_os = "linux")]
+const POLL_TIMEOUT: libc::c_int = 500;
+#[cfg(target_os = "linux")]
+const EVENT_BUFFER: usize = 4096;
+#[cfg(target_os = "linux")]
+const WATCHED: u32 = libc::IN_CREATE
+    | libc::IN_DELETE
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO
+    | libc::IN_CLOSE_WRITE
+    | libc::IN_DELETE_SELF
+    | libc::IN_MOVE_SELF;
+/// The watched directory itself went away, taking the watch with it
+#[cfg(target_os = "linux")]
+const GONE: u32 = libc::IN_DELETE_SELF | libc::IN_MOVE_SELF | libc::IN_IGNORED;
+
+/// Digests of configs focus announced it is about to save, kept when they land on disk.
+static EXPECTED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+/// How often the config and binary are compared where there is no inotify
+#[cfg(not(target_os = "linux"))]
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A watched directory and the file in it that matters.
+#[cfg(target_os = "linux")]
+struct Watch {
+    descriptor: libc::c_int,
+    name: Vec<u8>,
+}
+
+/// Watches a strict session's config and the focus binary, logging attempts to delete or
+/// replace them and putting the config back from the last good copy. Only configs focus
+/// itself saves, announced through `expect`, replace that copy.
+#[cfg(target_os = "linux")]
+pub fn guard(config: Arc<Config>, running: Arc<AtomicBool>) {
+    let Ok(mut snapshot) = fs::read_to_string(CONFIG_PATH) else {
+        return;
+    };
+    let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+    if fd < 0 {
+        eprintln!(
+            "{}",
+            format!(
+                "[!] Failed to watch the config and binary: {}",
+                io::Error::last_os_error()
+            )
+            .bold()
+            .red()
+        );
+        return;
+    }
+    let binary = env::current_exe().ok();
+    let mut config_watch = watch(fd, Path::new(CONFIG_PATH));
+    let binary_watch = binary.as_deref().and_then(|binary| watch(fd, binary));
+
+    thread::spawn(move || {
+        let mut buffer = [0u8; EVENT_BUFFER];
+        while running.load(Ordering::SeqCst) {
+            let mut poll = libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            if unsafe { libc::poll(&mut poll, 1, POLL_TIMEOUT) } <= 0 {
+                continue;
+            }
+            let length = unsafe { libc::read(fd, buffer.as_mut_ptr().cast(), buffer.len()) };
+            if length <= 0 {
+                continue;
+            }
+            let events = parse(&buffer[..length as usize]);
+            let touches = |watch: &Option<Watch>| {
+                watch.as_ref().is_some_and(|watch| {
+                    events.iter().any(|(descriptor, _, name)| {
+                        *descriptor == watch.descriptor && *name == watch.name
+                    })
+                })
+            };
+            let directory_gone = config_watch.as_ref().is_some_and(|watch| {
+                events.iter().any(|(descriptor, mask, _)| {
+                    *descriptor == watch.descriptor && mask & GONE != 0
+                })
+            });
+
+            if touches(&config_watch) || directory_gone {
+                check_config(&config, &mut snapshot);
+            }
+            // A recreated directory is a new inode the old watch never sees
+            if directory_gone {
+                config_watch = watch(fd, Path::new(CONFIG_PATH));
+            }
+            if let Some(binary) = binary.as_deref().filter(|_| touches(&binary_watch)) {
+                report_binary(&config, binary);
+            }
+        }
+        unsafe { libc::close(fd) };
+    });
+}
+
+/// Without inotify, the config and the binary's modification time are compared every poll.
+#[cfg(not(target_os = "linux"))]
+pub fn guard(config: Arc<Config>, running: Arc<AtomicBool>) {
+    let Ok(mut snapshot) = fs::read_to_string(CONFIG_PATH) else {
+        return;
+    };
+    let binary = env::current_exe().ok();
+    let modified =
+        |binary: &Path| -> Option<SystemTime> { fs::metadata(binary).ok()?.modified().ok() };
+    let mut binary_modified = binary.as_deref().and_then(modified);
+
+    thread::spawn(move || {
+        while running.load(Ordering::SeqCst) {
+            thread::sleep(POLL_INTERVAL);
+            check_config(&config, &mut snapshot);
+            if let Some(binary) = binary.as_deref() {
+                let current = modified(binary);
+                if current != binary_modified {
+                    report_binary(&config, binary);
+                    binary_modified = current;
+                }
+            }
+        }
+    });
+}
+
+/// What is on disk decides, since editors save by renaming and writing anew.
+fn check_config(config: &Config, snapshot: &mut String) {
+    match fs::read_to_string(CONFIG_PATH) {
+        Ok(content) if content == *snapshot => {}
+        Ok(content) if take_expected(&content) => *snapshot = content,
+        Ok(content) if toml::from_str::<Config>(&content).is_ok() => {
+            restore_config(config, snapshot, "edited outside focus")
+        }
+        Ok(_) => restore_config(config, snapshot, "broken"),
+        Err(_) => restore_config(config, snapshot, "deleted"),
+    }
+}
+
+/// Lets the guard keep a config focus is about to save; the socket's `expect-config`.
+pub fn expect(digest: String) {
+    EXPECTED.lock().unwrap().push(digest);
+}
+
+/// The digest `expect` takes for a config's content.
+pub fn digest(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn take_expected(content: &str) -> bool {
+    let digest = digest(content);
+    let mut expected = EXPECTED.lock().unwrap();
+    let Some(index) = expected.iter().position(|expected| *expected == digest) else {
+        return false;
+    };
+    expected.remove(index);
+    true
+}
+
+/// Watches the directory holding `path`, as the file itself may be deleted and recreated.
+#[cfg(target_os = "linux")]
+fn watch(fd: libc::c_int, path: &Path) -> Option<Watch> {
+    let directory = CString::new(path.parent()?.as_os_str().as_bytes()).ok()?;
+    let descriptor = unsafe { libc::inotify_add_watch(fd, directory.as_ptr(), WATCHED) };
+    if descriptor < 0 {
+        return None;
+    }
+    Some(Watch {
+        descriptor,
+        name: path.file_name()?.as_bytes().to_vec(),
+    })
+}
+
+/// Splits a read into the watch, mask and file name of each event.
+#[cfg(target_os = "linux")]
+fn parse(buffer: &[u8]) -> Vec<(libc::c_int, u32, &[u8])> {
+    let header = size_of::<libc::inotify_event>();
+    let mut events = Vec::new();
+    let mut position = 0;
+    while position + header <= buffer.len() {
+        let event: libc::inotify_event = unsafe {
+            buffer
+                .as_ptr()
+                .add(position)
+                .cast::<libc::inotify_event>()
+                .read_unaligned()
+        };
+        let start = position + header;
+        let end = (start + event.len as usize).min(buffer.len());
+        // Names are padded with NULs to the event's length
+        let name = &buffer[start..end];
+        let name = &name[..name
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(name.len())];
+        events.push((event.wd, event.mask, name));
+        position = end;
+    }
+    events
+}
+
+fn restore_config(config: &Config, snapshot: &str, how: &str) {
+    println!(
+        "{}",
+        format!(
+            "[!] Tamper detected! {} was {}; restoring it",
+            CONFIG_PATH, how
+        )
+        .bold()
+        .red()
+    );
+    audit::record(config, "session", &format!("config {}, restored", how));
+    events::publish(config, Event::TamperDetected);
+    // Removing the whole directory takes the config with it
+    let restored =
+        fs::create_dir_all(util::config_directory()).and_then(|_| fs::write(CONFIG_PATH, snapshot));
+    if let Err(e) = restored {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to restore {}: {}", CONFIG_PATH, e)
+                .bold()
+                .red()
+        );
+    }
+}
+
+fn report_binary(config: &Config, binary: &Path) {
+    let how = if binary.exists() {
+        "replaced"
+    } else {
+        "removed"
+    };
+    println!(
+        "{}",
+        format!(
+            "[!] Tamper detected! {} was {} during a strict session",
+            binary.display(),
+            how
+        )
+        .bold()
+        .red()
+    );
+    audit::record(config, "session", &format!("binary {}", how));
+    events::publish(config, Event::TamperDetected);
+}
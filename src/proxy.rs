@@ -0,0 +1,385 @@
+use crate::{certificate, sni, util::Config};
+use colored::Colorize;
+use rustls::{
+    ClientConnection, ServerConfig, ServerConnection, StreamOwned, pki_types::ServerName,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+const PAC_FILE: &str = "focus.pac";
+const PAC_PATH: &str = "/proxy.pac";
+const MAX_HEAD: usize = 64 * 1024;
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(500);
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProxyConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_listen")]
+    pub listen: String,
+    #[serde(default)]
+    pub rules: Vec<PathRule>,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        ProxyConfig {
+            enabled: false,
+            listen: default_listen(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PathRule {
+    pub domain: String,
+    #[serde(default)]
+    pub allow_paths: Vec<String>,
+}
+
+fn default_listen() -> String {
+    String::from("127.0.0.1:3128")
+}
+
+struct Request {
+    method: String,
+    target: String,
+    version: String,
+    headers: Vec<(String, String)>,
+    body_start: Vec<u8>,
+}
+
+pub fn pac_path(config: &Config) -> String {
+    format!("{}/{}", config.data_directory, PAC_FILE)
+}
+
+pub fn build_pac(config: &ProxyConfig) -> String {
+    let conditions: Vec<String> = config
+        .rules
+        .iter()
+        .map(|rule| {
+            format!(
+                "host == \"{0}\" || dnsDomainIs(host, \".{0}\")",
+                rule.domain
+            )
+        })
+        .collect();
+    let condition = if conditions.is_empty() {
+        String::from("false")
+    } else {
+        conditions.join(" ||\n        ")
+    };
+
+    format!(
+        "function FindProxyForURL(url, host) {{\n    if ({}) {{\n        return \"PROXY {}\";\n    }}\n    return \"DIRECT\";\n}}\n",
+        condition, config.listen
+    )
+}
+
+pub fn start_proxy(config: Arc<Config>, running: Arc<AtomicBool>) {
+    let listener = match TcpListener::bind(&config.proxy.listen) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                format!(
+                    "[!] Failed to start path proxy on {}: {}",
+                    config.proxy.listen, e
+                )
+                .bold()
+                .red()
+            );
+            return;
+        }
+    };
+    let _ = listener.set_nonblocking(true);
+    let _ = fs::write(pac_path(&config), build_pac(&config.proxy));
+    println!(
+        "{}",
+        format!(
+            "[>] Path proxy on {}; point your browser at http://{}{} or {}",
+            config.proxy.listen,
+            config.proxy.listen,
+            PAC_PATH,
+            pac_path(&config)
+        )
+        .bold()
+        .cyan()
+    );
+    // HTTPS to a domain with exceptions is decrypted with the local CA to see the path
+    let exceptions: Vec<String> = config
+        .proxy
+        .rules
+        .iter()
+        .filter(|rule| !rule.allow_paths.is_empty())
+        .map(|rule| rule.domain.to_lowercase())
+        .collect();
+    let tls = (!exceptions.is_empty())
+        .then(|| certificate::server_config(&config, exceptions.clone()))
+        .flatten();
+    if tls.is_none() && !exceptions.is_empty() {
+        println!(
+            "{}",
+            "[!] Path exceptions apply to plain HTTP only until `focus certificate` creates a local CA; HTTPS to those domains is refused"
+                .bold()
+                .yellow()
+        );
+    }
+
+    thread::spawn(move || {
+        while running.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((client, _)) => {
+                    let config = Arc::clone(&config);
+                    let tls = tls.clone();
+                    thread::spawn(move || handle_client(&config, tls, client));
+                }
+                Err(_) => thread::sleep(ACCEPT_INTERVAL),
+            }
+        }
+    });
+}
+
+fn handle_client(config: &Config, tls: Option<Arc<ServerConfig>>, mut client: TcpStream) {
+    let _ = client.set_nonblocking(false);
+    let Some(request) = read_request(&mut client) else {
+        return;
+    };
+
+    if request.method == "CONNECT" {
+        let (host, port) = match request.target.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().unwrap_or(443)),
+            None => (&*request.target, 443),
+        };
+        if let Some(rule) = matching_rule(&config.proxy, host) {
+            match tls {
+                Some(tls) if !rule.allow_paths.is_empty() => {
+                    if client
+                        .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                        .is_ok()
+                    {
+                        intercept(rule, tls, client, host, port);
+                    }
+                }
+                _ => respond_blocked(&mut client, host),
+            }
+            return;
+        }
+        let Ok(upstream) = TcpStream::connect(&request.target) else {
+            let _ = client.write_all(b"HTTP/1.1 502 Bad Gateway\r\n\r\n");
+            return;
+        };
+        let _ = client.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n");
+        sni::relay(client, upstream);
+        return;
+    }
+
+    let Some((host, port, path)) = split_url(&request.target) else {
+        if request.target == PAC_PATH {
+            let pac = build_pac(&config.proxy);
+            let _ = client.write_all(
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/x-ns-proxy-autoconfig\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    pac.len(),
+                    pac
+                )
+                .as_bytes(),
+            );
+        }
+        return;
+    };
+
+    if let Some(rule) = matching_rule(&config.proxy, &host)
+        && !is_allowed(rule, &path)
+    {
+        respond_blocked(&mut client, &host);
+        return;
+    }
+
+    let Ok(mut upstream) = TcpStream::connect((host.as_str(), port)) else {
+        let _ = client.write_all(b"HTTP/1.1 502 Bad Gateway\r\n\r\n");
+        return;
+    };
+    if send_request(&request, &path, &mut upstream).is_err() {
+        return;
+    }
+    sni::relay(client, upstream);
+}
+
+/// Answers a tunnel to a domain with exceptions as that site, using a certificate from the
+/// local CA, and passes on the one request the tunnel carries if its path is allowed.
+fn intercept(rule: &PathRule, tls: Arc<ServerConfig>, client: TcpStream, host: &str, port: u16) {
+    let Ok(connection) = ServerConnection::new(tls) else {
+        return;
+    };
+    if client.set_read_timeout(Some(READ_TIMEOUT)).is_err() {
+        return;
+    }
+    let mut client = StreamOwned::new(connection, client);
+    let Some(request) = read_request(&mut client) else {
+        return;
+    };
+    if !is_allowed(rule, &request.target) {
+        respond_blocked(&mut client, host);
+        let _ = finish_tls(&mut client);
+        return;
+    }
+
+    let upstream = ServerName::try_from(host.to_string())
+        .ok()
+        .and_then(|name| ClientConnection::new(certificate::client_config(), name).ok())
+        .zip(TcpStream::connect((host, port)).ok())
+        .filter(|(_, stream)| stream.set_read_timeout(Some(READ_TIMEOUT)).is_ok());
+    let Some((connection, stream)) = upstream else {
+        let _ = client.write_all(b"HTTP/1.1 502 Bad Gateway\r\n\r\n");
+        let _ = finish_tls(&mut client);
+        return;
+    };
+    let mut upstream = StreamOwned::new(connection, stream);
+
+    // One request per connection, so the rest of the body is all that follows the head
+    let remaining = content_length(&request).saturating_sub(request.body_start.len() as u64);
+    if send_request(&request, &request.target, &mut upstream).is_err()
+        || io::copy(&mut (&mut client).take(remaining), &mut upstream).is_err()
+    {
+        return;
+    }
+    let _ = io::copy(&mut upstream, &mut client);
+    let _ = finish_tls(&mut client);
+}
+
+fn finish_tls(client: &mut StreamOwned<ServerConnection, TcpStream>) -> io::Result<()> {
+    client.conn.send_close_notify();
+    client.flush()
+}
+
+/// Whether an exception like "/playlist?list=MyCourse" covers the request: the same path, or
+/// one below it when the exception has no query, with each of its query parameters present
+/// with exactly that value.
+fn is_allowed(rule: &PathRule, target: &str) -> bool {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let parameters: Vec<&str> = query.split('&').collect();
+    rule.allow_paths
+        .iter()
+        .any(|allowed| match allowed.split_once('?') {
+            Some((allowed_path, allowed_query)) => {
+                path == allowed_path
+                    && allowed_query
+                        .split('&')
+                        .filter(|parameter| !parameter.is_empty())
+                        .all(|parameter| parameters.contains(&parameter))
+            }
+            None => {
+                path == allowed || path.starts_with(&format!("{}/", allowed.trim_end_matches('/')))
+            }
+        })
+}
+
+fn content_length(request: &Request) -> u64 {
+    request
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0)
+}
+
+fn send_request(request: &Request, path: &str, upstream: &mut impl Write) -> io::Result<()> {
+    let mut head = format!("{} {} {}\r\n", request.method, path, request.version);
+    for (name, value) in &request.headers {
+        let lower = name.to_lowercase();
+        if lower == "connection" || lower == "proxy-connection" || lower == "keep-alive" {
+            continue;
+        }
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    // One request per connection so every path passes through the rule check
+    head.push_str("Connection: close\r\n\r\n");
+    upstream.write_all(head.as_bytes())?;
+    upstream.write_all(&request.body_start)
+}
+
+pub fn matching_rule<'a>(config: &'a ProxyConfig, host: &str) -> Option<&'a PathRule> {
+    let host = host.trim_end_matches('.').to_lowercase();
+    config.rules.iter().find(|rule| {
+        let domain = rule.domain.to_lowercase();
+        host == domain || host.ends_with(&format!(".{}", domain))
+    })
+}
+
+fn split_url(target: &str) -> Option<(String, u16, String)> {
+    let rest = target.strip_prefix("http://")?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().ok()?),
+        None => (authority, 80),
+    };
+    Some((host.to_string(), port, path.to_string()))
+}
+
+fn read_request(client: &mut impl Read) -> Option<Request> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(index) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break index;
+        }
+        if buffer.len() > MAX_HEAD {
+            return None;
+        }
+        let length = client.read(&mut chunk).ok()?;
+        if length == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..length]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let target = request_line.next()?.to_string();
+    let version = request_line.next().unwrap_or("HTTP/1.1").to_string();
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    Some(Request {
+        method,
+        target,
+        version,
+        headers,
+        body_start: buffer[head_end + 4..].to_vec(),
+    })
+}
+
+fn respond_blocked(client: &mut impl Write, host: &str) {
+    let body = format!(
+        "<!doctype html><html><body style=\"font-family:sans-serif;text-align:center;margin-top:20vh\"><h1>Blocked by focus</h1><p>{} is not allowed during this session.</p></body></html>",
+        host
+    );
+    let _ = client.write_all(
+        format!(
+            "HTTP/1.1 403 Forbidden\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .as_bytes(),
+    );
+}
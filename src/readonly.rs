@@ -0,0 +1,91 @@
+use crate::{
+    privileged::{self, CONFIG_PATH},
+    util::{self, Config},
+};
+use colored::Colorize;
+#[cfg(unix)]
+use std::{ffi::CString, os::unix::ffi::OsStrExt};
+use std::{io, path::Path, process};
+
+/// Whether `path`, or the nearest directory above it that exists, is on a read-only mount.
+#[cfg(unix)]
+pub fn is_read_only(path: &Path) -> bool {
+    let Some(existing) = path.ancestors().find(|ancestor| ancestor.exists()) else {
+        return false;
+    };
+    let Ok(path) = CString::new(existing.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    unsafe { libc::statvfs(path.as_ptr(), &mut stat) == 0 && stat.f_flag & libc::ST_RDONLY != 0 }
+}
+
+/// The Windows hosts file always sits on the system drive, which is never mounted read-only.
+#[cfg(target_os = "windows")]
+pub fn is_read_only(_path: &Path) -> bool {
+    false
+}
+
+/// Checks the hosts file before a session touches it. On immutable distros and in
+/// containers it may be read-only, so the session falls back to the DNS sinkhole.
+pub fn check_hosts(config: &mut Config) {
+    if config.warn_only {
+        return;
+    }
+    let Ok(target) = privileged::resolve_hosts(&config.hosts_path) else {
+        return;
+    };
+    if !is_read_only(&target) {
+        return;
+    }
+    eprintln!(
+        "{}",
+        format!(
+            "[!] {} is on a read-only filesystem, as on immutable distros and in containers, so no sites can be blocked in it",
+            target.display()
+        )
+        .bold()
+        .yellow()
+    );
+    let runtime = util::runtime_directory();
+    if is_read_only(Path::new(&runtime)) {
+        eprintln!(
+            "{}",
+            format!(
+                "[!] {} is read-only too; point hosts_path at a writable hosts file or run focus on the host",
+                runtime
+            )
+            .bold()
+            .red()
+        );
+        process::exit(1);
+    }
+    config.hosts_read_only = true;
+    config.dns.enabled = true;
+    println!(
+        "{}",
+        format!(
+            "[>] Blocking with the DNS sinkhole on {} instead; the system resolver must use it, e.g. `resolvectl dns lo 127.0.0.1`",
+            config.dns.listen
+        )
+        .bold()
+        .cyan()
+    );
+}
+
+/// Explains a config that can't be saved because its mount is read-only, then exits.
+pub fn exit_if_config_read_only(e: &io::Error) {
+    if e.raw_os_error() != Some(libc::EROFS) {
+        return;
+    }
+    eprintln!(
+        "{}",
+        format!(
+            "[!] {} is on a read-only filesystem; change it where the image is built, or mount a writable config there",
+            CONFIG_PATH
+        )
+        .bold()
+        .red()
+    );
+    process::exit(1);
+}
@@ -0,0 +1,278 @@
+use crate::{audit, events, notify::Event, platform, util::Config};
+use colored::Colorize;
+use std::{
+    collections::HashSet,
+    fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::Mutex,
+};
+
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+/// Where DHCP clients keep their leases: systemd-networkd, NetworkManager and dhclient
+const LEASE_DIRECTORIES: &[&str] = &[
+    "/run/systemd/netif/leases",
+    "/var/lib/NetworkManager",
+    "/var/lib/dhcp",
+    "/var/lib/dhclient",
+];
+
+/// Well-known DNS-over-HTTPS endpoints, blocked in hardened mode so browsers fall back to
+/// the system resolver and with it to the block.
+const DOH_SITES: &[&str] = &[
+    "dns.google",
+    "cloudflare-dns.com",
+    "mozilla.cloudflare-dns.com",
+    "chrome.cloudflare-dns.com",
+    "one.one.one.one",
+    "dns.quad9.net",
+    "doh.opendns.com",
+    "dns.nextdns.io",
+    "doh.mullvad.net",
+    "dns.adguard-dns.com",
+];
+
+/// The resolver settings when the session started. Networks change during a session, so
+/// only a new symlink target or a server neither known then nor handed out by DHCP counts as
+/// tampering.
+struct Snapshot {
+    /// Where /etc/resolv.conf pointed, as systemd-resolved and NetworkManager link it
+    target: Option<PathBuf>,
+    content: String,
+    /// Servers in /etc/resolv.conf and on every link at the start
+    servers: HashSet<String>,
+}
+
+static SNAPSHOT: Mutex<Option<Snapshot>> = Mutex::new(None);
+
+/// DoH endpoints to block on top of the configured sites, except one `[dns]` forwards to.
+pub fn doh_sites(config: &Config) -> Vec<String> {
+    if !config.hardened {
+        return Vec::new();
+    }
+    DOH_SITES
+        .iter()
+        .filter(|site| !config.dns.upstream.contains(*site))
+        .map(|site| site.to_string())
+        .collect()
+}
+
+/// Remembers the resolver settings so `check` can pin them; called whenever a block is written.
+pub fn lock(config: &Config) {
+    if !config.hardened {
+        return;
+    }
+    let mut snapshot = SNAPSHOT.lock().unwrap();
+    if snapshot.is_some() {
+        return;
+    }
+    if !platform::is_root() {
+        eprintln!(
+            "{}",
+            "[!] hardened needs focus to run as root; the DNS settings are left unguarded"
+                .bold()
+                .yellow()
+        );
+        return;
+    }
+    let Ok(content) = fs::read_to_string(RESOLV_CONF) else {
+        return;
+    };
+    let servers = nameservers(&content)
+        .chain(
+            resolved_links()
+                .into_iter()
+                .flat_map(|(_, servers)| servers),
+        )
+        .collect();
+    *snapshot = Some(Snapshot {
+        target: fs::read_link(RESOLV_CONF).ok(),
+        content,
+        servers,
+    });
+    println!(
+        "{}",
+        format!("[>] Guarding {} for the session", RESOLV_CONF)
+            .bold()
+            .cyan()
+    );
+}
+
+/// Stops pinning the resolver settings, leaving whatever they are now.
+pub fn unlock() {
+    SNAPSHOT.lock().unwrap().take();
+}
+
+/// Undoes DNS changes made since `lock` that didn't come from the network; runs with the
+/// tamper check.
+pub fn check(config: &Config) {
+    let snapshot = SNAPSHOT.lock().unwrap();
+    let Some(snapshot) = snapshot.as_ref() else {
+        return;
+    };
+    let mut restored = Vec::new();
+    let dhcp = dhcp_servers();
+    let is_foreign = |server: &String| {
+        !snapshot.servers.contains(server)
+            && !dhcp.contains(server)
+            && !server.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+    };
+
+    let target = fs::read_link(RESOLV_CONF).ok();
+    if target != snapshot.target {
+        let _ = fs::remove_file(RESOLV_CONF);
+        let result = match &snapshot.target {
+            #[cfg(unix)]
+            Some(target) => std::os::unix::fs::symlink(target, RESOLV_CONF),
+            #[cfg(target_os = "windows")]
+            Some(target) => std::os::windows::fs::symlink_file(target, RESOLV_CONF),
+            None => fs::write(RESOLV_CONF, &snapshot.content),
+        };
+        report_restore(result);
+        restored.push(String::from(RESOLV_CONF));
+    } else if target.is_none() {
+        // A linked file belongs to its resolver, whose servers the links below cover
+        let content = fs::read_to_string(RESOLV_CONF).unwrap_or_default();
+        if nameservers(&content).any(|server| is_foreign(&server)) {
+            let kept: Vec<&str> = content
+                .lines()
+                .filter(|line| nameservers(line).all(|server| !is_foreign(&server)))
+                .collect();
+            let kept = kept.join("\n") + "\n";
+            let content = if nameservers(&kept).next().is_some() {
+                kept
+            } else {
+                snapshot.content.clone()
+            };
+            report_restore(fs::write(RESOLV_CONF, content));
+            restored.push(String::from(RESOLV_CONF));
+        }
+    }
+
+    for (link, servers) in resolved_links() {
+        if !servers.iter().any(is_foreign) {
+            continue;
+        }
+        // Back to the servers the network configured, not the ones of the last network
+        let _ = Command::new("resolvectl")
+            .args(["revert", &link])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        restored.push(format!("link {}", link));
+    }
+
+    if restored.is_empty() {
+        return;
+    }
+    println!(
+        "{}",
+        format!(
+            "[!] Tamper detected! Undid DNS changes to {}",
+            restored.join(" and ")
+        )
+        .bold()
+        .red()
+    );
+    audit::record(config, "session", "DNS settings restored");
+    events::publish(config, Event::TamperDetected);
+    // A new resolver may have cached answers from before the block
+    let _ = Command::new("resolvectl")
+        .arg("flush-caches")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
+fn report_restore(result: std::io::Result<()>) {
+    if let Err(e) = result {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to restore {}: {}", RESOLV_CONF, e)
+                .bold()
+                .red()
+        );
+    }
+}
+
+/// The servers of `nameserver` lines.
+fn nameservers(content: &str) -> impl Iterator<Item = String> {
+    content.lines().filter_map(|line| {
+        let mut words = line.split_whitespace();
+        (words.next() == Some("nameserver")).then(|| words.next().map(normalize))?
+    })
+}
+
+/// An address without the interface or TLS name resolvectl appends, as in "fe80::1%2#dns".
+fn normalize(server: &str) -> String {
+    server
+        .split(['#', '%'])
+        .next()
+        .unwrap_or(server)
+        .to_lowercase()
+}
+
+/// Servers handed out by DHCP: `DNS=` in networkd and NetworkManager leases,
+/// `option domain-name-servers` in dhclient ones, and what NetworkManager reports per device.
+fn dhcp_servers() -> HashSet<String> {
+    let mut servers = HashSet::new();
+    for directory in LEASE_DIRECTORIES {
+        for entry in fs::read_dir(directory).into_iter().flatten().flatten() {
+            let Ok(content) = fs::read_to_string(entry.path()) else {
+                continue;
+            };
+            for line in content.lines().map(str::trim) {
+                let list = line
+                    .strip_prefix("DNS=")
+                    .or_else(|| line.strip_prefix("option domain-name-servers"));
+                if let Some(list) = list {
+                    servers.extend(
+                        list.split([' ', ',', ';'])
+                            .filter(|server| !server.is_empty())
+                            .map(normalize),
+                    );
+                }
+            }
+        }
+    }
+    if let Ok(output) = Command::new("nmcli")
+        .args(["-t", "-g", "IP4.DNS,IP6.DNS", "device", "show"])
+        .stderr(Stdio::null())
+        .output()
+    {
+        servers.extend(
+            String::from_utf8_lossy(&output.stdout)
+                .split(['\n', '|'])
+                .map(str::trim)
+                .filter(|server| !server.is_empty())
+                .map(normalize),
+        );
+    }
+    servers
+}
+
+/// Per-link DNS servers from `resolvectl dns`, e.g. ("2", ["192.168.1.1"]).
+fn resolved_links() -> Vec<(String, Vec<String>)> {
+    if !Path::new("/run/systemd/resolve").is_dir() {
+        return Vec::new();
+    }
+    let Ok(output) = Command::new("resolvectl")
+        .arg("dns")
+        .stderr(Stdio::null())
+        .output()
+    else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (link, servers) = line.strip_prefix("Link ")?.split_once(':')?;
+            let index = link.split_whitespace().next()?;
+            Some((
+                index.to_string(),
+                servers.split_whitespace().map(normalize).collect(),
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,38 @@
+use crate::{prompt, util::Config};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ChecklistResult {
+    pub done: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+/// Walks through the configured pre-session checklist; `None` when there is none.
+pub fn run(config: &Config) -> Option<ChecklistResult> {
+    if config.checklist.is_empty() {
+        return None;
+    }
+
+    println!("{}", "[>] Before you start:".bold().cyan());
+    let mut result = ChecklistResult::default();
+    for item in &config.checklist {
+        let question = format!("[?] {}? [y/N] ", item);
+        if prompt::confirm(&format!("{}", question.bold().yellow()), None) {
+            result.done.push(item.clone());
+        } else {
+            result.skipped.push(item.clone());
+        }
+    }
+    println!(
+        "{}",
+        format!(
+            "[+] Checklist: {} of {} done",
+            result.done.len(),
+            config.checklist.len()
+        )
+        .bold()
+        .green()
+    );
+    Some(result)
+}
@@ -0,0 +1,424 @@
+use crate::{
+    audit, session,
+    util::{self, Config},
+    vacation::DaysOff,
+};
+use chrono::{
+    DateTime, Datelike, Local, LocalResult, NaiveDate, NaiveTime, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fmt,
+    process::{self, Command, Stdio},
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+/// Longest sleep between checks, so rules added with `focus schedule add` apply soon
+const RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+const SCHEDULED_TAG: &str = "scheduled";
+
+const DAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// Where wall-clock times are interpreted; DST comes from the zone's rules, not a fixed offset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    System,
+    Named(Tz),
+}
+
+impl Zone {
+    fn date_of(&self, instant: DateTime<Utc>) -> NaiveDate {
+        match self {
+            Zone::System => instant.with_timezone(&Local).date_naive(),
+            Zone::Named(tz) => instant.with_timezone(tz).date_naive(),
+        }
+    }
+
+    /// The instant a wall-clock time happens on a date. Times skipped by a DST jump
+    /// move past the gap; times repeated when clocks go back use the first occurrence.
+    pub fn resolve(&self, date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+        let mut local = date.and_time(time);
+        loop {
+            let resolved = match self {
+                Zone::System => to_utc(Local.from_local_datetime(&local)),
+                Zone::Named(tz) => to_utc(tz.from_local_datetime(&local)),
+            };
+            if let Some(instant) = resolved {
+                return instant;
+            }
+            local += chrono::Duration::minutes(15);
+        }
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Zone::System => write!(f, "local time"),
+            Zone::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+fn to_utc<T: TimeZone>(result: LocalResult<DateTime<T>>) -> Option<DateTime<Utc>> {
+    match result {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => {
+            Some(time.with_timezone(&Utc))
+        }
+        LocalResult::None => None,
+    }
+}
+
+pub fn parse_zone(name: &str) -> Result<Zone, String> {
+    Tz::from_str(name)
+        .map(Zone::Named)
+        .map_err(|_| format!("unknown time zone '{}'", name))
+}
+
+/// The configured `timezone`, or the system's when unset or invalid.
+pub fn default_zone(config: &Config) -> Zone {
+    config
+        .timezone
+        .as_deref()
+        .and_then(|name| parse_zone(name).ok())
+        .unwrap_or(Zone::System)
+}
+
+/// A recurring window such as `Mon-Fri 09:00-12:00` or `Sat 10:00-11:30 Europe/Lisbon`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ScheduleRule {
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    /// Overrides the config-wide zone, e.g. to keep home hours while travelling
+    pub timezone: Option<Zone>,
+}
+
+impl ScheduleRule {
+    pub fn zone(&self, config: &Config) -> Zone {
+        self.timezone.unwrap_or_else(|| default_zone(config))
+    }
+
+    /// The window starting on `date`; windows ending at or before their start run past midnight.
+    /// Windows starting on a day off don't happen at all.
+    fn window_on(
+        &self,
+        zone: Zone,
+        days_off: &DaysOff,
+        date: NaiveDate,
+    ) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        if !self.days.contains(&date.weekday()) || days_off.contains(date) {
+            return None;
+        }
+        let end_date = if self.end <= self.start {
+            date.succ_opt()?
+        } else {
+            date
+        };
+        Some((
+            zone.resolve(date, self.start),
+            zone.resolve(end_date, self.end),
+        ))
+    }
+
+    /// When the window containing `now` ends, if there is one.
+    pub fn active_until(&self, config: &Config, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let zone = self.zone(config);
+        let days_off = DaysOff::load(config);
+        let today = zone.date_of(now);
+        [today.pred_opt()?, today]
+            .into_iter()
+            .filter_map(|date| self.window_on(zone, &days_off, date))
+            .find(|(start, end)| *start <= now && now < *end)
+            .map(|(_, end)| end)
+    }
+
+    /// The next window that starts after `now`, looking past vacations up to a year ahead.
+    pub fn next_window(
+        &self,
+        config: &Config,
+        now: DateTime<Utc>,
+    ) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let zone = self.zone(config);
+        let days_off = DaysOff::load(config);
+        let today = zone.date_of(now);
+        (0..=366)
+            .filter_map(|offset| today.checked_add_days(chrono::Days::new(offset)))
+            .filter_map(|date| self.window_on(zone, &days_off, date))
+            .find(|(start, _)| *start > now)
+    }
+}
+
+impl FromStr for ScheduleRule {
+    type Err = String;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = rule.split_whitespace().collect();
+        let (days, times, timezone) = match parts.as_slice() {
+            [days, times] => (days, times, None),
+            [days, times, zone] => (days, times, Some(parse_zone(zone)?)),
+            _ => {
+                return Err(format!(
+                    "'{}' should look like 'Mon-Fri 09:00-12:00 [Time/Zone]'",
+                    rule
+                ));
+            }
+        };
+        let (start, end) = times
+            .split_once('-')
+            .ok_or_else(|| format!("'{}' should be a range like 09:00-12:00", times))?;
+        Ok(ScheduleRule {
+            days: parse_days(days)?,
+            start: parse_time(start)?,
+            end: parse_time(end)?,
+            timezone,
+        })
+    }
+}
+
+impl TryFrom<String> for ScheduleRule {
+    type Error = String;
+
+    fn try_from(rule: String) -> Result<Self, Self::Error> {
+        rule.parse()
+    }
+}
+
+impl From<ScheduleRule> for String {
+    fn from(rule: ScheduleRule) -> Self {
+        rule.to_string()
+    }
+}
+
+impl fmt::Display for ScheduleRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {}-{}",
+            format_days(&self.days),
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )?;
+        if let Some(zone) = self.timezone {
+            write!(f, " {}", zone)?;
+        }
+        Ok(())
+    }
+}
+
+/// Accepts single days, ranges and lists: `Mon`, `Mon-Fri`, `Sat,Sun`, `daily`.
+fn parse_days(days: &str) -> Result<Vec<Weekday>, String> {
+    if days.eq_ignore_ascii_case("daily") {
+        return Ok(DAYS.to_vec());
+    }
+    let mut parsed = Vec::new();
+    for part in days.split(',') {
+        let day = |name: &str| {
+            Weekday::from_str(name).map_err(|_| format!("'{}' is not a day of the week", name))
+        };
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let (from, to) = (day(from)?, day(to)?);
+                let mut current = from;
+                loop {
+                    parsed.push(current);
+                    if current == to {
+                        break;
+                    }
+                    current = current.succ();
+                }
+            }
+            None => parsed.push(day(part)?),
+        }
+    }
+    parsed.dedup();
+    Ok(parsed)
+}
+
+/// The inverse of `parse_days`, folding runs of consecutive days back into ranges.
+fn format_days(days: &[Weekday]) -> String {
+    if DAYS.iter().all(|day| days.contains(day)) {
+        return String::from("daily");
+    }
+    let mut runs: Vec<(Weekday, Weekday)> = Vec::new();
+    for &day in days {
+        match runs.last_mut() {
+            Some((first, last)) if last.succ() == day && day != *first => *last = day,
+            _ => runs.push((day, day)),
+        }
+    }
+    runs.iter()
+        .map(|(first, last)| {
+            if first == last {
+                first.to_string()
+            } else {
+                format!("{}-{}", first, last)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+pub fn parse_time(time: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .map_err(|_| format!("'{}' is not a time like 09:30", time))
+}
+
+/// The next time the wall clock shows `time` in `zone`, which may be tomorrow.
+pub fn next_occurrence(zone: Zone, time: NaiveTime, now: DateTime<Utc>) -> DateTime<Utc> {
+    let today = zone.date_of(now);
+    let candidate = zone.resolve(today, time);
+    if candidate > now {
+        return candidate;
+    }
+    zone.resolve(today.succ_opt().unwrap_or(today), time)
+}
+
+/// When the scheduled window open now ends, if any rule has one open.
+pub fn active_until(config: &Config) -> Option<DateTime<Utc>> {
+    let now = Utc::now();
+    config
+        .schedule
+        .iter()
+        .filter_map(|rule| rule.active_until(config, now))
+        .max()
+}
+
+/// Lists the rules with their next or current window.
+pub fn list(config: &Config) {
+    if config.schedule.is_empty() {
+        println!(
+            "{}",
+            "[>] No scheduled blocks; add one with: focus schedule add Mon-Fri 09:00-12:00"
+                .bold()
+                .cyan()
+        );
+        return;
+    }
+    util::show_schedule(config);
+}
+
+pub fn add(config: &Config, rule: &str) {
+    let rule = match rule.parse::<ScheduleRule>() {
+        Ok(rule) => rule,
+        Err(e) => {
+            eprintln!("{}", format!("[!] {}", e).bold().red());
+            process::exit(1);
+        }
+    };
+    if config.schedule.contains(&rule) {
+        println!(
+            "{}",
+            format!("[>] {} is already scheduled", rule).bold().cyan()
+        );
+        return;
+    }
+    let mut config = config.clone();
+    config.schedule.push(rule.clone());
+    util::save_config(&config).expect("[!] Failed to save configuration");
+    println!("{}", format!("[+] Scheduled {}", rule).bold().green());
+}
+
+/// Removes a rule given as written or as `focus schedule list` prints it.
+pub fn remove(config: &Config, rule: &str) -> bool {
+    let parsed = rule.parse::<ScheduleRule>().ok();
+    let mut config = config.clone();
+    let before = config.schedule.len();
+    config
+        .schedule
+        .retain(|existing| Some(existing) != parsed.as_ref() && existing.to_string() != rule);
+    if config.schedule.len() == before {
+        eprintln!(
+            "{}",
+            format!("[!] No scheduled block matches '{}'", rule)
+                .bold()
+                .red()
+        );
+        return false;
+    }
+    util::save_config(&config).expect("[!] Failed to save configuration");
+    println!("{}", format!("[+] Unscheduled {}", rule).bold().green());
+    true
+}
+
+/// Starts a background session whenever a window opens, for as long as the window lasts;
+/// run it as a system service. A session stopped early stays stopped until the next window.
+pub fn run(config: &Config) {
+    println!(
+        "{}",
+        "[>] Following the schedule; sessions start as windows open"
+            .bold()
+            .cyan()
+    );
+    let mut config = config.clone();
+    let mut handled: Option<DateTime<Utc>> = None;
+    loop {
+        if let Ok(reloaded) = util::load_config() {
+            config = reloaded;
+        }
+        let now = Utc::now();
+        if let Some(end) = active_until(&config)
+            && handled != Some(end)
+        {
+            handled = Some(end);
+            if !session_running(&config) {
+                start_session(&config, end - now);
+            }
+        }
+        let next_start = config
+            .schedule
+            .iter()
+            .filter_map(|rule| rule.next_window(&config, now))
+            .map(|(start, _)| start)
+            .min();
+        let sleep = next_start
+            .and_then(|start| (start - Utc::now()).to_std().ok())
+            .map_or(RELOAD_INTERVAL, |until| until.min(RELOAD_INTERVAL));
+        thread::sleep(sleep);
+    }
+}
+
+fn session_running(config: &Config) -> bool {
+    session::load_state(config).is_some_and(|state| session::is_alive(state.pid))
+}
+
+fn start_session(config: &Config, length: chrono::Duration) {
+    let Ok(exe) = env::current_exe() else {
+        return;
+    };
+    let minutes = (length.num_seconds().max(1) as u64).div_ceil(60);
+    println!(
+        "{}",
+        format!("[>] Starting a scheduled block for {} minutes", minutes)
+            .bold()
+            .cyan()
+    );
+    let started = Command::new(exe)
+        .args(["--background", "--no-ritual", "--tag", SCHEDULED_TAG])
+        .args(["--duration", &minutes.to_string()])
+        .stdin(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    audit::record(
+        config,
+        "schedule",
+        if started {
+            "started scheduled block"
+        } else {
+            "scheduled block failed to start"
+        },
+    );
+}
@@ -0,0 +1,474 @@
+use crate::{
+    audit, backend,
+    embed::{self, Backend},
+    hosts::{self, FoundBlock},
+    platform, privileged, prompt,
+    ritual::ChecklistResult,
+    socket, stats,
+    util::{self, Config},
+};
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, IsTerminal},
+    process,
+    sync::{Condvar, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+const STATE_EXTENSION: &str = "session";
+const HANDOVER_TIMEOUT: Duration = Duration::from_secs(10);
+const HANDOVER_POLL: Duration = Duration::from_millis(100);
+
+/// What to do with a block whose session process is gone.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OrphanAction {
+    /// Take it over and run until its original deadline
+    Adopt,
+    /// Remove it and start fresh
+    Clean,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionState {
+    pub pid: u32,
+    pub start: DateTime<Local>,
+    pub planned_minutes: u64,
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub checklist: Option<ChecklistResult>,
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub strict: bool,
+    /// Time spent in finished pauses, kept out of the focused minutes
+    #[serde(default)]
+    pub paused_seconds: u64,
+    #[serde(default)]
+    pub pauses: u32,
+    /// When the current pause began, if paused
+    #[serde(default)]
+    pub paused_since: Option<DateTime<Local>>,
+    /// Backends the session enforces with, so a resumed session keeps a `--backend` override
+    #[serde(default)]
+    pub backends: Option<Vec<Backend>>,
+}
+
+impl SessionState {
+    /// All pause time up to `now`, including a pause still in progress.
+    pub fn paused_until(&self, now: DateTime<Local>) -> u64 {
+        let current = self
+            .paused_since
+            .map(|since| (now - since).num_seconds().max(0) as u64)
+            .unwrap_or(0);
+        self.paused_seconds + current
+    }
+}
+
+pub struct Timer {
+    deadline: Mutex<Instant>,
+    paused_at: Mutex<Option<Instant>>,
+    /// Bumped whenever the deadline moves so waiters can re-plan instead of polling
+    changes: Mutex<u64>,
+    changed: Condvar,
+}
+
+impl Timer {
+    pub fn new(duration: Duration) -> Self {
+        Timer {
+            deadline: Mutex::new(Instant::now() + duration),
+            paused_at: Mutex::new(None),
+            changes: Mutex::new(0),
+            changed: Condvar::new(),
+        }
+    }
+
+    pub fn remaining(&self) -> Duration {
+        let now = self.paused_at.lock().unwrap().unwrap_or_else(Instant::now);
+        self.deadline.lock().unwrap().saturating_duration_since(now)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.lock().unwrap().is_some()
+    }
+
+    pub fn pause(&self) {
+        self.paused_at
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Instant::now);
+        self.notify_change();
+    }
+
+    pub fn resume(&self) {
+        if let Some(paused_at) = self.paused_at.lock().unwrap().take() {
+            *self.deadline.lock().unwrap() += paused_at.elapsed();
+        }
+        self.notify_change();
+    }
+
+    pub fn extend(&self, by: Duration) {
+        *self.deadline.lock().unwrap() += by;
+        self.notify_change();
+    }
+
+    /// Brings the deadline closer, e.g. when the wall clock it was set against jumped ahead.
+    pub fn shorten(&self, by: Duration) {
+        {
+            let mut deadline = self.deadline.lock().unwrap();
+            *deadline = deadline.checked_sub(by).unwrap_or_else(Instant::now);
+        }
+        self.notify_change();
+    }
+
+    pub fn changes(&self) -> u64 {
+        *self.changes.lock().unwrap()
+    }
+
+    /// Wakes anything waiting in `wait_for_change`.
+    pub fn notify_change(&self) {
+        *self.changes.lock().unwrap() += 1;
+        self.changed.notify_all();
+    }
+
+    /// Sleeps for up to `timeout`, returning early if anything changed since `seen`.
+    pub fn wait_for_change(&self, seen: u64, timeout: Duration) {
+        let changes = self.changes.lock().unwrap();
+        let _ = self
+            .changed
+            .wait_timeout_while(changes, timeout, |changes| *changes == seen);
+    }
+}
+
+/// Identifies a session in its hosts block, e.g. `20261016-173102`.
+pub fn new_id() -> String {
+    Local::now().format("%Y%m%d-%H%M%S").to_string()
+}
+
+pub fn state_path(config: &Config) -> String {
+    util::scoped_path(config, STATE_EXTENSION)
+}
+
+pub fn save_state(config: &Config, tag: Option<String>, checklist: Option<ChecklistResult>) {
+    let state = SessionState {
+        pid: process::id(),
+        start: Local::now(),
+        planned_minutes: config.duration,
+        tag,
+        checklist,
+        profile: config.profile.clone(),
+        strict: config.strict,
+        paused_seconds: 0,
+        pauses: 0,
+        paused_since: None,
+        backends: Some(embed::enabled_backends(config)),
+    };
+    write_state(config, &state);
+}
+
+fn write_state(config: &Config, state: &SessionState) {
+    let content = serde_json::to_string(state).expect("[!] Could not encode session state");
+    let _ = fs::write(state_path(config), content);
+}
+
+/// Makes this process the session's owner, then waits for the one it replaces to exit.
+pub fn take_over(config: &Config, pid: u32) {
+    if let Some(mut state) = load_state(config) {
+        state.pid = process::id();
+        write_state(config, &state);
+    }
+    let deadline = Instant::now() + HANDOVER_TIMEOUT;
+    while is_alive(pid) && Instant::now() < deadline {
+        thread::sleep(HANDOVER_POLL);
+    }
+}
+
+/// Waits for another process to take the session over; false if none did in time.
+pub fn wait_for_handover(config: &Config) -> bool {
+    let deadline = Instant::now() + HANDOVER_TIMEOUT;
+    while Instant::now() < deadline {
+        if load_state(config).is_some_and(|state| state.pid != process::id()) {
+            return true;
+        }
+        thread::sleep(HANDOVER_POLL);
+    }
+    false
+}
+
+/// Asks a session running in a terminal to carry on in the background.
+pub fn send_to_background(config: &Config) -> bool {
+    let Some(state) = load_state(config).filter(|state| is_alive(state.pid)) else {
+        eprintln!("{}", "[!] No active focus session found".bold().red());
+        return false;
+    };
+    let daemon = fs::read_to_string(util::runtime_path(config, "pid"))
+        .ok()
+        .and_then(|pid| pid.trim().parse::<u32>().ok());
+    if daemon == Some(state.pid) {
+        println!(
+            "{}",
+            "[!] The session is already running in the background"
+                .bold()
+                .yellow()
+        );
+        return false;
+    }
+    if !platform::request_detach(state.pid) {
+        eprintln!(
+            "{}",
+            "[!] Press b in the session's terminal to move it to the background"
+                .bold()
+                .red()
+        );
+        return false;
+    }
+    println!(
+        "{}",
+        "[>] Moving the session to the background".bold().cyan()
+    );
+    true
+}
+
+/// Notes a pause starting or ending so stats can tell pause time from focus time.
+pub fn record_pause(config: &Config, paused: bool) {
+    let Some(mut state) = load_state(config) else {
+        return;
+    };
+    let now = Local::now();
+    if paused {
+        state.pauses += 1;
+        state.paused_since.get_or_insert(now);
+    } else {
+        state.paused_seconds = state.paused_until(now);
+        state.paused_since = None;
+    }
+    write_state(config, &state);
+}
+
+pub fn load_state(config: &Config) -> Option<SessionState> {
+    fs::read_to_string(state_path(config))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+pub fn clear_state(config: &Config) {
+    let _ = fs::remove_file(state_path(config));
+    let _ = fs::remove_file(socket::socket_path(config));
+}
+
+/// The deadline written into the hosts block includes extensions, so prefer it.
+pub fn deadline(config: &Config, state: &SessionState) -> DateTime<Local> {
+    fs::read_to_string(&config.hosts_path)
+        .ok()
+        .and_then(|content| {
+            hosts::find_blocks(&content, &config.markers)
+                .into_iter()
+                .find(|block| block.scope == config.scope)
+                .and_then(|block| block.info)
+                .and_then(|info| info.deadline)
+        })
+        .unwrap_or(state.start + chrono::Duration::minutes(state.planned_minutes as i64))
+}
+
+/// Seconds left in a running session, from the session itself when it answers.
+pub fn remaining_seconds(config: &Config, state: &SessionState) -> u64 {
+    // The session's own timer stands still while paused; the written deadline doesn't
+    socket::ask(config, serde_json::json!({ "op": "remaining" }))
+        .ok()
+        .and_then(|reply| reply["remaining"].as_u64())
+        .unwrap_or_else(|| {
+            (deadline(config, state) - Local::now())
+                .num_seconds()
+                .max(0) as u64
+        })
+}
+
+/// Prints only the time left, for scripts and status lines; false when nothing is running.
+pub fn print_remaining(config: &Config, clock: bool, seconds: bool) -> bool {
+    let Some(state) = load_state(config).filter(|state| is_alive(state.pid)) else {
+        return false;
+    };
+    let left = remaining_seconds(config, &state);
+    if seconds {
+        println!("{}", left);
+    } else if clock {
+        println!("{}:{:02}", left / 60, left % 60);
+    } else {
+        println!("{}m", left.div_ceil(60));
+    }
+    true
+}
+
+/// Re-anchors on the session: time left, what it is for, and today's goal progress.
+pub fn print_why(config: &Config) -> bool {
+    let Some(state) = load_state(config).filter(|state| is_alive(state.pid)) else {
+        println!("{}", "[+] Focus is not running".bold().green());
+        return false;
+    };
+    let left = remaining_seconds(config, &state);
+    println!(
+        "{}",
+        format!(
+            "[>] {} left in this session",
+            stats::format_minutes(left.div_ceil(60))
+        )
+        .bold()
+        .cyan()
+    );
+    if let Some(tag) = &state.tag {
+        println!("{}", format!("[>] Working on: {}", tag).bold().cyan());
+    }
+    if let Some(goal) = config.daily_goal_minutes {
+        let today = stats::summarize_day(&stats::load_history(config), Local::now().date_naive());
+        // The running session isn't in the history yet
+        let now = Local::now();
+        let elapsed = (now - state.start).num_seconds().max(0) as u64;
+        let focused = today.focused_minutes + elapsed.saturating_sub(state.paused_until(now)) / 60;
+        println!(
+            "{}",
+            format!(
+                "[+] Daily goal: {} of {} ({}%)",
+                stats::format_minutes(focused),
+                stats::format_minutes(goal),
+                stats::goal_percent(focused, goal)
+            )
+            .bold()
+            .green()
+        );
+    }
+    true
+}
+
+/// Finds every running session, keyed by site group (`None` for the default list).
+pub fn list_sessions(config: &Config) -> Vec<(Option<String>, SessionState)> {
+    let Ok(entries) = fs::read_dir(&config.log_directory) else {
+        return Vec::new();
+    };
+    let mut sessions: Vec<(Option<String>, SessionState)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let stem = name.strip_suffix(&format!(".{}", STATE_EXTENSION))?;
+            let scope = match stem {
+                "focus" => None,
+                _ => Some(stem.strip_prefix("focus-")?.to_string()),
+            };
+            let state: SessionState =
+                serde_json::from_str(&fs::read_to_string(entry.path()).ok()?).ok()?;
+            is_alive(state.pid).then_some((scope, state))
+        })
+        .collect();
+    sessions.sort_by(|a, b| a.0.cmp(&b.0));
+    sessions
+}
+
+/// The block for this scope when no live process owns it, e.g. after a crash or `kill -9`.
+pub fn find_orphan(config: &Config) -> Option<FoundBlock> {
+    if load_state(config).is_some_and(|state| is_alive(state.pid)) {
+        return None;
+    }
+    let content = fs::read_to_string(&config.hosts_path).ok()?;
+    hosts::find_blocks(&content, &config.markers)
+        .into_iter()
+        .find(|block| block.scope == config.scope)
+        // Blocks from `focus start` have no process and no deadline by design
+        .filter(|block| {
+            block
+                .info
+                .as_ref()
+                .is_none_or(|info| info.deadline.is_some())
+        })
+}
+
+/// Blocks of any scope whose session is gone and whose deadline has passed, which
+/// nothing would ever remove. Orphans with time left are kept: they can be adopted,
+/// and killing a session must not be a way out of it.
+fn find_stale(config: &Config) -> Vec<FoundBlock> {
+    let Ok(content) = fs::read_to_string(&config.hosts_path) else {
+        return Vec::new();
+    };
+    let live: Vec<Option<String>> = list_sessions(config)
+        .into_iter()
+        .map(|(scope, _)| scope)
+        .collect();
+    hosts::find_blocks(&content, &config.markers)
+        .into_iter()
+        .filter(|block| !live.contains(&block.scope))
+        .filter(|block| {
+            block.info.as_ref().is_some_and(|info| {
+                info.deadline
+                    .is_some_and(|deadline| deadline <= Local::now())
+            })
+        })
+        .collect()
+}
+
+/// Offers to remove stale blocks, or removes them with `remove_stale_blocks`.
+pub fn clean_stale(config: &Config) {
+    let interactive = io::stdin().is_terminal();
+    for block in find_stale(config) {
+        if let Some(description) = util::describe_block(&block) {
+            eprintln!("{}", description.bold().yellow());
+        }
+        let remove = config.remove_stale_blocks
+            || (interactive
+                && prompt::confirm(
+                    &format!("{}", "[?] Remove this block now? [y/N] ".bold().yellow()),
+                    None,
+                ));
+        if !remove {
+            continue;
+        }
+        let mut scoped = config.clone();
+        scoped.scope = block.scope.clone();
+        match util::unblock_sites(&scoped) {
+            Ok(()) => {
+                eprintln!("{}", "[+] Stale block removed".bold().green());
+                audit::record(&scoped, "session", "stale block removed");
+            }
+            Err(e) => eprintln!(
+                "{}",
+                format!("[!] Could not remove the stale block: {}", e)
+                    .bold()
+                    .red()
+            ),
+        }
+    }
+    // Firewall rules carry no deadline, so any left without a session are stale
+    if backend::uses_firewall(config)
+        && platform::is_root()
+        && !list_sessions(config)
+            .iter()
+            .any(|(scope, _)| *scope == config.scope)
+        && privileged::has_firewall(&config.scope)
+        && util::privileged(
+            config,
+            privileged::Request::ClearFirewall {
+                scope: config.scope.clone(),
+            },
+        )
+        .is_ok()
+    {
+        eprintln!("{}", "[+] Stale firewall rules removed".bold().green());
+        audit::record(config, "session", "stale firewall rules removed");
+    }
+}
+
+/// Mentions stale blocks without prompting or touching the firewall, for read-only commands.
+pub fn hint_stale(config: &Config) {
+    for block in find_stale(config) {
+        if let Some(description) = util::describe_block(&block) {
+            eprintln!("{}", description.bold().yellow());
+        }
+    }
+}
+
+/// Whether the session's process still runs, however the system tells.
+pub fn is_alive(pid: u32) -> bool {
+    platform::is_alive(pid)
+}
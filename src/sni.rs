@@ -0,0 +1,371 @@
+#[cfg(target_os = "linux")]
+use crate::{dns, privileged::PROXY_MARK, stats};
+use crate::{
+    privileged::Request,
+    util::{self, Config},
+};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    io,
+    net::{Shutdown, TcpStream},
+    sync::{Arc, atomic::AtomicBool},
+    thread,
+};
+#[cfg(target_os = "linux")]
+use std::{
+    io::{Read, Write},
+    mem,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener},
+    os::fd::{AsRawFd, FromRawFd},
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+#[cfg(target_os = "linux")]
+const SO_ORIGINAL_DST: libc::c_int = 80;
+#[cfg(target_os = "linux")]
+const MAX_CLIENT_HELLO: usize = 16 * 1024;
+#[cfg(target_os = "linux")]
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SniConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+impl Default for SniConfig {
+    fn default() -> Self {
+        SniConfig {
+            enabled: false,
+            port: default_port(),
+        }
+    }
+}
+
+fn default_port() -> u16 {
+    8443
+}
+
+#[cfg(target_os = "linux")]
+pub fn start_proxy(config: Arc<Config>, running: Arc<AtomicBool>) {
+    let listener = match TcpListener::bind(("::", config.sni.port))
+        .or_else(|_| TcpListener::bind(("0.0.0.0", config.sni.port)))
+    {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                format!(
+                    "[!] Failed to start SNI proxy on port {}: {}",
+                    config.sni.port, e
+                )
+                .bold()
+                .red()
+            );
+            return;
+        }
+    };
+    if let Err(e) = install_rules(&config) {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to install nftables redirect: {}", e)
+                .bold()
+                .red()
+        );
+        return;
+    }
+    let _ = listener.set_nonblocking(true);
+    println!(
+        "{}",
+        format!(
+            "[>] SNI proxy intercepting HTTPS on port {}",
+            config.sni.port
+        )
+        .bold()
+        .cyan()
+    );
+
+    thread::spawn(move || {
+        let mut sites = Arc::new(util::blocked_sites(&config));
+        let mut refreshed = Instant::now();
+        while running.load(Ordering::SeqCst) {
+            if refreshed.elapsed() >= dns::SITES_REFRESH {
+                sites = Arc::new(util::blocked_sites(&config));
+                refreshed = Instant::now();
+            }
+            match listener.accept() {
+                Ok((client, _)) => {
+                    let config = Arc::clone(&config);
+                    let sites = Arc::clone(&sites);
+                    thread::spawn(move || handle_connection(&config, &sites, client));
+                }
+                Err(_) => thread::sleep(ACCEPT_INTERVAL),
+            }
+        }
+        remove_rules(&config);
+    });
+}
+
+#[cfg(target_os = "linux")]
+fn install_rules(config: &Config) -> Result<(), String> {
+    util::privileged(
+        config,
+        Request::Redirect {
+            port: config.sni.port,
+        },
+    )
+}
+
+/// The proxy is fed by an nftables redirect, so it only runs on Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn start_proxy(_config: Arc<Config>, _running: Arc<AtomicBool>) {
+    eprintln!("{}", "[!] The SNI proxy only works on Linux".bold().red());
+}
+
+pub fn remove_rules(config: &Config) {
+    let _ = util::privileged(config, Request::ClearRedirect);
+}
+
+#[cfg(target_os = "linux")]
+fn handle_connection(config: &Config, sites: &[String], mut client: TcpStream) {
+    let _ = client.set_nonblocking(false);
+    let Some(destination) = original_destination(&client) else {
+        return;
+    };
+
+    let mut hello = Vec::new();
+    let server_name = read_server_name(&mut client, &mut hello);
+    if let Some(name) = &server_name
+        && dns::is_blocked(sites, name)
+    {
+        if config.dns.log_queries {
+            stats::record_query(config, name, true);
+        }
+        let _ = client.shutdown(Shutdown::Both);
+        return;
+    }
+
+    let Ok(mut upstream) = connect_marked(destination) else {
+        return;
+    };
+    if upstream.write_all(&hello).is_err() {
+        return;
+    }
+    relay(client, upstream);
+}
+
+#[cfg(target_os = "linux")]
+fn read_server_name(client: &mut TcpStream, buffer: &mut Vec<u8>) -> Option<String> {
+    let mut chunk = [0u8; 4096];
+    while buffer.len() < MAX_CLIENT_HELLO {
+        if let Some(name) = parse_server_name(buffer) {
+            return name;
+        }
+        let length = client.read(&mut chunk).ok()?;
+        if length == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..length]);
+    }
+    None
+}
+
+/// Returns `None` while more bytes are needed and `Some(None)` when the hello carries no SNI.
+#[cfg(target_os = "linux")]
+fn parse_server_name(data: &[u8]) -> Option<Option<String>> {
+    if data.len() < 5 {
+        return None;
+    }
+    if data[0] != 0x16 {
+        return Some(None);
+    }
+    let record_length = u16::from_be_bytes([data[3], data[4]]) as usize;
+    let record = data.get(5..5 + record_length)?;
+
+    let mut cursor = Cursor {
+        data: record,
+        position: 0,
+    };
+    let parsed = (|| {
+        if cursor.take(1)?[0] != 0x01 {
+            return None;
+        }
+        cursor.take(3)?;
+        cursor.take(2 + 32)?;
+        let session_id = cursor.take(1)?[0] as usize;
+        cursor.take(session_id)?;
+        let ciphers = cursor.u16()? as usize;
+        cursor.take(ciphers)?;
+        let compression = cursor.take(1)?[0] as usize;
+        cursor.take(compression)?;
+        let extensions_end = cursor.u16()? as usize + cursor.position;
+
+        while cursor.position + 4 <= extensions_end {
+            let extension = cursor.u16()?;
+            let length = cursor.u16()? as usize;
+            let body = cursor.take(length)?;
+            if extension != 0 {
+                continue;
+            }
+            let mut names = Cursor {
+                data: body,
+                position: 2,
+            };
+            while names.position < body.len() {
+                let kind = names.take(1)?[0];
+                let length = names.u16()? as usize;
+                let name = names.take(length)?;
+                if kind == 0 {
+                    return Some(String::from_utf8_lossy(name).to_string());
+                }
+            }
+        }
+        None
+    })();
+    Some(parsed)
+}
+
+#[cfg(target_os = "linux")]
+struct Cursor<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+#[cfg(target_os = "linux")]
+impl<'a> Cursor<'a> {
+    fn take(&mut self, length: usize) -> Option<&'a [u8]> {
+        let slice = self.data.get(self.position..self.position + length)?;
+        self.position += length;
+        Some(slice)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let bytes = self.take(2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn original_destination(stream: &TcpStream) -> Option<SocketAddr> {
+    let fd = stream.as_raw_fd();
+
+    let mut address: libc::sockaddr_in = unsafe { mem::zeroed() };
+    let mut length = mem::size_of::<libc::sockaddr_in>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_IP,
+            SO_ORIGINAL_DST,
+            &mut address as *mut _ as *mut libc::c_void,
+            &mut length,
+        )
+    };
+    if result == 0 {
+        let ip = Ipv4Addr::from(u32::from_be(address.sin_addr.s_addr));
+        return Some(SocketAddr::from((ip, u16::from_be(address.sin_port))));
+    }
+
+    let mut address: libc::sockaddr_in6 = unsafe { mem::zeroed() };
+    let mut length = mem::size_of::<libc::sockaddr_in6>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_IPV6,
+            SO_ORIGINAL_DST,
+            &mut address as *mut _ as *mut libc::c_void,
+            &mut length,
+        )
+    };
+    if result != 0 {
+        return None;
+    }
+    let ip = Ipv6Addr::from(address.sin6_addr.s6_addr);
+    Some(SocketAddr::from((ip, u16::from_be(address.sin6_port))))
+}
+
+#[cfg(target_os = "linux")]
+fn connect_marked(destination: SocketAddr) -> io::Result<TcpStream> {
+    let domain = if destination.is_ipv4() {
+        libc::AF_INET
+    } else {
+        libc::AF_INET6
+    };
+    let fd = unsafe { libc::socket(domain, libc::SOCK_STREAM | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mark = PROXY_MARK;
+    unsafe {
+        libc::setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_MARK,
+            &mark as *const _ as *const libc::c_void,
+            mem::size_of::<u32>() as libc::socklen_t,
+        );
+    }
+
+    // Hand the marked socket to std, which then performs the connect
+    let socket = unsafe { TcpStream::from_raw_fd(fd) };
+    let (address, length) = socket_address(destination);
+    let result =
+        unsafe { libc::connect(fd, &address as *const _ as *const libc::sockaddr, length) };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(socket)
+}
+
+#[cfg(target_os = "linux")]
+fn socket_address(address: SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let length = match address {
+        SocketAddr::V4(address) => {
+            let raw = libc::sockaddr_in {
+                sin_family: libc::AF_INET as libc::sa_family_t,
+                sin_port: address.port().to_be(),
+                sin_addr: libc::in_addr {
+                    s_addr: u32::from(*address.ip()).to_be(),
+                },
+                sin_zero: [0; 8],
+            };
+            unsafe { *(&mut storage as *mut _ as *mut libc::sockaddr_in) = raw };
+            mem::size_of::<libc::sockaddr_in>()
+        }
+        SocketAddr::V6(address) => {
+            let raw = libc::sockaddr_in6 {
+                sin6_family: libc::AF_INET6 as libc::sa_family_t,
+                sin6_port: address.port().to_be(),
+                sin6_flowinfo: address.flowinfo(),
+                sin6_addr: libc::in6_addr {
+                    s6_addr: address.ip().octets(),
+                },
+                sin6_scope_id: address.scope_id(),
+            };
+            unsafe { *(&mut storage as *mut _ as *mut libc::sockaddr_in6) = raw };
+            mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+    (storage, length as libc::socklen_t)
+}
+
+pub fn relay(client: TcpStream, upstream: TcpStream) {
+    let (Ok(mut client_reader), Ok(mut upstream_writer)) =
+        (client.try_clone(), upstream.try_clone())
+    else {
+        return;
+    };
+    let outbound = thread::spawn(move || {
+        let _ = io::copy(&mut client_reader, &mut upstream_writer);
+        let _ = upstream_writer.shutdown(Shutdown::Write);
+    });
+
+    let (mut upstream_reader, mut client_writer) = (upstream, client);
+    let _ = io::copy(&mut upstream_reader, &mut client_writer);
+    let _ = client_writer.shutdown(Shutdown::Write);
+    let _ = outbound.join();
+}
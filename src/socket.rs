@@ -0,0 +1,734 @@
+//! The session's control socket, for the CLI, tray apps and third-party clients.
+//!
+//! Messages are JSON objects, each sent as a frame: a 4-byte big-endian length, then
+//! that many bytes of UTF-8 JSON, at most 1 MiB. A connection starts with a handshake:
+//!
+//! ```text
+//! -> {"op":"hello","protocol_version":1}
+//! <- {"ok":true,"protocol_version":1,"server":"0.1.0"}
+//! ```
+//!
+//! The reply carries the version both sides will speak, the lower of the two; a client
+//! older than the server still supports gets `{"ok":false,"error":...}` and the
+//! connection closes. After the handshake the client sends any number of requests and
+//! gets one reply per request, until it closes the connection or stays idle for 5 seconds.
+//!
+//! | request | reply |
+//! |---|---|
+//! | `{"op":"status"}` | `group`, `remaining` (seconds), `paused`, `tag`, `profile`, `strict` |
+//! | `{"op":"remaining"}` | `remaining` |
+//! | `{"op":"stats"}` | today's `focused_minutes` and `sessions` |
+//! | `{"op":"extend","minutes":10}` | `remaining` |
+//! | `{"op":"pause"}`, `{"op":"resume"}` | `paused` |
+//! | `{"op":"stop"}` | nothing else |
+//! | `{"op":"add","sites":["example.com"]}` | `sites`, the number newly blocked |
+//! | `{"op":"end"}` | nothing else; root or the owner, for `focus stop` after its own checks |
+//! | `{"op":"expect-config","digest":"..."}` | nothing else; root only, for focus about to save its config |
+//! | `{"op":"watch"}` | a stream instead of a reply, see below |
+//!
+//! Every reply has `"ok"`, and an `"error"` string when it is false. Requests that change
+//! the session need the session's owner, root, or `"token"` matching `[socket] token`.
+//! `watch` turns the connection into a stream of `{"remaining","paused"}` ticks every
+//! second and event objects with `event`, `title`, `message` and `minutes`, as webhooks get.
+//!
+//! Windows has no Unix sockets, so there the session listens on a loopback TCP port written
+//! to the socket file. The peer's user is unknown, so changes always need the token.
+//!
+//! Within a protocol version, fields are only ever added: clients ignore fields they
+//! don't know and the server ignores unknown request fields. Removing or changing the
+//! meaning of a field bumps `PROTOCOL_VERSION`. Clients that send a single
+//! newline-terminated JSON request without a frame or handshake are still answered the
+//! same way, one request per connection, as before versioning.
+
+use crate::{
+    audit, bypass,
+    controls::{self, CLEAR_LINE},
+    events, platform, protect,
+    session::Timer,
+    stats,
+    util::{self, Config},
+};
+use chrono::Local;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+#[cfg(target_os = "windows")]
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::{
+    fs::PermissionsExt,
+    io::AsRawFd,
+    net::{UnixListener, UnixStream},
+};
+use std::{
+    env, fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    process,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+const SOCKET_EXTENSION: &str = "sock";
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(500);
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// Connections served at once; anyone may connect, so more are turned away
+const MAX_CLIENTS: usize = 32;
+const MAX_EXTEND: u64 = 240;
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+/// How often `focus events --follow` looks for the next session
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+/// The version this build speaks; see the module docs for what a bump means
+pub const PROTOCOL_VERSION: u32 = 1;
+/// The oldest version still answered
+const MIN_PROTOCOL_VERSION: u32 = 1;
+const MAX_FRAME: usize = 1 << 20;
+
+/// A connection to the session; Windows has no Unix sockets in std, so it uses loopback TCP
+#[cfg(unix)]
+type Stream = UnixStream;
+#[cfg(target_os = "windows")]
+type Stream = TcpStream;
+
+/// A client following the session with `watch`, such as `focus attach`
+struct Watcher {
+    stream: Stream,
+    framed: bool,
+}
+
+type Watchers = Arc<Mutex<Vec<Watcher>>>;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SocketConfig {
+    #[serde(default = "util::default_true")]
+    pub enabled: bool,
+    /// Lets clients other than the session's owner change the session
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+impl Default for SocketConfig {
+    fn default() -> Self {
+        SocketConfig {
+            enabled: true,
+            token: None,
+        }
+    }
+}
+
+/// A request, e.g. `{"op":"remaining"}` or `{"op":"extend","minutes":10,"token":"..."}`.
+#[derive(Debug, Deserialize)]
+struct Message {
+    #[serde(flatten)]
+    query: Query,
+    #[serde(default)]
+    token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "kebab-case")]
+enum Query {
+    /// Opens a framed connection and agrees on the protocol version
+    Hello {
+        protocol_version: u32,
+    },
+    Status,
+    Remaining,
+    Stats,
+    Extend {
+        minutes: u64,
+    },
+    Pause,
+    Resume,
+    Stop,
+    /// Blocks more sites for the rest of the session, as `focus add` does
+    Add {
+        sites: Vec<String>,
+    },
+    /// Ends the session without asking again; root, or the owner once a strict session's
+    /// bypass code is spent
+    End,
+    /// Announces the SHA-256 of a config focus is about to save, so the strict session's
+    /// guard keeps it; only root, which writes the config
+    ExpectConfig {
+        digest: String,
+    },
+    /// Keeps the connection open and streams the countdown and session events
+    Watch,
+}
+
+impl Query {
+    /// Anyone who can reach the socket may read; only the owner or a token holder may change things.
+    fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Query::Hello { .. } | Query::Status | Query::Remaining | Query::Stats | Query::Watch
+        )
+    }
+}
+
+pub fn socket_path(config: &Config) -> String {
+    util::runtime_path(config, SOCKET_EXTENSION)
+}
+
+/// Serves status to widgets and other unprivileged clients for the length of the session.
+pub fn start_server(
+    config: Arc<Config>,
+    running: Arc<AtomicBool>,
+    timer: Arc<Timer>,
+    tag: Option<String>,
+) {
+    let path = socket_path(&config);
+    let _ = fs::remove_file(&path);
+    let listener = match listen(&path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                format!("[!] Failed to open socket {}: {}", path, e)
+                    .bold()
+                    .red()
+            );
+            return;
+        }
+    };
+    let _ = listener.set_nonblocking(true);
+    let owner = owner_uid();
+    let tag = Arc::new(tag);
+    let watchers: Watchers = Arc::default();
+    let event_watchers = Arc::clone(&watchers);
+    events::subscribe(move |_, event| broadcast(&event_watchers, &event.payload()));
+
+    thread::spawn(move || {
+        let clients = Arc::new(AtomicUsize::new(0));
+        while running.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((client, _)) => {
+                    // Only this thread adds clients, so the count can't pass the cap
+                    if clients.load(Ordering::SeqCst) >= MAX_CLIENTS {
+                        continue;
+                    }
+                    let _ = client.set_read_timeout(Some(READ_TIMEOUT));
+                    clients.fetch_add(1, Ordering::SeqCst);
+                    let config = Arc::clone(&config);
+                    let timer = Arc::clone(&timer);
+                    let tag = Arc::clone(&tag);
+                    let running = Arc::clone(&running);
+                    let watchers = Arc::clone(&watchers);
+                    let clients = Arc::clone(&clients);
+                    thread::spawn(move || {
+                        let watch = (running.as_ref(), &watchers);
+                        handle_client(&config, &timer, tag.as_deref(), owner, watch, client);
+                        clients.fetch_sub(1, Ordering::SeqCst);
+                    });
+                }
+                Err(_) => thread::sleep(ACCEPT_INTERVAL),
+            }
+        }
+    });
+}
+
+/// Binds the socket, open to everyone so widgets can reach it.
+#[cfg(unix)]
+fn listen(path: &str) -> io::Result<UnixListener> {
+    let listener = UnixListener::bind(path)?;
+    let _ = fs::set_permissions(path, fs::Permissions::from_mode(0o666));
+    Ok(listener)
+}
+
+/// Listens on a loopback port and leaves the port number where the socket would be.
+#[cfg(target_os = "windows")]
+fn listen(path: &str) -> io::Result<TcpListener> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    fs::write(path, listener.local_addr()?.port().to_string())?;
+    Ok(listener)
+}
+
+#[cfg(unix)]
+fn connect(path: &str) -> io::Result<Stream> {
+    UnixStream::connect(path)
+}
+
+#[cfg(target_os = "windows")]
+fn connect(path: &str) -> io::Result<Stream> {
+    let port = fs::read_to_string(path)?
+        .trim()
+        .parse::<u16>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+}
+
+/// The user who started the session, looking through sudo.
+pub fn owner_uid() -> u32 {
+    env::var("SUDO_UID")
+        .ok()
+        .and_then(|uid| uid.parse().ok())
+        .unwrap_or_else(platform::user_id)
+}
+
+#[cfg(target_os = "linux")]
+pub fn peer_uid(client: &Stream) -> Option<u32> {
+    let mut credentials = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut length = size_of::<libc::ucred>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            client.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut credentials as *mut libc::ucred as *mut libc::c_void,
+            &mut length,
+        )
+    };
+    (result == 0).then_some(credentials.uid)
+}
+
+/// macOS has no `SO_PEERCRED`; `getpeereid` reads the same `LOCAL_PEERCRED` credentials.
+#[cfg(target_os = "macos")]
+pub fn peer_uid(client: &Stream) -> Option<u32> {
+    let (mut uid, mut gid) = (0, 0);
+    let result = unsafe { libc::getpeereid(client.as_raw_fd(), &mut uid, &mut gid) };
+    (result == 0).then_some(uid)
+}
+
+/// Loopback TCP doesn't say who connected, so Windows clients need the socket token to
+/// change the session.
+#[cfg(target_os = "windows")]
+pub fn peer_uid(_client: &Stream) -> Option<u32> {
+    None
+}
+
+/// A frame or, for clients from before versioning, a newline-terminated line.
+fn encode(value: &serde_json::Value, framed: bool) -> Vec<u8> {
+    let body = value.to_string().into_bytes();
+    if !framed {
+        return [body, vec![b'\n']].concat();
+    }
+    [(body.len() as u32).to_be_bytes().to_vec(), body].concat()
+}
+
+fn read_frame(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut length = [0; 4];
+    reader.read_exact(&mut length)?;
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_FRAME {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {} bytes is over the limit", length),
+        ));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(body)
+}
+
+fn handle_client(
+    config: &Config,
+    timer: &Timer,
+    tag: Option<&str>,
+    owner: u32,
+    (running, watchers): (&AtomicBool, &Watchers),
+    client: Stream,
+) {
+    let _ = client.set_nonblocking(false);
+    let uid = peer_uid(&client);
+    let Ok(mut writer) = client.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(client);
+    // Frames are under 16 MiB, so their first byte is always 0 and never starts a JSON line
+    let framed = match reader.fill_buf() {
+        Ok([first, ..]) => *first == 0,
+        _ => return,
+    };
+
+    if !framed {
+        let mut line = String::new();
+        if reader.read_line(&mut line).is_err() {
+            return;
+        }
+        let reply = match serde_json::from_str::<Message>(&line) {
+            Ok(Message {
+                query: Query::Watch,
+                ..
+            }) => return watch(timer, running, watchers, writer, false),
+            Ok(message) => respond(config, timer, tag, owner, uid, message),
+            Err(e) => error(&format!("invalid request: {}", e)),
+        };
+        let _ = writer.write_all(&encode(&reply, false));
+        return;
+    }
+
+    let mut greeted = false;
+    while let Ok(frame) = read_frame(&mut reader) {
+        let reply = match serde_json::from_slice::<Message>(&frame) {
+            Ok(Message {
+                query: Query::Hello { protocol_version },
+                ..
+            }) if protocol_version < MIN_PROTOCOL_VERSION => {
+                let reply = serde_json::json!({
+                    "ok": false,
+                    "error": format!("protocol version {} is no longer supported", protocol_version),
+                    "protocol_version": PROTOCOL_VERSION,
+                });
+                let _ = writer.write_all(&encode(&reply, true));
+                return;
+            }
+            Ok(Message {
+                query: Query::Hello { protocol_version },
+                ..
+            }) => {
+                greeted = true;
+                serde_json::json!({
+                    "ok": true,
+                    "protocol_version": protocol_version.min(PROTOCOL_VERSION),
+                    "server": env!("CARGO_PKG_VERSION"),
+                })
+            }
+            _ if !greeted => {
+                let reply = error("the first request must be hello");
+                let _ = writer.write_all(&encode(&reply, true));
+                return;
+            }
+            Ok(Message {
+                query: Query::Watch,
+                ..
+            }) => return watch(timer, running, watchers, writer, true),
+            Ok(message) => respond(config, timer, tag, owner, uid, message),
+            Err(e) => error(&format!("invalid request: {}", e)),
+        };
+        if writer.write_all(&encode(&reply, true)).is_err() {
+            return;
+        }
+    }
+}
+
+/// Answers a request if the client may make it.
+fn respond(
+    config: &Config,
+    timer: &Timer,
+    tag: Option<&str>,
+    owner: u32,
+    uid: Option<u32>,
+    message: Message,
+) -> serde_json::Value {
+    let trusted = uid.is_some_and(|uid| uid == 0 || uid == owner)
+        || config
+            .socket
+            .token
+            .as_ref()
+            .is_some_and(|token| message.token.as_ref() == Some(token));
+    // `focus stop` has already spent the stop or the bypass code; the owner's client may
+    // ask too, but a strict session only ends this way once its bypass code is redeemed
+    if matches!(message.query, Query::End) && uid != Some(0) {
+        if !trusted {
+            audit::record(config, "socket", "refused end from another user");
+            return error("only root or the session owner may end the session this way");
+        }
+        if config.strict && !bypass::was_used(config) {
+            audit::record(config, "socket", "refused end of a strict session");
+            return error("a strict session ends early only with its bypass code");
+        }
+    }
+    // Windows can't tell who asks, but only administrators can write the config there
+    if matches!(message.query, Query::ExpectConfig { .. }) && uid != Some(0) && cfg!(unix) {
+        audit::record(config, "socket", "refused config from a non-root client");
+        return error("only root may announce a config");
+    }
+    if message.query.is_read_only() || trusted {
+        answer(config, timer, tag, message.query)
+    } else {
+        audit::record(config, "socket", "refused change from another user");
+        error("read-only client: changes need the session owner or the socket token")
+    }
+}
+
+/// Sends the countdown every second until the session ends or the client leaves; events
+/// reach the client through `broadcast` in between.
+fn watch(
+    timer: &Timer,
+    running: &AtomicBool,
+    watchers: &Watchers,
+    mut client: Stream,
+    framed: bool,
+) {
+    let _ = client.set_write_timeout(Some(READ_TIMEOUT));
+    if let Ok(stream) = client.try_clone() {
+        watchers.lock().unwrap().push(Watcher { stream, framed });
+    }
+    while running.load(Ordering::SeqCst) {
+        let tick = serde_json::json!({
+            "remaining": timer.remaining().as_secs(),
+            "paused": timer.is_paused(),
+        });
+        // One write per message, so ticks and events never interleave
+        if client.write_all(&encode(&tick, framed)).is_err() {
+            return;
+        }
+        thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+fn broadcast(watchers: &Watchers, payload: &serde_json::Value) {
+    watchers.lock().unwrap().retain_mut(|watcher| {
+        watcher
+            .stream
+            .write_all(&encode(payload, watcher.framed))
+            .is_ok()
+    });
+}
+
+/// A framed connection to the session's socket.
+pub struct Client {
+    reader: BufReader<Stream>,
+    writer: Stream,
+    /// The version agreed on in the handshake
+    pub protocol_version: u32,
+}
+
+impl Client {
+    /// Connects to the running session and agrees on a protocol version.
+    pub fn connect(config: &Config) -> Result<Self, String> {
+        let stream = connect(&socket_path(config)).map_err(|e| e.to_string())?;
+        let writer = stream.try_clone().map_err(|e| e.to_string())?;
+        let mut client = Client {
+            reader: BufReader::new(stream),
+            writer,
+            protocol_version: PROTOCOL_VERSION,
+        };
+        let reply = client.request(&serde_json::json!({
+            "op": "hello",
+            "protocol_version": PROTOCOL_VERSION,
+        }))?;
+        match reply["protocol_version"].as_u64() {
+            Some(version) if reply["ok"] == true => client.protocol_version = version as u32,
+            _ => {
+                return Err(reply["error"]
+                    .as_str()
+                    .unwrap_or("handshake failed")
+                    .to_string());
+            }
+        }
+        Ok(client)
+    }
+
+    /// Sends a request and waits for its reply.
+    pub fn request(&mut self, request: &serde_json::Value) -> Result<serde_json::Value, String> {
+        self.send(request)?;
+        self.receive()
+    }
+
+    pub fn send(&mut self, request: &serde_json::Value) -> Result<(), String> {
+        self.writer
+            .write_all(&encode(request, true))
+            .map_err(|e| e.to_string())
+    }
+
+    /// The next reply, or the next tick or event after `watch`.
+    pub fn receive(&mut self) -> Result<serde_json::Value, String> {
+        let frame = read_frame(&mut self.reader).map_err(|e| e.to_string())?;
+        serde_json::from_slice(&frame).map_err(|e| e.to_string())
+    }
+}
+
+/// Sends one request to the running session; an `"ok": false` reply becomes the error.
+pub fn ask(config: &Config, mut request: serde_json::Value) -> Result<serde_json::Value, String> {
+    // Where the session can't tell who connects, as on Windows, the token vouches for us
+    if let Some(token) = &config.socket.token
+        && request.get("token").is_none()
+    {
+        request["token"] = serde_json::json!(token);
+    }
+    let reply = Client::connect(config)?.request(&request)?;
+    if reply["ok"] == true {
+        Ok(reply)
+    } else {
+        Err(reply["error"]
+            .as_str()
+            .unwrap_or("request failed")
+            .to_string())
+    }
+}
+
+/// Follows a running session's countdown and events; Ctrl-C leaves it running.
+pub fn attach(config: &Config) -> bool {
+    let Ok(mut client) = Client::connect(config) else {
+        eprintln!(
+            "{}",
+            "[!] No active focus session found, or its socket is disabled"
+                .bold()
+                .red()
+        );
+        return false;
+    };
+    if client.send(&serde_json::json!({ "op": "watch" })).is_err() {
+        return false;
+    }
+    let _ = ctrlc::set_handler(|| {
+        println!(
+            "{}{}",
+            CLEAR_LINE,
+            "[>] Detached; the session keeps running".bold().cyan()
+        );
+        process::exit(0);
+    });
+    println!(
+        "{}",
+        "[>] Attached; Ctrl-C detaches without ending the session"
+            .bold()
+            .cyan()
+    );
+
+    while let Ok(message) = client.receive() {
+        match message["event"].as_str() {
+            Some(kind) => {
+                let text = message["message"].as_str().unwrap_or(kind);
+                if kind == "tamper-detected" {
+                    println!("{}{}", CLEAR_LINE, format!("[!] {}", text).bold().red());
+                } else {
+                    println!("{}{}", CLEAR_LINE, format!("[>] {}", text).bold().cyan());
+                }
+                if matches!(kind, "session-ended" | "session-stopped") {
+                    return true;
+                }
+            }
+            None => {
+                if let Some(remaining) = message["remaining"].as_u64() {
+                    controls::draw_countdown(remaining, message["paused"] == true);
+                }
+            }
+        }
+    }
+    println!(
+        "{}{}",
+        CLEAR_LINE,
+        "[!] The session is no longer running".bold().yellow()
+    );
+    false
+}
+
+/// Prints the session's events as NDJSON, one object per line with a `timestamp` added, until
+/// it ends; `follow` waits for the next session instead and only stops when interrupted.
+pub fn stream_events(config: &Config, follow: bool) -> bool {
+    let mut stdout = io::stdout();
+    loop {
+        let client = Client::connect(config).ok().and_then(|mut client| {
+            client.send(&serde_json::json!({ "op": "watch" })).ok()?;
+            Some(client)
+        });
+        match client {
+            Some(mut client) => {
+                let mut paused = None;
+                while let Ok(mut message) = client.receive() {
+                    if message["event"].is_null() {
+                        // Pausing isn't an event of its own; the ticks show it flipping
+                        let now = message["paused"] == true;
+                        let flipped = paused.is_some_and(|was| was != now);
+                        paused = Some(now);
+                        if !flipped {
+                            continue;
+                        }
+                        message = serde_json::json!({
+                            "event": if now { "paused" } else { "resumed" },
+                            "remaining": message["remaining"],
+                        });
+                    }
+                    message["timestamp"] = serde_json::json!(Local::now().to_rfc3339());
+                    // A closed pipe means the reader is gone
+                    if writeln!(stdout, "{}", message).is_err() {
+                        return true;
+                    }
+                }
+            }
+            None if !follow => {
+                eprintln!(
+                    "{}",
+                    "[!] No active focus session found, or its socket is disabled"
+                        .bold()
+                        .red()
+                );
+                return false;
+            }
+            None => {}
+        }
+        if !follow {
+            return true;
+        }
+        thread::sleep(RECONNECT_INTERVAL);
+    }
+}
+
+fn answer(config: &Config, timer: &Timer, tag: Option<&str>, query: Query) -> serde_json::Value {
+    match query {
+        Query::Status => serde_json::json!({
+            "ok": true,
+            "group": config.scope,
+            "remaining": timer.remaining().as_secs(),
+            "paused": timer.is_paused(),
+            "tag": tag,
+            "profile": config.profile,
+            "strict": config.strict,
+        }),
+        Query::Remaining => serde_json::json!({
+            "ok": true,
+            "remaining": timer.remaining().as_secs(),
+        }),
+        Query::Stats => {
+            let today =
+                stats::summarize_day(&stats::load_history(config), Local::now().date_naive());
+            serde_json::json!({
+                "ok": true,
+                "focused_minutes": today.focused_minutes,
+                "sessions": today.sessions,
+            })
+        }
+        Query::Extend { minutes } if minutes == 0 || minutes > MAX_EXTEND => {
+            error(&format!("minutes must be between 1 and {}", MAX_EXTEND))
+        }
+        Query::Extend { minutes } => {
+            util::extend_session(config, timer, Duration::from_mins(minutes));
+            audit::record(config, "session", "extended");
+            serde_json::json!({ "ok": true, "remaining": timer.remaining().as_secs() })
+        }
+        Query::Pause | Query::Resume if timer.is_paused() == matches!(query, Query::Pause) => {
+            serde_json::json!({ "ok": true, "paused": timer.is_paused() })
+        }
+        Query::Pause | Query::Resume => {
+            if controls::toggle_pause(config, timer) {
+                serde_json::json!({ "ok": true, "paused": timer.is_paused() })
+            } else {
+                error("pausing is not allowed right now")
+            }
+        }
+        Query::Hello { .. } => error("hello needs a framed connection"),
+        Query::Watch => error("watch is only valid as the first request"),
+        Query::Add { sites } => {
+            let added = util::add_live_sites(config, &sites);
+            audit::record(config, "session", "sites added");
+            serde_json::json!({ "ok": true, "sites": added })
+        }
+        Query::ExpectConfig { digest } => {
+            protect::expect(digest);
+            serde_json::json!({ "ok": true })
+        }
+        Query::End => {
+            util::approve_stop();
+            platform::interrupt();
+            serde_json::json!({ "ok": true })
+        }
+        // Goes through the Ctrl-C path so strict, admin and rate-limit checks still apply
+        Query::Stop => {
+            platform::interrupt();
+            serde_json::json!({ "ok": true })
+        }
+    }
+}
+
+fn error(message: &str) -> serde_json::Value {
+    serde_json::json!({ "ok": false, "error": message })
+}
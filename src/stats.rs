@@ -0,0 +1,764 @@
+use crate::{limits, microbreak, notify::Event, ritual::ChecklistResult, session, util::Config};
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::Write,
+};
+
+const HISTORY_FILE: &str = "focus.history";
+const TAMPER_FILE: &str = "focus.tamper";
+const QUERY_FILE: &str = "focus.queries";
+const TOP_DOMAINS: usize = 10;
+const HEATMAP_LEVELS: [&str; 5] = ["·", "░", "▒", "▓", "█"];
+/// Ten years of columns, well inside chrono's calendar
+const HEATMAP_MAX_WEEKS: u32 = 520;
+const WEEKDAY_LABELS: [&str; 7] = ["Mon", "", "Wed", "", "Fri", "", "Sun"];
+const UNTAGGED: &str = "(untagged)";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionRecord {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub planned_minutes: u64,
+    pub focused_minutes: u64,
+    pub outcome: String,
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub checklist: Option<ChecklistResult>,
+    #[serde(default)]
+    pub journal: Option<String>,
+    #[serde(default)]
+    pub micro_breaks: u32,
+    #[serde(default)]
+    pub paused_minutes: u64,
+    #[serde(default)]
+    pub pauses: u32,
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Tampering caught during the session, from the tamper log
+    #[serde(default)]
+    pub tampers: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TamperRecord {
+    pub timestamp: DateTime<Local>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueryRecord {
+    pub timestamp: DateTime<Local>,
+    pub domain: String,
+    pub blocked: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Retention {
+    pub history_days: Option<u64>,
+    pub tamper_log_days: Option<u64>,
+    pub query_log_days: Option<u64>,
+}
+
+pub struct DaySummary {
+    pub focused_minutes: u64,
+    pub sessions: usize,
+}
+
+pub fn history_path(config: &Config) -> String {
+    format!("{}/{}", config.log_directory, HISTORY_FILE)
+}
+
+pub fn tamper_path(config: &Config) -> String {
+    format!("{}/{}", config.log_directory, TAMPER_FILE)
+}
+
+pub fn query_path(config: &Config) -> String {
+    format!("{}/{}", config.log_directory, QUERY_FILE)
+}
+
+/// The history side of the event pipeline: finished sessions and tampering.
+pub fn record_event(config: &Config, event: &Event) {
+    match event {
+        Event::SessionEnded { journal } => record_session(config, "completed", journal.clone()),
+        Event::SessionStopped { outcome } => record_session(config, outcome, None),
+        Event::TamperDetected => record_tamper(config),
+        _ => {}
+    }
+}
+
+pub fn record_session(config: &Config, outcome: &str, journal: Option<String>) {
+    let Some(state) = session::load_state(config) else {
+        return;
+    };
+    session::clear_state(config);
+
+    let end = Local::now();
+    let paused_seconds = state.paused_until(end);
+    let elapsed_seconds = (end - state.start).num_seconds().max(0) as u64;
+    let record = SessionRecord {
+        start: state.start,
+        end,
+        planned_minutes: state.planned_minutes,
+        focused_minutes: elapsed_seconds.saturating_sub(paused_seconds) / 60,
+        outcome: outcome.to_string(),
+        tag: state.tag,
+        checklist: state.checklist,
+        journal,
+        micro_breaks: microbreak::taken(),
+        paused_minutes: paused_seconds / 60,
+        pauses: state.pauses,
+        profile: state.profile,
+        tampers: count_tampers(config, state.start, end),
+    };
+
+    let line = serde_json::to_string(&record).expect("[!] Could not encode session record");
+    append_line(&history_path(config), &line, "session history");
+    prune_expired(config);
+}
+
+pub fn record_tamper(config: &Config) {
+    let record = TamperRecord {
+        timestamp: Local::now(),
+    };
+    let line = serde_json::to_string(&record).expect("[!] Could not encode tamper record");
+    append_line(&tamper_path(config), &line, "tamper log");
+}
+
+fn count_tampers(config: &Config, start: DateTime<Local>, end: DateTime<Local>) -> u32 {
+    let Ok(content) = fs::read_to_string(tamper_path(config)) else {
+        return 0;
+    };
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<TamperRecord>(line).ok())
+        .filter(|record| record.timestamp >= start && record.timestamp <= end)
+        .count() as u32
+}
+
+pub fn record_query(config: &Config, domain: &str, blocked: bool) {
+    let record = QueryRecord {
+        timestamp: Local::now(),
+        domain: domain.trim_end_matches('.').to_lowercase(),
+        blocked,
+    };
+    let line = serde_json::to_string(&record).expect("[!] Could not encode query record");
+    append_line(&query_path(config), &line, "query log");
+}
+
+pub fn load_queries(config: &Config) -> Vec<QueryRecord> {
+    let Ok(content) = fs::read_to_string(query_path(config)) else {
+        return Vec::new();
+    };
+
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+fn append_line(path: &str, line: &str, description: &str) {
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{}", line));
+    if let Err(e) = result {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to write {}: {}", description, e)
+                .bold()
+                .yellow()
+        );
+    }
+}
+
+pub fn prune_expired(config: &Config) {
+    let today = Local::now().date_naive();
+    // A retention reaching past chrono's calendar keeps everything
+    let cutoff = |days: Option<u64>| days.and_then(|days| today.checked_sub_days(Days::new(days)));
+    if let Some(before) = cutoff(config.retention.history_days) {
+        prune_history(config, before);
+    }
+    if let Some(before) = cutoff(config.retention.tamper_log_days) {
+        prune_tamper_log(config, before);
+    }
+    if let Some(before) = cutoff(config.retention.query_log_days) {
+        prune_query_log(config, before);
+    }
+}
+
+pub fn prune_before(config: &Config, before: NaiveDate) {
+    let sessions = prune_history(config, before);
+    let tampers = prune_tamper_log(config, before);
+    let queries = prune_query_log(config, before);
+    println!(
+        "{}",
+        format!(
+            "[+] Pruned {} sessions, {} tamper events and {} queries before {}",
+            sessions, tampers, queries, before
+        )
+        .bold()
+        .green()
+    );
+}
+
+fn prune_history(config: &Config, before: NaiveDate) -> usize {
+    prune_lines(&history_path(config), |line| {
+        serde_json::from_str::<SessionRecord>(line)
+            .map(|record| record.start.date_naive() >= before)
+            .unwrap_or(true)
+    })
+}
+
+fn prune_tamper_log(config: &Config, before: NaiveDate) -> usize {
+    prune_lines(&tamper_path(config), |line| {
+        serde_json::from_str::<TamperRecord>(line)
+            .map(|record| record.timestamp.date_naive() >= before)
+            .unwrap_or(true)
+    })
+}
+
+fn prune_query_log(config: &Config, before: NaiveDate) -> usize {
+    prune_lines(&query_path(config), |line| {
+        serde_json::from_str::<QueryRecord>(line)
+            .map(|record| record.timestamp.date_naive() >= before)
+            .unwrap_or(true)
+    })
+}
+
+fn prune_lines(path: &str, keep: impl Fn(&str) -> bool) -> usize {
+    let Ok(content) = fs::read_to_string(path) else {
+        return 0;
+    };
+
+    let (kept, pruned): (Vec<&str>, Vec<&str>) = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .partition(|line| keep(line));
+    if pruned.is_empty() {
+        return 0;
+    }
+
+    let mut new_content = kept.join("\n");
+    if !new_content.is_empty() {
+        new_content.push('\n');
+    }
+    if let Err(e) = fs::write(path, new_content) {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to prune {}: {}", path, e)
+                .bold()
+                .yellow()
+        );
+        return 0;
+    }
+    pruned.len()
+}
+
+pub fn load_history(config: &Config) -> Vec<SessionRecord> {
+    let Ok(content) = fs::read_to_string(history_path(config)) else {
+        return Vec::new();
+    };
+
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+pub fn summarize_day(history: &[SessionRecord], day: NaiveDate) -> DaySummary {
+    let sessions: Vec<&SessionRecord> = history
+        .iter()
+        .filter(|record| record.start.date_naive() == day)
+        .collect();
+
+    DaySummary {
+        focused_minutes: sessions.iter().map(|record| record.focused_minutes).sum(),
+        sessions: sessions.len(),
+    }
+}
+
+pub fn show_stats(config: &Config, today_only: bool) {
+    let history = load_history(config);
+    let today = summarize_day(&history, Local::now().date_naive());
+
+    if today_only {
+        println!("{}", today_line(config, &today));
+        return;
+    }
+
+    println!(
+        "{}",
+        format!(
+            "[+] Today: {} focused across {} sessions",
+            format_minutes(today.focused_minutes),
+            today.sessions
+        )
+        .bold()
+        .green()
+    );
+    if let Some(goal) = config.daily_goal_minutes {
+        println!(
+            "{}",
+            format!(
+                "[+] Daily goal: {} of {} ({}%)",
+                format_minutes(today.focused_minutes),
+                format_minutes(goal),
+                goal_percent(today.focused_minutes, goal)
+            )
+            .bold()
+            .green()
+        );
+    }
+
+    let (current, longest) = streaks(&history);
+    if longest > 0 {
+        println!(
+            "{}",
+            format!(
+                "[+] Streak: {} (longest: {})",
+                format_days(current),
+                format_days(longest)
+            )
+            .bold()
+            .green()
+        );
+    }
+
+    let bypasses = bypasses_this_week(&history);
+    if bypasses > 0 {
+        println!(
+            "{}",
+            format!(
+                "[!] Emergency bypass code used {} this week",
+                match bypasses {
+                    1 => String::from("once"),
+                    count => format!("{} times", count),
+                }
+            )
+            .bold()
+            .yellow()
+        );
+    }
+
+    let counters = limits::load_counters(config);
+    if counters.refused_stops + counters.refused_pauses > 0 {
+        println!(
+            "{}",
+            format!(
+                "[!] Daily limits refused {} early stops and {} pauses today",
+                counters.refused_stops, counters.refused_pauses
+            )
+            .bold()
+            .yellow()
+        );
+    }
+
+    show_week_comparison(&history);
+    show_week_pauses(&history);
+    show_week_journal(&history);
+}
+
+/// Runs of consecutive days with focused time: the one reaching today or yesterday, and the
+/// longest. Today not having a session yet doesn't break the current run.
+fn streaks(history: &[SessionRecord]) -> (u64, u64) {
+    let mut days: Vec<NaiveDate> = history
+        .iter()
+        .filter(|record| record.focused_minutes > 0)
+        .map(|record| record.start.date_naive())
+        .collect();
+    days.sort();
+    days.dedup();
+
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for day in &days {
+        run = match previous {
+            Some(previous) if previous.succ_opt() == Some(*day) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(*day);
+    }
+
+    let today = Local::now().date_naive();
+    let current = match previous {
+        Some(last) if last == today || last.succ_opt() == Some(today) => run,
+        _ => 0,
+    };
+    (current, longest)
+}
+
+fn bypasses_this_week(history: &[SessionRecord]) -> usize {
+    let today = Local::now().date_naive();
+    let this_monday = today - Days::new(today.weekday().num_days_from_monday() as u64);
+    history
+        .iter()
+        .filter(|record| record.outcome == "bypassed" && record.start.date_naive() >= this_monday)
+        .count()
+}
+
+fn format_days(days: u64) -> String {
+    match days {
+        1 => String::from("1 day"),
+        days => format!("{} days", days),
+    }
+}
+
+/// Pause time is left out of focused minutes, so it is shown on its own.
+fn show_week_pauses(history: &[SessionRecord]) {
+    let today = Local::now().date_naive();
+    let this_monday = today - Days::new(today.weekday().num_days_from_monday() as u64);
+    let (minutes, pauses) = history
+        .iter()
+        .filter(|record| record.start.date_naive() >= this_monday)
+        .fold((0, 0), |(minutes, pauses), record| {
+            (minutes + record.paused_minutes, pauses + record.pauses)
+        });
+    if pauses > 0 {
+        println!(
+            "{}",
+            format!(
+                "[+] Paused this week: {} across {} pause{}",
+                format_minutes(minutes),
+                pauses,
+                if pauses == 1 { "" } else { "s" }
+            )
+            .bold()
+            .yellow()
+        );
+    }
+}
+
+fn show_week_journal(history: &[SessionRecord]) {
+    let today = Local::now().date_naive();
+    let this_monday = today - Days::new(today.weekday().num_days_from_monday() as u64);
+    let entries: Vec<&SessionRecord> = history
+        .iter()
+        .filter(|record| record.start.date_naive() >= this_monday && record.journal.is_some())
+        .collect();
+    if entries.is_empty() {
+        return;
+    }
+
+    println!("{}", "[+] Journal this week:".bold().green());
+    for record in entries {
+        println!(
+            "    {}  {}",
+            record.start.format("%a %H:%M"),
+            record.journal.as_deref().unwrap_or_default()
+        );
+    }
+}
+
+/// Lists recent sessions, newest first, with their journal entries.
+pub fn show_log(config: &Config, limit: usize) {
+    let history = load_history(config);
+    if history.is_empty() {
+        println!("{}", "[+] No sessions recorded yet".bold().green());
+        return;
+    }
+
+    for record in history.iter().rev().take(limit) {
+        let tag = record
+            .tag
+            .as_deref()
+            .map(|tag| format!(" [{}]", tag))
+            .unwrap_or_default();
+        let paused = match record.pauses {
+            0 => String::new(),
+            _ => format!(", paused {}", format_minutes(record.paused_minutes)),
+        };
+        let breaks = match record.micro_breaks {
+            0 => String::new(),
+            1 => String::from(", 1 micro-break"),
+            count => format!(", {} micro-breaks", count),
+        };
+        let tampers = match record.tampers {
+            0 => String::new(),
+            1 => String::from(", 1 tamper attempt"),
+            count => format!(", {} tamper attempts", count),
+        };
+        let profile = record
+            .profile
+            .as_deref()
+            .map(|profile| format!(" ({})", profile))
+            .unwrap_or_default();
+        println!(
+            "{}",
+            format!(
+                "[+] {}  {:>7}  {}{}{}{}{}{}",
+                record.start.format("%Y-%m-%d %H:%M"),
+                format_minutes(record.focused_minutes),
+                record.outcome,
+                paused,
+                breaks,
+                tampers,
+                profile,
+                tag
+            )
+            .bold()
+            .green()
+        );
+        if let Some(journal) = &record.journal {
+            println!("    {}", journal);
+        }
+    }
+}
+
+/// Today, the daily goal, streaks and the week so far, for `focus stats --json`.
+pub fn stats_json(config: &Config) -> serde_json::Value {
+    let history = load_history(config);
+    let today = Local::now().date_naive();
+    let summary = summarize_day(&history, today);
+    let (current, longest) = streaks(&history);
+    let counters = limits::load_counters(config);
+    let this_monday = today - Days::new(today.weekday().num_days_from_monday() as u64);
+    let last_monday = this_monday - Days::new(7);
+    let week_minutes = |from: NaiveDate, to: NaiveDate| -> u64 {
+        history
+            .iter()
+            .filter(|record| (from..to).contains(&record.start.date_naive()))
+            .map(|record| record.focused_minutes)
+            .sum()
+    };
+    serde_json::json!({
+        "today": {
+            "focused_minutes": summary.focused_minutes,
+            "sessions": summary.sessions,
+            "goal_minutes": config.daily_goal_minutes,
+            "goal_percent": config
+                .daily_goal_minutes
+                .map(|goal| goal_percent(summary.focused_minutes, goal)),
+            "refused_stops": counters.refused_stops,
+            "refused_pauses": counters.refused_pauses,
+        },
+        "streak": {
+            "current_days": current,
+            "longest_days": longest,
+        },
+        "this_week_minutes": week_minutes(this_monday, today + Days::new(1)),
+        "bypasses_this_week": bypasses_this_week(&history),
+        "last_week_minutes": week_minutes(last_monday, this_monday),
+    })
+}
+
+fn show_week_comparison(history: &[SessionRecord]) {
+    let today = Local::now().date_naive();
+    let this_monday = today - Days::new(today.weekday().num_days_from_monday() as u64);
+    let last_monday = this_monday - Days::new(7);
+
+    let mut this_week: HashMap<&str, u64> = HashMap::new();
+    let mut last_week: HashMap<&str, u64> = HashMap::new();
+    for record in history {
+        let day = record.start.date_naive();
+        let tag = record.tag.as_deref().unwrap_or(UNTAGGED);
+        if day >= this_monday {
+            *this_week.entry(tag).or_default() += record.focused_minutes;
+        } else if day >= last_monday {
+            *last_week.entry(tag).or_default() += record.focused_minutes;
+        }
+    }
+
+    let this_total: u64 = this_week.values().sum();
+    let last_total: u64 = last_week.values().sum();
+    println!(
+        "{}",
+        format!(
+            "[+] This week: {} vs last week: {} ({})",
+            format_minutes(this_total),
+            format_minutes(last_total),
+            format_change(this_total, last_total)
+        )
+        .bold()
+        .green()
+    );
+
+    let mut tags: Vec<&str> = this_week.keys().chain(last_week.keys()).copied().collect();
+    tags.sort();
+    tags.dedup();
+    for tag in tags {
+        let current = this_week.get(tag).copied().unwrap_or(0);
+        let previous = last_week.get(tag).copied().unwrap_or(0);
+        println!(
+            "    {:<16} {:>7} vs {:>7} ({})",
+            tag,
+            format_minutes(current),
+            format_minutes(previous),
+            format_change(current, previous)
+        );
+    }
+}
+
+fn format_change(current: u64, previous: u64) -> String {
+    let delta = current as i64 - previous as i64;
+    let sign = if delta < 0 { "-" } else { "+" };
+    let absolute = format!("{}{}", sign, format_minutes(delta.unsigned_abs()));
+    if previous == 0 {
+        return absolute;
+    }
+    format!(
+        "{}, {}{}%",
+        absolute,
+        sign,
+        delta.unsigned_abs() * 100 / previous
+    )
+}
+
+pub fn show_queries(config: &Config) {
+    let queries: Vec<QueryRecord> = load_queries(config)
+        .into_iter()
+        .filter(|query| query.blocked)
+        .collect();
+    let today = Local::now().date_naive();
+
+    let todays: Vec<&QueryRecord> = queries
+        .iter()
+        .filter(|query| query.timestamp.date_naive() == today)
+        .collect();
+    println!(
+        "{}",
+        format!(
+            "[+] Most requested blocked domains today ({} queries)",
+            todays.len()
+        )
+        .bold()
+        .green()
+    );
+    print_top_domains(&todays);
+
+    let mut sessions: Vec<(DateTime<Local>, DateTime<Local>)> = load_history(config)
+        .iter()
+        .filter(|record| record.start.date_naive() == today)
+        .map(|record| (record.start, record.end))
+        .collect();
+    if let Some(state) = session::load_state(config) {
+        sessions.push((state.start, Local::now()));
+    }
+
+    for (start, end) in sessions {
+        let in_session: Vec<&QueryRecord> = queries
+            .iter()
+            .filter(|query| query.timestamp >= start && query.timestamp <= end)
+            .collect();
+        if in_session.is_empty() {
+            continue;
+        }
+        println!(
+            "{}",
+            format!(
+                "[+] Session {}-{} ({} queries)",
+                start.format("%H:%M"),
+                end.format("%H:%M"),
+                in_session.len()
+            )
+            .bold()
+            .green()
+        );
+        print_top_domains(&in_session);
+    }
+}
+
+fn print_top_domains(queries: &[&QueryRecord]) {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for query in queries {
+        *counts.entry(&query.domain).or_default() += 1;
+    }
+    let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    for (domain, count) in counts.into_iter().take(TOP_DOMAINS) {
+        println!("    {:>6}  {}", count, domain);
+    }
+}
+
+pub fn show_heatmap(config: &Config, weeks: u32) {
+    let weeks = weeks.clamp(1, HEATMAP_MAX_WEEKS);
+    let mut minutes_by_day: HashMap<NaiveDate, u64> = HashMap::new();
+    for record in load_history(config) {
+        *minutes_by_day.entry(record.start.date_naive()).or_default() += record.focused_minutes;
+    }
+
+    let today = Local::now().date_naive();
+    let this_monday = today - Days::new(today.weekday().num_days_from_monday() as u64);
+    let first_day = this_monday - Days::new(7 * (weeks as u64 - 1));
+
+    let scale = config
+        .daily_goal_minutes
+        .or_else(|| minutes_by_day.values().copied().max())
+        .unwrap_or(0)
+        .max(1);
+
+    for (weekday, label) in WEEKDAY_LABELS.iter().enumerate() {
+        let mut row = format!("{:<4}", label);
+        for week in 0..weeks {
+            let day = first_day + Days::new(week as u64 * 7 + weekday as u64);
+            if day > today {
+                row.push_str("  ");
+                continue;
+            }
+            let minutes = minutes_by_day.get(&day).copied().unwrap_or(0);
+            row.push_str(&format!("{} ", heatmap_cell(minutes, scale)));
+        }
+        println!("{}", row.trim_end());
+    }
+
+    let total: u64 = minutes_by_day
+        .iter()
+        .filter(|(day, _)| **day >= first_day)
+        .map(|(_, minutes)| minutes)
+        .sum();
+    println!(
+        "{}",
+        format!(
+            "[+] {} focused over the past {} weeks",
+            format_minutes(total),
+            weeks
+        )
+        .bold()
+        .green()
+    );
+}
+
+fn heatmap_cell(minutes: u64, scale: u64) -> String {
+    if minutes == 0 {
+        return HEATMAP_LEVELS[0].dimmed().to_string();
+    }
+    let level = (1 + minutes * 4 / (scale + 1)).min(4) as usize;
+    HEATMAP_LEVELS[level].green().to_string()
+}
+
+fn today_line(config: &Config, today: &DaySummary) -> String {
+    let mut line = format!(
+        "{} focused, {} sessions",
+        format_minutes(today.focused_minutes),
+        today.sessions
+    );
+    if let Some(goal) = config.daily_goal_minutes {
+        line.push_str(&format!(
+            ", goal {}%",
+            goal_percent(today.focused_minutes, goal)
+        ));
+    }
+    line
+}
+
+pub fn goal_percent(minutes: u64, goal: u64) -> u64 {
+    if goal == 0 {
+        return 100;
+    }
+    minutes * 100 / goal
+}
+
+pub fn format_minutes(minutes: u64) -> String {
+    if minutes < 60 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}h{:02}m", minutes / 60, minutes % 60)
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{notify, util::Config};
+use colored::Colorize;
+use minisign_verify::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+const SUBSCRIPTIONS_DIRECTORY: &str = "subscriptions";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Subscription {
+    pub name: String,
+    pub url: String,
+    pub public_key: String,
+    #[serde(default)]
+    pub signature_url: Option<String>,
+}
+
+fn cache_path(config: &Config, subscription: &Subscription) -> String {
+    format!(
+        "{}/{}/{}.list",
+        config.data_directory, SUBSCRIPTIONS_DIRECTORY, subscription.name
+    )
+}
+
+pub fn refresh(config: &Config) {
+    if config.subscriptions.is_empty() {
+        return;
+    }
+    let _ = fs::create_dir_all(format!(
+        "{}/{}",
+        config.data_directory, SUBSCRIPTIONS_DIRECTORY
+    ));
+
+    for subscription in &config.subscriptions {
+        match fetch_verified(subscription) {
+            Ok(content) => {
+                let count = parse_sites(&content).len();
+                if let Err(e) = fs::write(cache_path(config, subscription), content) {
+                    eprintln!(
+                        "{}",
+                        format!("[!] Failed to cache {}: {}", subscription.name, e)
+                            .bold()
+                            .yellow()
+                    );
+                    continue;
+                }
+                println!(
+                    "{}",
+                    format!(
+                        "[+] Subscription {} verified ({} sites)",
+                        subscription.name, count
+                    )
+                    .bold()
+                    .green()
+                );
+            }
+            Err(e) => eprintln!(
+                "{}",
+                format!(
+                    "[!] Subscription {} not updated, keeping cached copy: {}",
+                    subscription.name, e
+                )
+                .bold()
+                .yellow()
+            ),
+        }
+    }
+}
+
+fn fetch_verified(subscription: &Subscription) -> Result<String, String> {
+    let public_key = PublicKey::from_base64(&subscription.public_key).map_err(|e| e.to_string())?;
+    let signature_url = subscription
+        .signature_url
+        .clone()
+        .unwrap_or_else(|| format!("{}.minisig", subscription.url));
+
+    let agent = notify::http_agent();
+    let content = agent
+        .get(&subscription.url)
+        .call()
+        .and_then(|mut response| response.body_mut().read_to_string())
+        .map_err(|e| e.to_string())?;
+    let signature = agent
+        .get(&signature_url)
+        .call()
+        .and_then(|mut response| response.body_mut().read_to_string())
+        .map_err(|e| e.to_string())?;
+
+    let signature = Signature::decode(&signature).map_err(|e| e.to_string())?;
+    public_key
+        .verify(content.as_bytes(), &signature, false)
+        .map_err(|e| format!("signature verification failed: {}", e))?;
+    Ok(content)
+}
+
+pub fn load_sites(config: &Config) -> Vec<String> {
+    config
+        .subscriptions
+        .iter()
+        .filter_map(|subscription| fs::read_to_string(cache_path(config, subscription)).ok())
+        .flat_map(|content| parse_sites(&content))
+        .collect()
+}
+
+pub fn parse_sites(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let first = fields.next()?;
+            Some(fields.next().unwrap_or(first).to_string())
+        })
+        .filter(|site| {
+            site.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+        })
+        .collect()
+}
@@ -0,0 +1,138 @@
+use crate::{events, notify::Event, schedule, stats, util::Config};
+use chrono::{Datelike, Days, Local, NaiveDate, Utc, Weekday};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, time::Instant};
+
+const SENT_EXTENSION: &str = "summary";
+const TOP_BLOCKED: usize = 3;
+
+/// A screen-time style report of the day, and optionally the week, sent at a set time.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SummaryConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_time")]
+    pub time: String,
+    /// Also sends a weekly summary on this day, e.g. "Sun"
+    #[serde(default)]
+    pub weekly_on: Option<Weekday>,
+}
+
+impl Default for SummaryConfig {
+    fn default() -> Self {
+        SummaryConfig {
+            enabled: false,
+            time: default_time(),
+            weekly_on: None,
+        }
+    }
+}
+
+fn default_time() -> String {
+    String::from("18:00")
+}
+
+/// When each summary last went out, so a summary is never sent twice for a day.
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct Sent {
+    daily: Option<NaiveDate>,
+    weekly: Option<NaiveDate>,
+}
+
+fn sent_path(config: &Config) -> String {
+    format!("{}/focus.{}", config.log_directory, SENT_EXTENSION)
+}
+
+fn load_sent(config: &Config) -> Sent {
+    fs::read_to_string(sent_path(config))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// The summary for the day or the last seven days, ending today.
+pub fn build(config: &Config, weekly: bool) -> Event {
+    let today = Local::now().date_naive();
+    let first = today
+        .checked_sub_days(Days::new(if weekly { 6 } else { 0 }))
+        .unwrap_or(today);
+    let in_range = |day: NaiveDate| first <= day && day <= today;
+
+    let history: Vec<stats::SessionRecord> = stats::load_history(config)
+        .into_iter()
+        .filter(|record| in_range(record.start.date_naive()))
+        .collect();
+    let mut attempts: HashMap<String, usize> = HashMap::new();
+    for query in stats::load_queries(config)
+        .into_iter()
+        .filter(|query| query.blocked && in_range(query.timestamp.date_naive()))
+    {
+        *attempts.entry(query.domain).or_default() += 1;
+    }
+    let mut top_blocked: Vec<(String, usize)> = attempts.into_iter().collect();
+    top_blocked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    top_blocked.truncate(TOP_BLOCKED);
+
+    Event::Summary {
+        weekly,
+        focused_minutes: history.iter().map(|record| record.focused_minutes).sum(),
+        sessions: history.len(),
+        top_blocked,
+    }
+}
+
+/// Prints the summary and sends it through the configured notifiers.
+pub fn send(config: &Config, weekly: bool) {
+    let event = build(config, weekly);
+    println!(
+        "{}",
+        format!("[+] {}: {}", event.title(), event.message())
+            .bold()
+            .green()
+    );
+    events::publish(config, event);
+
+    let mut sent = load_sent(config);
+    let today = Some(Local::now().date_naive());
+    if weekly {
+        sent.weekly = today;
+    } else {
+        sent.daily = today;
+    }
+    if let Ok(content) = serde_json::to_string(&sent) {
+        let _ = fs::write(sent_path(config), content);
+    }
+}
+
+/// Sends whichever summaries are due today and haven't gone out yet.
+pub fn send_due(config: &Config) {
+    let today = Local::now().date_naive();
+    let sent = load_sent(config);
+    if sent.daily != Some(today) {
+        send(config, false);
+    }
+    if config.summary.weekly_on == Some(today.weekday()) && sent.weekly != Some(today) {
+        send(config, true);
+    }
+}
+
+/// The next time the summary is due, for the session timeline.
+pub fn next_due(config: &Config) -> Option<Instant> {
+    if !config.summary.enabled {
+        return None;
+    }
+    let time = match schedule::parse_time(&config.summary.time) {
+        Ok(time) => time,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                format!("[!] Invalid summary time: {}", e).bold().red()
+            );
+            return None;
+        }
+    };
+    let now = Utc::now();
+    let due = schedule::next_occurrence(schedule::default_zone(config), time, now);
+    Some(Instant::now() + (due - now).to_std().ok()?)
+}
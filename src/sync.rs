@@ -0,0 +1,196 @@
+use crate::util::{self, Config};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::Path,
+    process::{self, Command, Output},
+};
+
+const CONFIG_FILE: &str = "config.toml";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SyncConfig {
+    pub remote: String,
+    #[serde(default = "default_branch")]
+    pub branch: String,
+}
+
+fn default_branch() -> String {
+    String::from("main")
+}
+
+fn git(directory: &str, args: &[&str]) -> Output {
+    Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(args)
+        .output()
+        .unwrap_or_else(|e| {
+            eprintln!("{}", format!("[!] Failed to run git: {}", e).bold().red());
+            process::exit(1);
+        })
+}
+
+fn git_checked(directory: &str, args: &[&str]) -> String {
+    let output = git(directory, args);
+    if !output.status.success() {
+        eprintln!(
+            "{}",
+            format!(
+                "[!] git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .bold()
+            .red()
+        );
+        process::exit(1);
+    }
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+pub fn sync(config: &Config) {
+    let Some(sync) = &config.sync else {
+        eprintln!(
+            "{}",
+            "[!] No [sync] remote configured in config.toml"
+                .bold()
+                .red()
+        );
+        process::exit(1);
+    };
+    let directory = util::config_directory();
+
+    if !Path::new(&format!("{}/.git", directory)).exists() {
+        println!(
+            "{}",
+            format!("[>] Initializing git repository in {}", directory)
+                .bold()
+                .cyan()
+        );
+        git_checked(&directory, &["init", "--initial-branch", &sync.branch]);
+        git_checked(&directory, &["remote", "add", "origin", &sync.remote]);
+    }
+
+    git_checked(&directory, &["add", "-A"]);
+    if !git(&directory, &["diff", "--cached", "--quiet"])
+        .status
+        .success()
+    {
+        println!("{}", "[>] Committing local changes".bold().cyan());
+        git_checked(
+            &directory,
+            &["commit", "-m", &format!("focus sync from {}", hostname())],
+        );
+    }
+
+    println!("{}", "[>] Fetching remote changes".bold().cyan());
+    let fetch = git(&directory, &["fetch", "origin", &sync.branch]);
+    if fetch.status.success() {
+        let remote_ref = format!("origin/{}", sync.branch);
+        let merge = git(
+            &directory,
+            &[
+                "merge",
+                "--no-edit",
+                "--allow-unrelated-histories",
+                &remote_ref,
+            ],
+        );
+        if !merge.status.success() && !resolve_conflicts(&directory) {
+            git(&directory, &["merge", "--abort"]);
+            eprintln!(
+                "{}",
+                format!(
+                    "[!] Could not merge remote changes automatically. Resolve them manually in {}",
+                    directory
+                )
+                .bold()
+                .red()
+            );
+            process::exit(1);
+        }
+    }
+
+    println!("{}", "[>] Pushing to remote".bold().cyan());
+    git_checked(&directory, &["push", "-u", "origin", &sync.branch]);
+    println!("{}", "[+] Config synchronized".bold().green());
+}
+
+fn resolve_conflicts(directory: &str) -> bool {
+    let conflicted = git_checked(directory, &["diff", "--name-only", "--diff-filter=U"]);
+    if conflicted.lines().any(|file| file != CONFIG_FILE) {
+        return false;
+    }
+
+    let base = read_stage(directory, 1);
+    let (Some(ours), Some(theirs)) = (read_stage(directory, 2), read_stage(directory, 3)) else {
+        return false;
+    };
+    let Some(merged) = merge_blocklists(base.as_ref(), &ours, &theirs) else {
+        return false;
+    };
+
+    let content = toml::to_string(&merged).expect("[!] Could not encode config to TOML");
+    if fs::write(format!("{}/{}", directory, CONFIG_FILE), content).is_err() {
+        return false;
+    }
+    git_checked(directory, &["add", CONFIG_FILE]);
+    git_checked(directory, &["commit", "--no-edit"]);
+    println!(
+        "{}",
+        "[+] Merged blocklist changes from both machines"
+            .bold()
+            .green()
+    );
+    true
+}
+
+fn read_stage(directory: &str, stage: u8) -> Option<Config> {
+    let output = git(directory, &["show", &format!(":{}:{}", stage, CONFIG_FILE)]);
+    if !output.status.success() {
+        return None;
+    }
+    toml::from_str(&String::from_utf8_lossy(&output.stdout)).ok()
+}
+
+/// Three-way merges `blocked_sites` when that is the only field both sides changed.
+fn merge_blocklists(base: Option<&Config>, ours: &Config, theirs: &Config) -> Option<Config> {
+    let mut ours_without_sites = ours.clone();
+    ours_without_sites.blocked_sites = theirs.blocked_sites.clone();
+    if toml::to_string(&ours_without_sites).ok()? != toml::to_string(theirs).ok()? {
+        return None;
+    }
+
+    let base_sites: BTreeSet<&String> = base
+        .map(|base| base.blocked_sites.iter().collect())
+        .unwrap_or_default();
+    let our_sites: BTreeSet<&String> = ours.blocked_sites.iter().collect();
+    let their_sites: BTreeSet<&String> = theirs.blocked_sites.iter().collect();
+
+    let mut merged = ours.clone();
+    merged.blocked_sites = ours
+        .blocked_sites
+        .iter()
+        .chain(theirs.blocked_sites.iter())
+        .filter(|site| {
+            let in_ours = our_sites.contains(site);
+            let in_theirs = their_sites.contains(site);
+            (in_ours && in_theirs) || !base_sites.contains(site)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut seen = BTreeSet::new();
+    merged
+        .blocked_sites
+        .retain(|site| seen.insert(site.clone()));
+    Some(merged)
+}
+
+fn hostname() -> String {
+    fs::read_to_string("/etc/hostname")
+        .map(|name| name.trim().to_string())
+        .unwrap_or_else(|_| String::from("unknown host"))
+}
@@ -0,0 +1,157 @@
+use crate::{
+    notify::{self, Event, Notifier},
+    session::Timer,
+    util::{self, Config},
+};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+const API_URL: &str = "https://api.telegram.org";
+const POLL_TIMEOUT: u64 = 25;
+const RETRY_INTERVAL: u64 = 30;
+const MAX_EXTEND: u64 = 240;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+    pub chat_id: i64,
+    #[serde(default = "default_remote_control")]
+    pub remote_control: bool,
+}
+
+fn default_remote_control() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct Updates {
+    result: Vec<Update>,
+}
+
+#[derive(Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    chat: Chat,
+    date: i64,
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+pub fn send_message(config: &TelegramConfig, text: &str) {
+    let url = format!("{}/bot{}/sendMessage", API_URL, config.bot_token);
+    let body = serde_json::json!({ "chat_id": config.chat_id, "text": text });
+    if let Err(e) = notify::http_agent().post(&url).send_json(&body) {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to send Telegram message: {}", e)
+                .bold()
+                .yellow()
+        );
+    }
+}
+
+impl Notifier for TelegramConfig {
+    fn notify(&self, event: &Event) {
+        send_message(self, &event.message());
+    }
+}
+
+pub fn start_command_thread(config: Arc<Config>, timer: Arc<Timer>, running: Arc<AtomicBool>) {
+    let Some(telegram) = config
+        .telegram
+        .clone()
+        .filter(|telegram| telegram.remote_control)
+    else {
+        return;
+    };
+
+    thread::spawn(move || {
+        let agent = notify::http_agent_with_timeout(Duration::from_secs(POLL_TIMEOUT + 10));
+        let url = format!("{}/bot{}/getUpdates", API_URL, telegram.bot_token);
+        // Commands queued before the session started were meant for an earlier one
+        let started = chrono::Utc::now().timestamp();
+        let mut offset = 0;
+
+        while running.load(Ordering::SeqCst) {
+            let updates = agent
+                .get(&url)
+                .query("offset", offset.to_string())
+                .query("timeout", POLL_TIMEOUT.to_string())
+                .call()
+                .and_then(|mut response| response.body_mut().read_json::<Updates>());
+
+            let updates = match updates {
+                Ok(updates) => updates,
+                Err(_) => {
+                    thread::sleep(Duration::from_secs(RETRY_INTERVAL));
+                    continue;
+                }
+            };
+
+            for update in updates.result {
+                offset = update.update_id + 1;
+                let Some(message) = update.message else {
+                    continue;
+                };
+                if message.chat.id != telegram.chat_id
+                    || message.date < started
+                    || !running.load(Ordering::SeqCst)
+                {
+                    continue;
+                }
+                if let Some(reply) =
+                    handle_command(message.text.as_deref().unwrap_or(""), &config, &timer)
+                {
+                    send_message(&telegram, &reply);
+                }
+            }
+        }
+    });
+}
+
+fn handle_command(text: &str, config: &Config, timer: &Timer) -> Option<String> {
+    let mut parts = text.split_whitespace();
+    let command = parts.next()?.split('@').next()?;
+
+    match command {
+        "/status" => Some(format!(
+            "Focus session active: {} minutes remaining",
+            timer.remaining().as_secs().div_ceil(60)
+        )),
+        "/extend" => match parts.next().map(str::parse::<u64>) {
+            Some(Ok(minutes)) if minutes > 0 && minutes <= MAX_EXTEND => {
+                util::extend_session(config, timer, Duration::from_mins(minutes));
+                println!(
+                    "{}",
+                    format!("[>] Session extended by {} minutes via Telegram", minutes)
+                        .bold()
+                        .cyan()
+                );
+                Some(format!(
+                    "Extended by {} minutes: {} minutes remaining",
+                    minutes,
+                    timer.remaining().as_secs().div_ceil(60)
+                ))
+            }
+            _ => Some(format!("Usage: /extend <minutes> (1-{})", MAX_EXTEND)),
+        },
+        _ => Some(String::from("Commands: /status, /extend <minutes>")),
+    }
+}
@@ -0,0 +1,122 @@
+use crate::session::Timer;
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// How long to sleep with nothing due, e.g. while paused; any timer change wakes it sooner.
+const IDLE: Duration = Duration::from_secs(60);
+
+/// Timed session events, handled in this order when several fall due together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Tick {
+    SoftStartEnd,
+    TamperCheck,
+    Summary,
+    Halfway,
+    MicroBreak,
+    WrappingUp,
+    Deadline,
+}
+
+impl Tick {
+    /// Milestones may fire a little early to share a wakeup; checks and the deadline never do.
+    fn batches(&self) -> bool {
+        !matches!(self, Tick::TamperCheck | Tick::Deadline)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Due {
+    /// A fixed point in time, for periodic work
+    At(Instant),
+    /// When the session has this much left, which moves with pauses and extensions
+    Remaining(Duration),
+}
+
+/// A single queue for everything timed in a session, drained by one thread that
+/// sleeps until the next event instead of every feature polling on its own.
+pub struct Timeline {
+    timer: Arc<Timer>,
+    queue: Mutex<Vec<(Due, Tick)>>,
+    slack: Mutex<Duration>,
+    /// Milestones due before this are handled along with the tick that just woke the thread
+    batch_until: Mutex<Option<Instant>>,
+}
+
+impl Timeline {
+    pub fn new(timer: Arc<Timer>) -> Self {
+        Timeline {
+            timer,
+            queue: Mutex::new(Vec::new()),
+            slack: Mutex::new(Duration::ZERO),
+            batch_until: Mutex::new(None),
+        }
+    }
+
+    pub fn timer(&self) -> Arc<Timer> {
+        Arc::clone(&self.timer)
+    }
+
+    pub fn schedule(&self, due: Due, tick: Tick) {
+        self.queue.lock().unwrap().push((due, tick));
+        self.timer.notify_change();
+    }
+
+    /// Lets milestones due within `slack` of a wakeup fire with it, e.g. on battery.
+    pub fn set_slack(&self, slack: Duration) {
+        *self.slack.lock().unwrap() = slack;
+    }
+
+    /// `None` while paused, since paused sessions never reach a remaining-time event.
+    fn instant(&self, due: Due) -> Option<Instant> {
+        match due {
+            Due::At(instant) => Some(instant),
+            Due::Remaining(_) if self.timer.is_paused() => None,
+            Due::Remaining(left) => {
+                Some(Instant::now() + self.timer.remaining().saturating_sub(left))
+            }
+        }
+    }
+
+    /// Blocks until the next event is due and takes it off the queue; `None` once
+    /// `running` is cleared.
+    pub fn next(&self, running: &AtomicBool) -> Option<Tick> {
+        loop {
+            if !running.load(Ordering::SeqCst) {
+                return None;
+            }
+            let seen = self.timer.changes();
+            let mut queue = self.queue.lock().unwrap();
+            let now = Instant::now();
+            let batch_until = self.batch_until.lock().unwrap().unwrap_or(now);
+            let scheduled: Vec<(Instant, Tick, usize)> = queue
+                .iter()
+                .enumerate()
+                .filter_map(|(index, (due, tick))| Some((self.instant(*due)?, *tick, index)))
+                .collect();
+            let due = scheduled
+                .iter()
+                .filter(|(instant, tick, _)| {
+                    *instant <= now || (tick.batches() && *instant <= batch_until)
+                })
+                .min();
+            if let Some(&(instant, tick, index)) = due {
+                if instant <= now {
+                    *self.batch_until.lock().unwrap() = Some(now + *self.slack.lock().unwrap());
+                }
+                queue.remove(index);
+                return Some(tick);
+            }
+            let timeout = match scheduled.iter().min() {
+                Some((instant, ..)) => (*instant - now).min(IDLE),
+                None => IDLE,
+            };
+            drop(queue);
+            self.timer.wait_for_change(seen, timeout);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{
+    prompt,
+    stats::{self, QueryRecord},
+    util::Config,
+};
+use chrono::{Duration as Span, Local};
+use colored::Colorize;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    time::Duration,
+};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+const WINDOW_MINUTES: i64 = 10;
+const MAX_ROWS: usize = 20;
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
+
+/// Follows the query log as it grows, keeping the last `WINDOW_MINUTES` of blocked queries.
+struct Follower {
+    path: String,
+    offset: u64,
+    partial: String,
+    queries: Vec<QueryRecord>,
+}
+
+impl Follower {
+    fn poll(&mut self) {
+        let Ok(mut file) = File::open(&self.path) else {
+            return;
+        };
+        let length = file.metadata().map(|meta| meta.len()).unwrap_or(0);
+        // Pruned or rotated since the last read
+        if length < self.offset {
+            self.offset = 0;
+            self.partial.clear();
+        }
+        let mut appended = String::new();
+        if file.seek(SeekFrom::Start(self.offset)).is_err()
+            || file.read_to_string(&mut appended).is_err()
+        {
+            return;
+        }
+        self.offset += appended.len() as u64;
+        self.partial.push_str(&appended);
+
+        let complete = self.partial.rfind('\n').map_or(0, |end| end + 1);
+        let lines: String = self.partial.drain(..complete).collect();
+        self.queries.extend(
+            lines
+                .lines()
+                .filter_map(|line| serde_json::from_str::<QueryRecord>(line).ok())
+                .filter(|query| query.blocked),
+        );
+        let since = Local::now() - Span::minutes(WINDOW_MINUTES);
+        self.queries.retain(|query| query.timestamp >= since);
+    }
+}
+
+/// A `top`-style view of blocked DNS queries per domain, refreshed until q or Ctrl-C.
+pub fn run(config: &Config) {
+    if !config.dns.enabled || !config.dns.log_queries {
+        eprintln!(
+            "{}",
+            "[!] Blocked queries are only logged by the DNS backend; set enabled and log_queries under [dns]"
+                .bold()
+                .yellow()
+        );
+    }
+    let mut follower = Follower {
+        path: stats::query_path(config),
+        offset: 0,
+        partial: String::new(),
+        queries: Vec::new(),
+    };
+    let keys = prompt::enter_cbreak();
+    loop {
+        follower.poll();
+        draw(&follower.queries);
+        if keys {
+            if prompt::read_key(REFRESH_INTERVAL) == Some('q') {
+                break;
+            }
+        } else {
+            std::thread::sleep(REFRESH_INTERVAL);
+        }
+    }
+    prompt::restore_terminal();
+}
+
+fn draw(queries: &[QueryRecord]) {
+    let now = Local::now();
+    let mut domains: HashMap<&str, [usize; WINDOW_MINUTES as usize]> = HashMap::new();
+    for query in queries {
+        let age = (now - query.timestamp)
+            .num_minutes()
+            .clamp(0, WINDOW_MINUTES - 1);
+        // Oldest minute first, so the sparkline reads left to right
+        domains.entry(&query.domain).or_default()[(WINDOW_MINUTES - 1 - age) as usize] += 1;
+    }
+    let mut rows: Vec<(&str, [usize; WINDOW_MINUTES as usize])> = domains.into_iter().collect();
+    rows.sort_by(|a, b| {
+        let (last_a, last_b) = (
+            a.1[WINDOW_MINUTES as usize - 1],
+            b.1[WINDOW_MINUTES as usize - 1],
+        );
+        let (total_a, total_b) = (a.1.iter().sum::<usize>(), b.1.iter().sum::<usize>());
+        last_b
+            .cmp(&last_a)
+            .then(total_b.cmp(&total_a))
+            .then(a.0.cmp(b.0))
+    });
+
+    let mut out = String::from(CLEAR_SCREEN);
+    out.push_str(&format!(
+        "{}\n\n",
+        format!(
+            "[>] Blocked DNS queries, last {} minutes  {}  (q quits)",
+            WINDOW_MINUTES,
+            now.format("%H:%M:%S")
+        )
+        .bold()
+        .cyan()
+    ));
+    if rows.is_empty() {
+        out.push_str("    No blocked queries yet\n");
+    } else {
+        out.push_str(&format!(
+            "{}\n",
+            format!(
+                "    {:>5}  {:>6}  {:<width$}  domain",
+                "/min",
+                "total",
+                "per minute",
+                width = WINDOW_MINUTES as usize
+            )
+            .bold()
+        ));
+    }
+    for (domain, minutes) in rows.iter().take(MAX_ROWS) {
+        out.push_str(&format!(
+            "    {:>5}  {:>6}  {}  {}\n",
+            minutes[WINDOW_MINUTES as usize - 1],
+            minutes.iter().sum::<usize>(),
+            sparkline(minutes),
+            domain
+        ));
+    }
+    if rows.len() > MAX_ROWS {
+        out.push_str(&format!("    ... and {} more\n", rows.len() - MAX_ROWS));
+    }
+    print!("{}", out);
+    let _ = io::stdout().flush();
+}
+
+fn sparkline(counts: &[usize]) -> String {
+    let peak = counts.iter().copied().max().unwrap_or(0).max(1);
+    counts
+        .iter()
+        .map(|&count| match count {
+            0 => ' ',
+            _ => SPARK_LEVELS[count * (SPARK_LEVELS.len() - 1) / peak],
+        })
+        .collect()
+}
@@ -0,0 +1,88 @@
+use crate::{notify, util::Config};
+use chrono::{DateTime, Local};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{fs, time::Duration};
+
+const RELEASES_URL: &str = "https://api.github.com/repos/gabriel-sparkes/focus/releases/latest";
+const CACHE_FILE: &str = "focus.update";
+const CHECK_INTERVAL_HOURS: i64 = 24;
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct UpdateCache {
+    checked: DateTime<Local>,
+    latest: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+}
+
+fn cache_path(config: &Config) -> String {
+    format!("{}/{}", config.log_directory, CACHE_FILE)
+}
+
+/// Prints a one-line notice when a newer release exists, checking at most once a day.
+pub fn notify_if_outdated(config: &Config) {
+    if config.offline {
+        return;
+    }
+
+    let cached: Option<UpdateCache> = fs::read_to_string(cache_path(config))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+    let latest = match cached {
+        Some(cache) if (Local::now() - cache.checked).num_hours() < CHECK_INTERVAL_HOURS => {
+            cache.latest
+        }
+        _ => {
+            let latest = fetch_latest();
+            let cache = UpdateCache {
+                checked: Local::now(),
+                latest: latest.clone(),
+            };
+            if let Ok(content) = serde_json::to_string(&cache) {
+                let _ = fs::write(cache_path(config), content);
+            }
+            latest
+        }
+    };
+
+    if let Some(latest) = latest
+        && is_newer(&latest, env!("CARGO_PKG_VERSION"))
+    {
+        println!(
+            "{}",
+            format!(
+                "[>] focus {} is available (you have {})",
+                latest,
+                env!("CARGO_PKG_VERSION")
+            )
+            .cyan()
+        );
+    }
+}
+
+fn fetch_latest() -> Option<String> {
+    let release: Release = notify::http_agent_with_timeout(CHECK_TIMEOUT)
+        .get(RELEASES_URL)
+        .header("Accept", "application/vnd.github+json")
+        .call()
+        .ok()?
+        .body_mut()
+        .read_json()
+        .ok()?;
+    Some(release.tag_name.trim_start_matches('v').to_string())
+}
+
+fn is_newer(candidate: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Vec<u64> {
+        version
+            .split(['.', '-', '+'])
+            .map_while(|part| part.parse().ok())
+            .collect()
+    };
+    parse(candidate) > parse(current)
+}
@@ -0,0 +1,81 @@
+#[cfg(unix)]
+use crate::platform;
+use std::process::Command;
+#[cfg(unix)]
+use std::{env, os::unix::process::CommandExt, path::Path, process::Stdio};
+
+/// The desktop user behind `sudo`, whose session owns the sound server and the session bus.
+pub struct SessionUser {
+    #[cfg(unix)]
+    uid: u32,
+    #[cfg(unix)]
+    gid: u32,
+    runtime_dir: String,
+}
+
+impl SessionUser {
+    /// None unless running as root on behalf of another user.
+    #[cfg(unix)]
+    pub fn from_sudo() -> Option<Self> {
+        if !platform::is_root() {
+            return None;
+        }
+        let uid: u32 = env::var("SUDO_UID").ok()?.parse().ok()?;
+        if uid == 0 {
+            return None;
+        }
+        let gid = env::var("SUDO_GID")
+            .ok()
+            .and_then(|gid| gid.parse().ok())
+            .unwrap_or(uid);
+        let runtime_dir = logind_runtime_dir(uid).or_else(|| {
+            let fallback = format!("/run/user/{}", uid);
+            Path::new(&fallback).is_dir().then_some(fallback)
+        })?;
+        Some(SessionUser {
+            uid,
+            gid,
+            runtime_dir,
+        })
+    }
+
+    /// Windows has no sudo; an elevated focus already runs as the desktop user.
+    #[cfg(target_os = "windows")]
+    pub fn from_sudo() -> Option<Self> {
+        None
+    }
+
+    /// Runs `program` as this user with their runtime directory and session bus.
+    pub fn command(&self, program: impl AsRef<std::ffi::OsStr>) -> Command {
+        let mut command = Command::new(program);
+        #[cfg(unix)]
+        command.uid(self.uid).gid(self.gid);
+        command
+            .env("XDG_RUNTIME_DIR", &self.runtime_dir)
+            .env(
+                "DBUS_SESSION_BUS_ADDRESS",
+                format!("unix:path={}/bus", self.runtime_dir),
+            )
+            .env_remove("SUDO_UID")
+            .env_remove("SUDO_GID")
+            .env_remove("SUDO_USER");
+        command
+    }
+}
+
+/// Asks logind rather than assuming /run/user/<uid>, which differs on some setups.
+#[cfg(unix)]
+fn logind_runtime_dir(uid: u32) -> Option<String> {
+    let output = Command::new("loginctl")
+        .args([
+            "show-user",
+            &uid.to_string(),
+            "--property=RuntimePath",
+            "--value",
+        ])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !path.is_empty()).then_some(path)
+}
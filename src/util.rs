@@ -1,40 +1,387 @@
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use clap::{Parser, Subcommand};
 use colored::Colorize;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::{
-    fs::{self, OpenOptions},
-    io::{self, Read, Write},
+    collections::{BTreeMap, HashSet},
+    fs, io,
     path::Path,
-    process::{self, Command},
+    process,
     sync::{
-        Arc,
+        Arc, Mutex, MutexGuard,
         atomic::{AtomicBool, Ordering},
     },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
+#[cfg(unix)]
+use std::{
+    io::Write,
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+};
+
+use crate::{
+    admin,
+    audio::{AudioBackend, BackgroundAudio},
+    backend::{self, BlockingBackend},
+    blockpage::{self, BlockPageConfig},
+    bypass,
+    dbus::DbusConfig,
+    desktop::DesktopConfig,
+    dns::DnsConfig,
+    ebpf::EbpfConfig,
+    embed::Backend,
+    events,
+    firewall::FirewallConfig,
+    hosts::{self, BlockInfo, FoundBlock, Markers},
+    limits::{self, Action, Limits},
+    matrix::MatrixConfig,
+    microbreak::MicroBreakConfig,
+    native::Browser,
+    notify::{Event, NotifierConfig},
+    ntfy::NtfyConfig,
+    onboarding::OnboardingConfig,
+    overlay::OverlayConfig,
+    platform,
+    power::LowPower,
+    privileged::{self, CONFIG_PATH, Request, Settings},
+    profile::{Profile, Template},
+    prompt, protect,
+    proxy::ProxyConfig,
+    readonly, resolver,
+    schedule::{self, ScheduleRule},
+    session::{self, OrphanAction, SessionState, Timer},
+    sni::{self, SniConfig},
+    socket::{self, SocketConfig},
+    stats::Retention,
+    subscriptions::{self, Subscription},
+    summary::SummaryConfig,
+    sync::SyncConfig,
+    telegram::TelegramConfig,
+    update,
+    vacation::{DaysOff, VacationConfig},
+};
+
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// How long `focus stop` waits for the session to end itself before killing it
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+/// PID files and sockets of root sessions; cleared at boot, unlike `log_directory`
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const RUNTIME_DIRECTORY: &str = "/run/focus";
+#[cfg(target_os = "macos")]
+const RUNTIME_DIRECTORY: &str = "/var/run/focus";
 
-const CHECK_INTERVAL: u64 = 5;
-const CONFIG_PATH: &str = "/usr/local/etc/focus/config.toml";
-const REGEX: &str = "# BEGIN FOCUS BLOCK([\\s\\S]*?)# END FOCUS BLOCK";
+static HOSTS_LOCK: Mutex<()> = Mutex::new(());
+static BLOCK_INFO: Mutex<Option<BlockInfo>> = Mutex::new(None);
+/// Set while a soft start blocks only `soft_start_sites`; cleared when the full list takes over.
+static SOFT_START: Mutex<Option<Instant>> = Mutex::new(None);
+/// Sites `focus add` sent to the running session, blocked until it ends
+static LIVE_SITES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+/// Set by `focus stop` through the socket, which already made the stop checks itself
+static STOP_APPROVED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Enforcement {
+    #[default]
+    Block,
+    Nag,
+}
+
+/// What counts as tampering during a session, from strictest to most tolerant of
+/// tools that rewrite the hosts file themselves.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TamperPolicy {
+    /// Any change to the focus block; it is written again at the end of the file
+    #[default]
+    Block,
+    /// A blocked site no longer pointing at `block_ip`; the block is rewritten where it is
+    Entries,
+    /// The focus block gone, as when the file is replaced; a new one is appended
+    Replaced,
+}
 
 #[derive(Subcommand, Debug, PartialEq)]
 pub enum Commands {
-    Add { urls: Vec<String> },
-    Remove { urls: Vec<String> },
+    Add {
+        urls: Vec<String>,
+    },
+    Remove {
+        urls: Vec<String>,
+    },
     Start,
     Status,
     Stop,
+    /// Prints the sites the config blocks, for the group with `--group`
+    List,
+    /// Moves a session running in a terminal to the background, keeping its timer
+    Background,
+    /// Follows a background session's countdown and events; Ctrl-C detaches again
+    Attach,
+    /// Prints the session's events as NDJSON for scripts, until it ends
+    Events {
+        /// Keep waiting for the next session until interrupted
+        #[arg(long)]
+        follow: bool,
+    },
+    /// Adds minutes to the running session
+    Extend {
+        minutes: u64,
+    },
+    /// Live view of blocked DNS queries per domain and minute
+    Top,
+    Audit,
+    Stats {
+        #[command(subcommand)]
+        action: Option<StatsAction>,
+        #[arg(long)]
+        today: bool,
+        #[arg(long)]
+        heatmap: bool,
+        #[arg(long)]
+        queries: bool,
+        #[arg(long, default_value_t = 12)]
+        weeks: u32,
+    },
+    ExportState {
+        file: String,
+    },
+    ImportState {
+        file: String,
+    },
+    Sync,
+    Refresh,
+    AdminPassword,
+    Import {
+        file: String,
+        #[arg(long)]
+        column: Option<String>,
+    },
+    /// Lists the blocklist packs installed in the data directory
+    Lists,
+    Log {
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+    },
+    NativeHost {
+        #[arg(long, requires = "extension")]
+        manifest: Option<Browser>,
+        #[arg(long)]
+        extension: Option<String>,
+        /// Arguments browsers pass when launching the host
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, hide = true)]
+        caller: Vec<String>,
+    },
+    /// Prints the time left, the session's tag and today's goal progress
+    Why,
+    /// Prints the time left in the running session; exits non-zero when there is none
+    Remaining {
+        /// Minutes and seconds, e.g. `27:14`
+        #[arg(long, conflicts_with = "seconds")]
+        clock: bool,
+        /// Whole seconds, e.g. `1634`
+        #[arg(long)]
+        seconds: bool,
+    },
+    /// Sends today's summary now, e.g. from cron when no session is running
+    Summary {
+        /// The last seven days instead of today
+        #[arg(long)]
+        week: bool,
+    },
+    /// Opens the config in $EDITOR and saves it only if it is valid
+    Edit,
+    /// Plays sounds and shows notifications for the root daemon; run it as a systemd user service
+    Companion {
+        /// Prints a systemd user unit for the companion
+        #[arg(long)]
+        unit: bool,
+    },
+    /// Plays a sound as the desktop user on behalf of a session started with sudo
+    #[command(hide = true)]
+    PlaySound {
+        path: String,
+        #[arg(long)]
+        repeat: bool,
+    },
+    /// Dims the screen for a micro-break as the desktop user
+    #[command(hide = true)]
+    BreakScreen {
+        seconds: u64,
+    },
+    /// Lists, adds or removes recurring blocks such as `Mon-Fri 09:00-12:00`
+    Schedule {
+        #[command(subcommand)]
+        action: Option<ScheduleAction>,
+    },
+    /// Lists, adds or removes desktop apps that are closed during a session
+    App {
+        #[command(subcommand)]
+        action: Option<AppAction>,
+    },
+    /// Shows what onboarding has seen so far; `--end` starts blocking now
+    Onboarding {
+        #[arg(long)]
+        end: bool,
+    },
+    /// Reports which optional integrations work here, before enabling the options that need them
+    Capabilities,
+    /// Creates a local CA so the block page can answer HTTPS sites too
+    Certificate {
+        /// Deletes the local CA instead
+        #[arg(long)]
+        remove: bool,
+    },
+    /// Watches a strict session's process and resumes the session if it is killed
+    #[command(hide = true)]
+    Watchdog {
+        #[arg(long)]
+        pid: u32,
+    },
+}
+
+#[derive(Subcommand, Debug, PartialEq)]
+pub enum ScheduleAction {
+    List,
+    /// e.g. `focus schedule add Mon-Fri 09:00-12:00` or `... Sat 10:00-11:30 Europe/Lisbon`
+    Add {
+        #[arg(required = true)]
+        rule: Vec<String>,
+    },
+    Remove {
+        #[arg(required = true)]
+        rule: Vec<String>,
+    },
+    /// Starts sessions as scheduled windows open; run it as a system service
+    Run,
+}
+
+#[derive(Subcommand, Debug, PartialEq)]
+pub enum AppAction {
+    List,
+    /// e.g. `focus app add steam discord`; matched against the process or executable name
+    Add {
+        #[arg(required = true)]
+        apps: Vec<String>,
+    },
+    Remove {
+        #[arg(required = true)]
+        apps: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug, PartialEq)]
+pub enum StatsAction {
+    Prune {
+        #[arg(long)]
+        before: NaiveDate,
+    },
+}
+
+impl Commands {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Commands::Add { .. } => "add",
+            Commands::Remove { .. } => "remove",
+            Commands::Start => "start",
+            Commands::Status => "status",
+            Commands::List => "list",
+            Commands::Stop => "stop",
+            Commands::Background => "background",
+            Commands::Attach => "attach",
+            Commands::Events { .. } => "events",
+            Commands::Extend { .. } => "extend",
+            Commands::Top => "top",
+            Commands::Capabilities => "capabilities",
+            Commands::Schedule { .. } => "schedule",
+            Commands::App { .. } => "app",
+            Commands::Onboarding { .. } => "onboarding",
+            Commands::Audit => "audit",
+            Commands::Stats { .. } => "stats",
+            Commands::ExportState { .. } => "export-state",
+            Commands::ImportState { .. } => "import-state",
+            Commands::Sync => "sync",
+            Commands::Refresh => "refresh",
+            Commands::AdminPassword => "admin-password",
+            Commands::Import { .. } => "import",
+            Commands::Lists => "lists",
+            Commands::Log { .. } => "log",
+            Commands::NativeHost { .. } => "native-host",
+            Commands::Remaining { .. } => "remaining",
+            Commands::Why => "why",
+            Commands::Edit => "edit",
+            Commands::Summary { .. } => "summary",
+            Commands::Certificate { .. } => "certificate",
+            Commands::Companion { .. } => "companion",
+            Commands::PlaySound { .. } => "play-sound",
+            Commands::BreakScreen { .. } => "break-screen",
+            Commands::Watchdog { .. } => "watchdog",
+        }
+    }
+
+    pub fn is_audited(&self, json: bool) -> bool {
+        // `status`, `stats --today` and JSON listings are meant to be polled by prompts and
+        // status bars, `attach`, `events` and `top` usually end with Ctrl-C, and the native
+        // host and the companion run as an unprivileged user
+        if json && matches!(self, Commands::List) {
+            return false;
+        }
+        !matches!(
+            self,
+            Commands::Status
+                | Commands::Stats { today: true, .. }
+                | Commands::Remaining { .. }
+                | Commands::Attach
+                | Commands::Events { .. }
+                | Commands::Top
+                | Commands::Why
+                | Commands::NativeHost { .. }
+                | Commands::Companion { .. }
+                | Commands::PlaySound { .. }
+                | Commands::BreakScreen { .. }
+                | Commands::Watchdog { .. }
+        )
+    }
+
+    /// Commands that start or change blocking, which may prompt to remove stale blocks;
+    /// the rest only mention them, as scripts and status bars poll them
+    pub fn cleans_stale(&self) -> bool {
+        matches!(
+            self,
+            Commands::Start
+                | Commands::Stop
+                | Commands::Add { .. }
+                | Commands::Remove { .. }
+                | Commands::Background
+                | Commands::Extend { .. }
+                | Commands::ImportState { .. }
+                | Commands::Refresh
+                | Commands::Schedule {
+                    action: Some(
+                        ScheduleAction::Add { .. }
+                            | ScheduleAction::Remove { .. }
+                            | ScheduleAction::Run
+                    ),
+                }
+                | Commands::App {
+                    action: Some(AppAction::Add { .. } | AppAction::Remove { .. }),
+                }
+        )
+    }
 }
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
-
 pub struct Args {
     #[command(subcommand)]
     pub command: Option<Commands>,
 
+    /// Minutes for this session only, e.g. `focus 25`, or a template such as `focus writing`
+    #[arg(conflicts_with_all = ["duration", "until"])]
+    pub shortcut: Option<String>,
+
     #[arg(short, long)]
     pub duration: Option<u64>,
 
@@ -46,18 +393,207 @@ pub struct Args {
 
     #[arg(long)]
     pub config: Option<String>,
+
+    #[arg(short, long)]
+    pub tag: Option<String>,
+
+    #[arg(short, long)]
+    pub group: Option<String>,
+
+    #[arg(short = 'P', long)]
+    pub profile: Option<String>,
+
+    /// End the session at a wall-clock time, e.g. `17:30` or `17:30 Europe/Paris`
+    #[arg(short, long, conflicts_with = "duration")]
+    pub until: Option<String>,
+
+    /// Minutes left in a session the watchdog is resuming
+    #[arg(long, hide = true)]
+    pub resume: Option<u64>,
+
+    /// PID of a foreground session this process continues in the background
+    #[arg(long, hide = true)]
+    pub takeover: Option<u32>,
+
+    /// What to do with a block left by a session that is no longer running
+    #[arg(long)]
+    pub orphan: Option<OrphanAction>,
+
+    /// Enforce this session with only these backends, e.g. `--backend hosts`; repeatable
+    #[arg(long = "backend", value_enum)]
+    pub backends: Vec<Backend>,
+
+    #[arg(long, global = true)]
+    pub no_ritual: bool,
+
+    /// Prints `status`, `stats` and `list` as JSON, for scripts and status bars
+    #[arg(long, global = true)]
+    pub json: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
+    #[serde(default = "platform::default_hosts_path")]
     pub hosts_path: String,
     pub block_ip: String,
     pub blocked_sites: Vec<String>,
+    #[serde(default)]
+    pub groups: BTreeMap<String, Vec<String>>,
+    /// Site group of the running session, set from `--group` and never saved
+    #[serde(skip)]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub markers: Markers,
+    #[serde(default)]
+    pub enforcement: Enforcement,
+    #[serde(default)]
+    pub tamper_policy: TamperPolicy,
     pub duration: u64,
     pub data_directory: String,
     pub log_directory: String,
     pub start_audio: String,
     pub end_audio: String,
+    /// Looped for the whole session when set
+    #[serde(default)]
+    pub ambient_audio: Option<String>,
+    /// Desktop notifications when a session starts, ends or catches tampering, and the
+    /// `notifiers` channels
+    #[serde(default = "default_true")]
+    pub notifications: bool,
+    /// Strict sessions can't be stopped or paused early
+    #[serde(default)]
+    pub strict: bool,
+    #[serde(default)]
+    pub blocking_backend: BlockingBackend,
+    /// Also pins /etc/resolv.conf and blocks DNS-over-HTTPS endpoints during a session,
+    /// so the block can't be sidestepped by changing DNS
+    #[serde(default)]
+    pub hardened: bool,
+    /// Remove blocks left by crashed sessions without asking
+    #[serde(default)]
+    pub remove_stale_blocks: bool,
+    /// Hold off blocking while apt, dnf or pacman is installing packages
+    #[serde(default)]
+    pub wait_for_updates: bool,
+    /// Fewer wakeups on battery: slower tamper checks, batched milestones, a coarser countdown
+    #[serde(default)]
+    pub low_power: LowPower,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    /// Sessions started by name, e.g. `focus writing`
+    #[serde(default)]
+    pub templates: BTreeMap<String, Template>,
+    /// Profile of the running session, set from `--profile` and never saved
+    #[serde(skip)]
+    pub profile: Option<String>,
+    /// Extra sites from a project's `.focus.toml`, for this run only
+    #[serde(skip)]
+    pub project_sites: Vec<String>,
+    /// Set when the hosts file is on a read-only mount, so only the DNS sinkhole blocks
+    #[serde(skip)]
+    pub hosts_read_only: bool,
+    /// Set while onboarding, when sessions log visits to blocked sites instead of blocking
+    #[serde(skip)]
+    pub warn_only: bool,
+    /// Sites from the profile's blocklist packs, for this run only
+    #[serde(skip)]
+    pub pack_sites: Vec<String>,
+    #[serde(default)]
+    pub warmup_minutes: u64,
+    /// Blocks only `soft_start_sites` for this many minutes before the full list
+    #[serde(default)]
+    pub soft_start_minutes: u64,
+    #[serde(default)]
+    pub soft_start_sites: Vec<String>,
+    /// IANA zone for schedules and `--until`; the system zone when unset
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub schedule: Vec<ScheduleRule>,
+    /// Process names closed while a session runs, e.g. `steam` or `discord`
+    #[serde(default)]
+    pub blocked_apps: Vec<String>,
+    #[serde(default)]
+    pub vacations: VacationConfig,
+    /// Disables automatic network checks such as update checks and list refreshes
+    #[serde(default)]
+    pub offline: bool,
+    #[serde(default)]
+    pub checklist: Vec<String>,
+    #[serde(default = "default_grace_minutes")]
+    pub grace_minutes: u64,
+    #[serde(default)]
+    pub grace_audio: Option<String>,
+    #[serde(default)]
+    pub grace_speech: bool,
+    #[serde(default)]
+    pub audio_backend: AudioBackend,
+    #[serde(default)]
+    pub background_audio: BackgroundAudio,
+    #[serde(default = "default_true")]
+    pub extend_prompt: bool,
+    #[serde(default = "default_extend_minutes")]
+    pub extend_prompt_minutes: u64,
+    #[serde(default)]
+    pub journal_prompt: bool,
+    #[serde(default)]
+    pub daily_goal_minutes: Option<u64>,
+    #[serde(default)]
+    pub limits: Limits,
+    #[serde(default)]
+    pub onboarding: OnboardingConfig,
+    #[serde(default)]
+    pub retention: Retention,
+    #[serde(default)]
+    pub sync: Option<SyncConfig>,
+    #[serde(default)]
+    pub subscriptions: Vec<Subscription>,
+    #[serde(default)]
+    pub admin_password_hash: Option<String>,
+    #[serde(default)]
+    pub dns: DnsConfig,
+    #[serde(default)]
+    pub sni: SniConfig,
+    #[serde(default)]
+    pub ebpf: EbpfConfig,
+    #[serde(default)]
+    pub firewall: FirewallConfig,
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+    #[serde(default)]
+    pub block_page: BlockPageConfig,
+    #[serde(default)]
+    pub overlay: OverlayConfig,
+    #[serde(default)]
+    pub summary: SummaryConfig,
+    #[serde(default)]
+    pub micro_breaks: MicroBreakConfig,
+    #[serde(default)]
+    pub socket: SocketConfig,
+    #[serde(default)]
+    pub dbus: DbusConfig,
+    #[serde(default)]
+    pub desktop: DesktopConfig,
+    #[serde(default)]
+    pub telegram: Option<TelegramConfig>,
+    #[serde(default)]
+    pub notifiers: Vec<NotifierConfig>,
+    #[serde(default)]
+    pub ntfy: Option<NtfyConfig>,
+    #[serde(default)]
+    pub matrix: Option<MatrixConfig>,
+}
+
+fn default_grace_minutes() -> u64 {
+    2
+}
+
+fn default_extend_minutes() -> u64 {
+    10
+}
+
+pub(crate) fn default_true() -> bool {
+    true
 }
 
 pub fn ctrlc_handler(
@@ -66,141 +602,459 @@ pub fn ctrlc_handler(
     is_background: bool,
     pid_path: &String,
 ) {
+    let approved = STOP_APPROVED.swap(false, Ordering::SeqCst);
+    if approved {
+        // `focus stop` already asked for the admin password and spent the stop
+    } else if config.strict && bypass::redeem(config) {
+        // An emergency skips the admin password and the stop limits
+    } else if config.strict {
+        println!(
+            "{}",
+            "\n[!] This is a strict session and can't be stopped early"
+                .bold()
+                .red()
+        );
+        super::audit::record(config, "session", "early stop refused (strict)");
+        return;
+    } else if schedule::active_until(config).is_some()
+        && !admin::require_admin(config, "Stopping the session")
+    {
+        super::audit::record(config, "session", "early stop denied");
+        return;
+    } else if !limits::try_consume(config, Action::Stop) {
+        super::audit::record(config, "session", "early stop refused");
+        return;
+    }
+
     running.store(false, Ordering::SeqCst);
 
+    super::prompt::restore_terminal();
     println!("{}", "\n[>] Cleaning up...".bold().cyan());
-    let old_content =
-        fs::read_to_string(&config.hosts_path).expect("[!] Failed to read host file content");
-    let new_content = Regex::new(REGEX)
-        .unwrap()
-        .replace_all(&old_content, "")
-        .to_string();
-    let _ = fs::write(&config.hosts_path, &new_content);
+    let _ = unblock_sites(config);
+    sni::remove_rules(config);
+    events::publish(
+        config,
+        Event::SessionStopped {
+            outcome: if bypass::was_used(config) {
+                "bypassed"
+            } else if approved {
+                "stopped"
+            } else {
+                "interrupted"
+            },
+        },
+    );
     println!("{}", "[>] Exiting".bold().cyan());
 
-    if !is_background {
-        super::audio::play_audio(format!("{}/{}", config.data_directory, config.end_audio));
-    }
+    super::audio::play_cue(config, &config.end_audio, "Session stopped", is_background);
     let _ = fs::remove_file(pid_path);
+    events::flush();
     process::exit(0);
 }
 
 pub fn load_config() -> Result<Config, toml::de::Error> {
-    let content =
-        fs::read_to_string(CONFIG_PATH).expect(&format!("[!] Could not read {}", CONFIG_PATH));
+    let content = fs::read_to_string(CONFIG_PATH)
+        .unwrap_or_else(|_| panic!("[!] Could not read {}", CONFIG_PATH));
 
-    let config = toml::from_str(&content);
-    config
+    toml::from_str(&content)
+}
+
+pub fn config_directory() -> String {
+    Path::new(CONFIG_PATH)
+        .parent()
+        .map(|parent| parent.to_string_lossy().to_string())
+        .unwrap_or_else(|| String::from("."))
 }
 
 pub fn save_config(config: &Config) -> Result<(), io::Error> {
-    let toml_string =
-        toml::to_string(config).expect(&format!("{}", "[!] Could not encode config to TOML"));
-    fs::write(CONFIG_PATH, toml_string)
-}
-
-pub fn start_checker_thead(config: Arc<Config>, running: Arc<AtomicBool>) {
-    thread::spawn(move || {
-        while running.load(Ordering::SeqCst) {
-            let blocked_content = build_blocked_content(&config);
-            if let Ok(current_content) = fs::read_to_string(&config.hosts_path) {
-                if !current_content.contains(&blocked_content) {
-                    let mut hosts_file = OpenOptions::new()
-                        .append(true)
-                        .open(&config.hosts_path)
-                        .expect(&format!(
-                            "Failed to open {}. Are you running as sudo?",
-                            &config.hosts_path
-                        ));
-                    println!(
-                        "{}",
-                        "[!] Tamper detected! Reblocking sites...".bold().red()
-                    );
-
-                    hosts_file
-                        .write(blocked_content.as_bytes())
-                        .expect(&format!("{}", "[!] Write to file failed"));
-                }
-            }
+    let toml_string = toml::to_string(config).expect("[!] Could not encode config to TOML");
+    announce_config(config, &toml_string);
+    fs::write(CONFIG_PATH, toml_string).inspect_err(readonly::exit_if_config_read_only)
+}
 
-            thread::sleep(Duration::from_secs(CHECK_INTERVAL));
-        }
-    });
+/// Like `save_config`, readable by its owner only, for a config arriving with its secrets.
+pub fn save_config_private(config: &Config) -> Result<(), io::Error> {
+    let toml_string = toml::to_string(config).expect("[!] Could not encode config to TOML");
+    announce_config(config, &toml_string);
+    write_private(CONFIG_PATH, &toml_string).inspect_err(readonly::exit_if_config_read_only)
 }
 
-pub fn check_status() {
-    let regex = Regex::new(REGEX).unwrap();
+/// Tells every running session the config focus is about to write, so a strict session's
+/// guard keeps it rather than restoring the old one.
+pub fn announce_config(config: &Config, content: &str) {
+    let digest = protect::digest(content);
+    for (scope, _) in session::list_sessions(config) {
+        let config = Config {
+            scope,
+            ..config.clone()
+        };
+        let _ = socket::ask(
+            &config,
+            serde_json::json!({ "op": "expect-config", "digest": digest }),
+        );
+    }
+}
 
-    let config = load_config().unwrap_or_else(|_| {
-        eprintln!(
+/// Rewrites the block if anything changed it since it was written.
+pub fn check_tamper(config: &Config) {
+    if config.hosts_read_only || config.warn_only || !backend::uses_hosts(config) {
+        return;
+    }
+    let _hosts = lock_hosts();
+    let Ok(current_content) = fs::read_to_string(&config.hosts_path) else {
+        return;
+    };
+    let tampered = match config.tamper_policy {
+        TamperPolicy::Block => {
+            !hosts::has_intact_block(&current_content, &build_blocked_content(config))
+        }
+        TamperPolicy::Entries => {
+            !hosts::missing_sites(&current_content, &config.block_ip, &blocked_sites(config))
+                .is_empty()
+        }
+        TamperPolicy::Replaced => {
+            !hosts::has_block(&current_content, &config.markers, config.scope.as_deref())
+        }
+    };
+    if tampered {
+        println!(
             "{}",
-            "[!] Could not load config to check status".bold().red()
+            "[!] Tamper detected! Reblocking sites...".bold().red()
         );
-        process::exit(1);
-    });
-    let pid_path = format!("{}/focus.pid", config.log_directory);
-    if Path::new(&pid_path).exists() {
-        println!("{}", "[+] Focus is running".bold().green());
-    } else {
-        println!("{}", "[+] Focus is not running".bold().green());
+
+        let in_place = config.tamper_policy == TamperPolicy::Entries;
+        if let Err(e) = write_block_at(config, in_place) {
+            eprintln!(
+                "{}",
+                format!("[!] Failed to reblock sites: {}", e).bold().red()
+            );
+        }
+        events::publish(config, Event::TamperDetected);
     }
+}
 
-    let content = fs::read_to_string(&config.hosts_path).expect("[!] Failed to read host file");
-    if regex.is_match(&content) {
-        println!("{}", "[+] Sites are blocked".bold().green());
+pub fn check_status(config: &Config) {
+    let sessions = session::list_sessions(config);
+    if let Err(e) = fs::read_dir(&config.log_directory) {
+        println!(
+            "{}",
+            format!(
+                "[!] Could not read {}: {}; running sessions are unknown",
+                config.log_directory, e
+            )
+            .bold()
+            .yellow()
+        );
+    } else if sessions.is_empty() {
+        println!("{}", "[+] Focus is not running".bold().green());
     } else {
+        println!("{}", "[+] Focus is running".bold().green());
+    }
+    for (scope, state) in &sessions {
+        let (remaining_seconds, paused) = live_remaining(config, scope, state);
+        let deadline = Local::now() + chrono::Duration::seconds(remaining_seconds);
+        println!(
+            "{}",
+            format!(
+                "[+] {}: until {} ({} minutes left){}",
+                scope.as_deref().unwrap_or("default"),
+                deadline.format("%H:%M"),
+                (remaining_seconds as u64).div_ceil(60),
+                if paused { ", paused" } else { "" }
+            )
+            .bold()
+            .green()
+        );
+    }
+
+    show_schedule(config);
+
+    let content = match fs::read_to_string(&config.hosts_path) {
+        Ok(content) => content,
+        Err(e) => {
+            let hint = if e.kind() == io::ErrorKind::PermissionDenied {
+                "run with sudo to check whether sites are blocked"
+            } else {
+                "check hosts_path in the config"
+            };
+            println!(
+                "{}",
+                format!("[!] Could not read {}: {}; {}", config.hosts_path, e, hint)
+                    .bold()
+                    .yellow()
+            );
+            update::notify_if_outdated(config);
+            return;
+        }
+    };
+    let blocks = hosts::find_blocks(&content, &config.markers);
+    if blocks.is_empty() {
         println!("{}", "[+] Sites are not blocked".bold().green());
+    } else {
+        println!("{}", "[+] Sites are blocked".bold().green());
     }
+    // Blocks no running session owns, e.g. after a crash or reboot
+    for block in blocks
+        .iter()
+        .filter(|block| !sessions.iter().any(|(scope, _)| *scope == block.scope))
+    {
+        if let Some(description) = describe_block(block) {
+            println!("{}", description.bold().yellow());
+        }
+    }
+    update::notify_if_outdated(config);
 }
 
-pub fn stop_daemon(config: &Config) {
-    let pid_path = format!("{}/focus.pid", config.log_directory);
+/// Seconds left in a session and whether it is paused.
+fn live_remaining(config: &Config, scope: &Option<String>, state: &SessionState) -> (i64, bool) {
+    let mut scoped = config.clone();
+    scoped.scope = scope.clone();
+    // The session itself knows about pauses and extensions; the state file only the plan
+    let live = socket::ask(&scoped, serde_json::json!({ "op": "status" })).ok();
+    let remaining_seconds = match live.as_ref().and_then(|live| live["remaining"].as_u64()) {
+        Some(seconds) => seconds as i64,
+        None => (session::deadline(&scoped, state) - Local::now())
+            .num_seconds()
+            .max(0),
+    };
+    let paused = live.as_ref().is_some_and(|live| live["paused"] == true);
+    (remaining_seconds, paused)
+}
+
+/// What `check_status` prints, for `focus status --json`. `blocked` lists the groups with a
+/// block in the hosts file, `default` for the ungrouped one, or is null when it can't be read.
+pub fn status_json(config: &Config) -> serde_json::Value {
+    let sessions: Vec<serde_json::Value> = session::list_sessions(config)
+        .iter()
+        .map(|(scope, state)| {
+            let (remaining_seconds, paused) = live_remaining(config, scope, state);
+            let deadline = Local::now() + chrono::Duration::seconds(remaining_seconds);
+            serde_json::json!({
+                "group": scope,
+                "remaining": remaining_seconds,
+                "deadline": deadline.to_rfc3339(),
+                "paused": paused,
+                "tag": state.tag,
+                "profile": state.profile,
+                "strict": state.strict,
+            })
+        })
+        .collect();
+    let blocked = fs::read_to_string(&config.hosts_path).ok().map(|content| {
+        hosts::find_blocks(&content, &config.markers)
+            .into_iter()
+            .map(|block| block.scope.unwrap_or_else(|| String::from("default")))
+            .collect::<Vec<_>>()
+    });
+    let now = chrono::Utc::now();
+    let schedule: Vec<serde_json::Value> = config
+        .schedule
+        .iter()
+        .map(|rule| {
+            let local = |time: DateTime<Utc>| time.with_timezone(&Local).to_rfc3339();
+            serde_json::json!({
+                "rule": rule.to_string(),
+                "active_until": rule.active_until(config, now).map(local),
+                "next_start": rule.next_window(config, now).map(|(start, _)| local(start)),
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "running": !sessions.is_empty(),
+        "sessions": sessions,
+        "blocked": blocked,
+        "schedule": schedule,
+    })
+}
+
+/// Prints the configured sites of the scope, one per line or as JSON, with how many the
+/// session blocks once packs and subscriptions are added.
+pub fn list_sites(config: &Config, json: bool) {
+    let sites = match &config.scope {
+        Some(group) => config.groups.get(group).cloned().unwrap_or_default(),
+        None => config.blocked_sites.clone(),
+    };
+    let total = effective_sites(config).len();
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({
+                "group": config.scope,
+                "sites": sites,
+                "total": total,
+            })
+        );
+        return;
+    }
+    for site in &sites {
+        println!("{}", site);
+    }
+    if total > sites.len() {
+        println!(
+            "{}",
+            format!(
+                "[>] {} sites blocked in all, with packs and subscriptions",
+                total
+            )
+            .bold()
+            .cyan()
+        );
+    }
+}
+
+pub fn show_schedule(config: &Config) {
+    let now = chrono::Utc::now();
+    if !config.schedule.is_empty() && DaysOff::load(config).contains(Local::now().date_naive()) {
+        println!(
+            "{}",
+            "[+] Day off: scheduled blocks are suppressed today"
+                .bold()
+                .green()
+        );
+    }
+    for rule in &config.schedule {
+        if let Some(end) = rule.active_until(config, now) {
+            println!(
+                "{}",
+                format!(
+                    "[+] Scheduled ({}): blocking until {}",
+                    rule,
+                    end.with_timezone(&Local).format("%H:%M")
+                )
+                .bold()
+                .green()
+            );
+        } else if let Some((start, _)) = rule.next_window(config, now) {
+            // Past a vacation the weekday alone would be ambiguous
+            let format = if start - now < chrono::Duration::days(6) {
+                "%a %H:%M"
+            } else {
+                "%a %-d %b %H:%M"
+            };
+            println!(
+                "{}",
+                format!(
+                    "[+] Scheduled ({}): next block {}",
+                    rule,
+                    start.with_timezone(&Local).format(format)
+                )
+                .bold()
+                .green()
+            );
+        }
+    }
+}
+
+/// Explains a block from the metadata written into it.
+pub fn describe_block(block: &FoundBlock) -> Option<String> {
+    let info = block.info.as_ref()?;
+    let scope = block.scope.as_deref().unwrap_or("default");
+    Some(match info.deadline {
+        Some(deadline) if deadline <= Local::now() => format!(
+            "[!] {}: block from session {} was due to end at {}; run focus stop to remove it",
+            scope,
+            info.session,
+            deadline.format("%Y-%m-%d %H:%M")
+        ),
+        Some(deadline) => format!(
+            "[!] {}: blocked until {} by session {}, which is no longer running",
+            scope,
+            deadline.format("%H:%M"),
+            info.session
+        ),
+        None => format!(
+            "[!] {}: blocked since {} until unblocked",
+            scope,
+            info.start.format("%Y-%m-%d %H:%M")
+        ),
+    })
+}
+
+pub fn stop_daemon(config: &Config) -> bool {
+    let mut pid_path = runtime_path(config, "pid");
+    // Sessions started before the PID file moved to the runtime directory
+    if !Path::new(&pid_path).exists() {
+        pid_path = scoped_path(config, "pid");
+    }
 
     if let Ok(pid_str) = fs::read_to_string(&pid_path) {
         if let Ok(pid) = pid_str.trim().parse::<i32>() {
-            println!("{}", format!("[>] Stopping daemon...").bold().cyan());
+            let strict = session::load_state(config).is_some_and(|state| state.strict);
+            if strict && !bypass::redeem(config) {
+                eprintln!(
+                    "{}",
+                    "[!] The running session is strict and can't be stopped early"
+                        .bold()
+                        .red()
+                );
+                return false;
+            }
+            if !strict && !limits::try_consume(config, Action::Stop) {
+                return false;
+            }
 
-            let _ = Command::new("kill").arg(pid.to_string()).status();
+            println!("{}", "[>] Stopping daemon...".bold().cyan());
 
-            println!("{}", "[>] Cleaning up...".bold().cyan());
+            // The session cleans up after itself when it can be asked to
+            if socket::ask(config, serde_json::json!({ "op": "end" })).is_ok() {
+                let deadline = Instant::now() + STOP_TIMEOUT;
+                while session::is_alive(pid as u32) && Instant::now() < deadline {
+                    thread::sleep(Duration::from_millis(100));
+                }
+            }
+            if session::is_alive(pid as u32) {
+                // Recording the stop clears the session's state first, so a strict session's
+                // watchdog doesn't take the killed process for tampering and resume it
+                let outcome = if strict { "bypassed" } else { "stopped" };
+                events::publish(config, Event::SessionStopped { outcome });
+                events::flush();
+                platform::terminate(pid as u32, false);
 
-            let old_content = fs::read_to_string(&config.hosts_path)
-                .expect("[!] Failed to read host file content");
-            let new_content = Regex::new(REGEX)
-                .unwrap()
-                .replace_all(&old_content, "")
-                .to_string();
-            let _ = fs::write(&config.hosts_path, &new_content);
+                println!("{}", "[>] Cleaning up...".bold().cyan());
+                let _ = unblock_sites(config);
 
-            thread::sleep(Duration::from_millis(500));
-            let _ = fs::remove_file(pid_path);
+                thread::sleep(Duration::from_millis(500));
+                sni::remove_rules(config);
+                let _ = fs::remove_file(&pid_path);
+            }
         }
     } else {
         eprintln!(
             "{}",
-            "[!] No active focus session found to stop"
-                .bold()
-                .red()
+            "[!] No active focus session found to stop".bold().red()
         );
     }
 
-    let hosts_content =
-        fs::read_to_string(&config.hosts_path).expect("[!] Failed to read host file");
-    let regex = Regex::new(REGEX).unwrap();
+    let hosts_content = match fs::read_to_string(&config.hosts_path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                format!("[!] Failed to read {}: {}", config.hosts_path, e)
+                    .bold()
+                    .red()
+            );
+            return false;
+        }
+    };
+    if let Some(description) = hosts::find_blocks(&hosts_content, &config.markers)
+        .iter()
+        .find(|block| block.scope == config.scope)
+        .and_then(describe_block)
+    {
+        println!("{}", description.bold().yellow());
+    }
 
-    if regex.is_match(&hosts_content) {
+    if hosts::has_block(&hosts_content, &config.markers, config.scope.as_deref()) {
         println!("{}", "[>] Sites are blocked. Unblocking...".bold().cyan());
-        let new_content = Regex::new(REGEX)
-            .unwrap()
-            .replace_all(&hosts_content, "")
-            .to_string();
-        let _ = fs::write(&config.hosts_path, &new_content);
+        let _ = unblock_sites(config);
     } else {
         println!("{}", "[+] Sites are not blocked".bold().green());
     }
+    true
 }
 
-pub fn add_urls(urls: &Vec<String>, config: Config) {
+pub fn add_urls(urls: &[String], config: Config) {
     if urls.is_empty() {
         println!(
             "{}",
@@ -210,12 +1064,43 @@ pub fn add_urls(urls: &Vec<String>, config: Config) {
     }
 
     let mut config = config.clone();
-    let mut urls = urls.clone();
-    config.blocked_sites.append(&mut urls);
+    site_list(&mut config).extend(urls.iter().cloned());
     save_config(&config).expect("[!] Failed to save configuration");
+
+    // Nothing to tell when no session is running
+    if socket::ask(&config, serde_json::json!({ "op": "add", "sites": urls })).is_ok() {
+        println!(
+            "{}",
+            "[+] Blocked in the running session too".bold().green()
+        );
+    }
+}
+
+/// Blocks more sites until the session ends; returns how many weren't blocked already.
+pub fn add_live_sites(config: &Config, sites: &[String]) -> usize {
+    let _hosts = lock_hosts();
+    let blocked = effective_sites(config);
+    let added = {
+        let mut live = LIVE_SITES.lock().unwrap();
+        let before = live.len();
+        for site in sites {
+            if !blocked.contains(site) && !live.contains(site) {
+                live.push(site.clone());
+            }
+        }
+        live.len() - before
+    };
+    if added > 0 {
+        rewrite_block(config);
+    }
+    added
 }
 
-pub fn remove_urls(urls: &Vec<String>, config: Config) {
+pub fn approve_stop() {
+    STOP_APPROVED.store(true, Ordering::SeqCst);
+}
+
+pub fn remove_urls(urls: &[String], config: Config) {
     if urls.is_empty() {
         println!(
             "{}",
@@ -225,67 +1110,429 @@ pub fn remove_urls(urls: &Vec<String>, config: Config) {
     }
 
     let mut config = config.clone();
-    let urls = urls.clone();
-    config.blocked_sites.retain(|url| !urls.contains(url));
+    let sites = site_list(&mut config);
+    let mut removed: Vec<String> = Vec::new();
+    for pattern in urls {
+        let matched = match_sites(sites, pattern);
+        if matched.is_empty() {
+            println!(
+                "{}",
+                format!("[!] No blocked site matches {}", pattern)
+                    .bold()
+                    .yellow()
+            );
+            continue;
+        }
+        println!(
+            "{}",
+            format!("[>] {} matches: {}", pattern, matched.join(", "))
+                .bold()
+                .cyan()
+        );
+        // Bare substrings are easy to get wrong, so those need a confirmation
+        let is_substring = !sites.contains(pattern) && !pattern.contains(['*', '?']);
+        if is_substring
+            && !prompt::confirm(
+                &format!("{}", "[?] Remove these sites? [y/N] ".bold().yellow()),
+                None,
+            )
+        {
+            continue;
+        }
+        removed.extend(matched);
+    }
+
+    if removed.is_empty() {
+        return;
+    }
+    sites.retain(|site| !removed.contains(site));
     save_config(&config).expect("[!] Failed to save configuration");
+    println!(
+        "{}",
+        format!("[+] Removed {} sites", removed.len())
+            .bold()
+            .green()
+    );
+}
+
+/// Matches exact entries first, then `*`/`?` globs, then plain substrings.
+fn match_sites(sites: &[String], pattern: &str) -> Vec<String> {
+    if sites.iter().any(|site| site == pattern) {
+        return vec![pattern.to_string()];
+    }
+    if pattern.contains(['*', '?']) {
+        let expression = regex::escape(pattern)
+            .replace(r"\*", ".*")
+            .replace(r"\?", ".");
+        let glob = Regex::new(&format!("^{}$", expression)).unwrap();
+        return sites
+            .iter()
+            .filter(|site| glob.is_match(site))
+            .cloned()
+            .collect();
+    }
+    sites
+        .iter()
+        .filter(|site| site.contains(pattern))
+        .cloned()
+        .collect()
 }
 
 pub fn block_sites(config: &Config, forever: bool) {
-    let blocked_content = build_blocked_content(config);
-    let mut hosts_file = OpenOptions::new()
-        .read(true)
-        .append(true)
-        .open(&config.hosts_path)
-        .expect(&format!(
-            "[!] Failed to open {}. Are you running as sudo?",
+    begin_block(config, forever);
+    let current_content = fs::read_to_string(&config.hosts_path).unwrap_or_else(|_| {
+        panic!(
+            "[!] Failed to read {}. Are you running as sudo?",
             &config.hosts_path
-        ));
-    let mut current_content = String::new();
-    hosts_file
-        .read_to_string(&mut current_content)
-        .expect("[!] Failed to read host file content");
-
-    let regex = Regex::new(REGEX).unwrap();
-    if regex.is_match(&current_content) {
-        println!(
-            "{}",
-            format!("[!] Blocking is already active").bold().yellow()
-        );
+        )
+    });
+
+    if hosts::has_block(&current_content, &config.markers, config.scope.as_deref()) {
+        println!("{}", "[!] Blocking is already active".bold().yellow());
         return;
     }
 
     if forever {
         println!(
             "{}",
-            format!("[>] Blocking sites until you unblock them")
-                .bold()
-                .cyan()
+            "[>] Blocking sites until you unblock them".bold().cyan()
         );
     } else {
         println!(
             "{}",
-            format!("[>] Blocking sites for {} minutes", config.duration)
+            format!(
+                "[>] {} sites for {} minutes",
+                if config.warn_only {
+                    "Watching"
+                } else {
+                    "Blocking"
+                },
+                config.duration
+            )
+            .bold()
+            .cyan()
+        );
+        begin_soft_start(config);
+    }
+    for site in effective_sites(config)
+        .iter()
+        .filter(|site| !hosts::is_valid_hostname(site))
+    {
+        println!(
+            "{}",
+            format!("[!] Skipping {:?}: not a hostname", site)
                 .bold()
-                .cyan()
+                .yellow()
         );
     }
 
-    if let Err(e) = hosts_file.write(blocked_content.as_bytes()) {
+    if let Err(e) = write_block(config) {
         eprintln!(
             "{}",
             format!("[!] Failed to write to hosts file: {}", e)
                 .bold()
                 .red()
         );
+        // Make sure no partial block outlives the failed start
+        let _ = unblock_sites(config);
         process::exit(1);
     }
 }
 
-fn build_blocked_content(config: &Config) -> String {
-    let mut content = String::from("\n# BEGIN FOCUS BLOCK\n");
-    for site in &config.blocked_sites {
-        content.push_str(&format!("{}\t{}\n", &config.block_ip, site));
+/// Records the session ID and deadline written into the block from now on.
+pub fn begin_block(config: &Config, forever: bool) {
+    let start = Local::now();
+    *BLOCK_INFO.lock().unwrap() = Some(BlockInfo {
+        session: session::new_id(),
+        start,
+        deadline: (!forever).then(|| start + chrono::Duration::minutes(config.duration as i64)),
+    });
+}
+
+/// Removes the focus block from the hosts file, leaving the rest untouched.
+pub fn unblock_sites(config: &Config) -> io::Result<()> {
+    if config.warn_only {
+        return Ok(());
     }
-    content.push_str("# END FOCUS BLOCK");
-    content
+    resolver::unlock();
+    // Every blocker is lifted even when one fails, then the first failure is reported
+    let results: Vec<Result<(), String>> = backend::blockers(config)
+        .iter()
+        .map(|blocker| blocker.unblock(config))
+        .collect();
+    results
+        .into_iter()
+        .collect::<Result<(), String>>()
+        .map_err(io::Error::other)
+}
+
+/// Continues the block a running session wrote, keeping its id and deadline; returns the
+/// time left, or None if the block is gone.
+pub fn adopt_block(config: &Config) -> Option<Duration> {
+    let content = fs::read_to_string(&config.hosts_path).ok()?;
+    let info = hosts::find_blocks(&content, &config.markers)
+        .into_iter()
+        .find(|block| block.scope == config.scope)?
+        .info?;
+    let remaining = (info.deadline? - Local::now()).to_std().unwrap_or_default();
+    *BLOCK_INFO.lock().unwrap() = Some(info);
+    Some(remaining)
+}
+
+/// Writes the focus block again after a pause, without the start-of-session messages.
+pub fn reblock_sites(config: &Config) {
+    if let Err(e) = write_block(config) {
+        eprintln!(
+            "{}",
+            format!("[!] Failed to write to hosts file: {}", e)
+                .bold()
+                .red()
+        );
+    }
+}
+
+/// Runs a hosts or firewall change directly as root, otherwise through `focus-helper`.
+pub fn privileged(config: &Config, request: Request) -> Result<(), String> {
+    if config.hosts_read_only && matches!(request, Request::Block { .. } | Request::Unblock { .. })
+    {
+        return Ok(());
+    }
+    if !platform::is_root() {
+        return privileged::call_helper(&request);
+    }
+    let settings = Settings {
+        hosts_path: config.hosts_path.clone(),
+        block_ip: config.block_ip.clone(),
+        markers: config.markers.clone(),
+    };
+    privileged::execute(&request, &settings)
+}
+
+pub fn write_block(config: &Config) -> Result<(), String> {
+    write_block_at(config, false)
+}
+
+fn write_block_at(config: &Config, in_place: bool) -> Result<(), String> {
+    if config.warn_only {
+        return Ok(());
+    }
+    for blocker in backend::blockers(config) {
+        blocker.block(config, in_place)?;
+    }
+    resolver::lock(config);
+    Ok(())
+}
+
+/// Writes the focus block into the hosts file, see `backend::Hosts`.
+pub fn write_hosts_block(config: &Config, in_place: bool) -> Result<(), String> {
+    privileged(
+        config,
+        Request::Block {
+            scope: config.scope.clone(),
+            info: BLOCK_INFO.lock().unwrap().clone(),
+            sites: blocked_sites(config),
+            in_place,
+        },
+    )
+}
+
+pub fn remove_hosts_block(config: &Config) -> Result<(), String> {
+    privileged(
+        config,
+        Request::Unblock {
+            scope: config.scope.clone(),
+        },
+    )
+}
+
+/// Per-session files get the group name so concurrent sessions don't share them.
+pub fn scoped_path(config: &Config, extension: &str) -> String {
+    scoped_file(&config.log_directory, config, extension)
+}
+
+/// Like `scoped_path`, for the PID file and socket of a running session.
+pub fn runtime_path(config: &Config, extension: &str) -> String {
+    scoped_file(&runtime_directory(), config, extension)
+}
+
+fn scoped_file(directory: &str, config: &Config, extension: &str) -> String {
+    match &config.scope {
+        Some(group) => format!("{}/focus-{}.{}", directory, group, extension),
+        None => format!("{}/focus.{}", directory, extension),
+    }
+}
+
+/// `/run/focus` for root; sessions run through focus-helper use the user's runtime dir.
+#[cfg(not(target_os = "windows"))]
+pub fn runtime_directory() -> String {
+    if platform::is_root() {
+        return String::from(RUNTIME_DIRECTORY);
+    }
+    format!("{}/focus", platform::user_runtime_directory())
+}
+
+/// Windows has no shared runtime directory, so elevated sessions use the user's too.
+#[cfg(target_os = "windows")]
+pub fn runtime_directory() -> String {
+    format!("{}/focus", platform::user_runtime_directory())
+}
+
+/// Creates the runtime directory, open to everyone so widgets can reach the socket.
+pub fn create_runtime_directory() -> io::Result<()> {
+    let directory = runtime_directory();
+    fs::create_dir_all(&directory)?;
+    #[cfg(unix)]
+    fs::set_permissions(&directory, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+/// Writes a file only its owner can read, restricting an existing one before the new content
+/// lands in it.
+#[cfg(unix)]
+pub fn write_private(path: &str, content: &str) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(content.as_bytes())
+}
+
+/// On Windows the file inherits the ACL of its directory, which is the user's own.
+#[cfg(target_os = "windows")]
+pub fn write_private(path: &str, content: &str) -> io::Result<()> {
+    fs::write(path, content)
+}
+
+fn site_list(config: &mut Config) -> &mut Vec<String> {
+    match config.scope.clone() {
+        Some(group) => config.groups.entry(group).or_default(),
+        None => &mut config.blocked_sites,
+    }
+}
+
+/// Serializes hosts-file rewrites made while a session is running.
+pub fn lock_hosts() -> MutexGuard<'static, ()> {
+    HOSTS_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Moves the deadline written in the block to where the timer now ends.
+pub fn sync_deadline(timer: &Timer) {
+    if let Some(info) = BLOCK_INFO.lock().unwrap().as_mut() {
+        info.deadline = chrono::Duration::from_std(timer.remaining())
+            .ok()
+            .map(|remaining| Local::now() + remaining);
+    }
+}
+
+/// Extends the running session and rewrites the block with the new deadline.
+pub fn extend_session(config: &Config, timer: &Timer, by: Duration) {
+    let _hosts = lock_hosts();
+    timer.extend(by);
+    sync_deadline(timer);
+    rewrite_block(config);
+}
+
+fn begin_soft_start(config: &Config) {
+    if config.soft_start_minutes == 0
+        || config.soft_start_minutes >= config.duration
+        || config.soft_start_sites.is_empty()
+    {
+        return;
+    }
+    *SOFT_START.lock().unwrap() =
+        Some(Instant::now() + Duration::from_mins(config.soft_start_minutes));
+    println!(
+        "{}",
+        format!(
+            "[>] Soft start: blocking {} sites now, the full list in {} minutes",
+            blocked_sites(config).len(),
+            config.soft_start_minutes
+        )
+        .bold()
+        .cyan()
+    );
+}
+
+/// When the soft start ends, for the session timeline.
+pub fn soft_start_end() -> Option<Instant> {
+    *SOFT_START.lock().unwrap()
+}
+
+/// Extends the block from the soft-start sites to the full list.
+pub fn end_soft_start(config: &Config) {
+    let _hosts = lock_hosts();
+    if SOFT_START.lock().unwrap().take().is_none() {
+        return;
+    }
+    rewrite_block(config);
+    println!(
+        "{}",
+        format!(
+            "[>] Soft start over: blocking all {} sites",
+            blocked_sites(config).len()
+        )
+        .bold()
+        .cyan()
+    );
+}
+
+/// Replaces the focus block in place, e.g. after the set of passed sites changed.
+pub fn rewrite_block(config: &Config) {
+    reblock_sites(config);
+}
+
+pub fn effective_sites(config: &Config) -> Vec<String> {
+    let (mut sites, subscribed) = match &config.scope {
+        Some(group) => (
+            config.groups.get(group).cloned().unwrap_or_default(),
+            Vec::new(),
+        ),
+        None => (
+            config.blocked_sites.clone(),
+            subscriptions::load_sites(config),
+        ),
+    };
+    // Subscriptions can bring tens of thousands of sites, and this runs every few seconds
+    let mut seen: HashSet<String> = sites.iter().cloned().collect();
+    for site in subscribed
+        .into_iter()
+        .chain(config.project_sites.clone())
+        .chain(config.pack_sites.clone())
+        .chain(LIVE_SITES.lock().unwrap().clone())
+        .chain(resolver::doh_sites(config))
+    {
+        if seen.insert(site.clone()) {
+            sites.push(site);
+        }
+    }
+    sites
+}
+
+/// The sites a block covers right now, leaving out passes and, early on, the soft start.
+pub fn blocked_sites(config: &Config) -> Vec<String> {
+    let soft_start = SOFT_START.lock().unwrap().is_some();
+    effective_sites(config)
+        .into_iter()
+        .filter(|site| hosts::is_valid_hostname(site) && !blockpage::has_pass(site))
+        .filter(|site| {
+            !soft_start
+                || config
+                    .soft_start_sites
+                    .iter()
+                    .any(|worst| site == worst || site.ends_with(&format!(".{}", worst)))
+        })
+        .collect()
+}
+
+fn build_blocked_content(config: &Config) -> String {
+    hosts::render_block(
+        &config.markers,
+        config.scope.as_deref(),
+        BLOCK_INFO.lock().unwrap().as_ref(),
+        &config.block_ip,
+        &blocked_sites(config),
+    )
 }
@@ -0,0 +1,162 @@
+use crate::{notify, util::Config};
+use chrono::{Days, NaiveDate};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, str::FromStr};
+
+const CALENDAR_FILE: &str = "holidays.ics";
+
+/// Days scheduled sessions are skipped: explicit ranges plus an optional holiday calendar.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct VacationConfig {
+    /// Inclusive ranges like "2026-12-24..2027-01-02", or single dates
+    #[serde(default)]
+    pub ranges: Vec<DateRange>,
+    /// An iCalendar (.ics) URL of public holidays, cached by `focus refresh`
+    #[serde(default)]
+    pub holiday_calendar: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct DateRange {
+    pub first: NaiveDate,
+    pub last: NaiveDate,
+}
+
+impl DateRange {
+    fn contains(&self, date: NaiveDate) -> bool {
+        self.first <= date && date <= self.last
+    }
+}
+
+impl FromStr for DateRange {
+    type Err = String;
+
+    fn from_str(range: &str) -> Result<Self, Self::Err> {
+        let date = |value: &str| {
+            NaiveDate::from_str(value.trim())
+                .map_err(|_| format!("'{}' is not a date like 2026-12-24", value.trim()))
+        };
+        let (first, last) = match range.split_once("..") {
+            Some((first, last)) => (date(first)?, date(last)?),
+            None => (date(range)?, date(range)?),
+        };
+        if last < first {
+            return Err(format!("'{}' ends before it starts", range));
+        }
+        Ok(DateRange { first, last })
+    }
+}
+
+impl TryFrom<String> for DateRange {
+    type Error = String;
+
+    fn try_from(range: String) -> Result<Self, Self::Error> {
+        range.parse()
+    }
+}
+
+impl From<DateRange> for String {
+    fn from(range: DateRange) -> Self {
+        range.to_string()
+    }
+}
+
+impl fmt::Display for DateRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.first == self.last {
+            write!(f, "{}", self.first)
+        } else {
+            write!(f, "{}..{}", self.first, self.last)
+        }
+    }
+}
+
+/// Every day off, loaded once so schedule lookups don't re-read the calendar per day.
+pub struct DaysOff {
+    ranges: Vec<DateRange>,
+}
+
+impl DaysOff {
+    pub fn load(config: &Config) -> Self {
+        let mut ranges = config.vacations.ranges.clone();
+        if config.vacations.holiday_calendar.is_some()
+            && let Ok(calendar) = fs::read_to_string(calendar_path(config))
+        {
+            ranges.extend(parse_calendar(&calendar));
+        }
+        DaysOff { ranges }
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.ranges.iter().any(|range| range.contains(date))
+    }
+}
+
+pub fn calendar_path(config: &Config) -> String {
+    format!("{}/{}", config.data_directory, CALENDAR_FILE)
+}
+
+/// Downloads the holiday calendar, keeping the cached copy if that fails.
+pub fn refresh(config: &Config) {
+    let Some(url) = &config.vacations.holiday_calendar else {
+        return;
+    };
+    let result = notify::http_agent()
+        .get(url)
+        .call()
+        .and_then(|mut response| response.body_mut().read_to_string())
+        .map_err(|e| e.to_string())
+        .and_then(|calendar| match parse_calendar(&calendar).len() {
+            0 => Err(String::from("no all-day events found")),
+            count => fs::write(calendar_path(config), calendar)
+                .map(|_| count)
+                .map_err(|e| e.to_string()),
+        });
+    match result {
+        Ok(count) => println!(
+            "{}",
+            format!("[+] Holiday calendar updated ({} holidays)", count)
+                .bold()
+                .green()
+        ),
+        Err(e) => eprintln!(
+            "{}",
+            format!(
+                "[!] Holiday calendar not updated, keeping cached copy: {}",
+                e
+            )
+            .bold()
+            .yellow()
+        ),
+    }
+}
+
+/// All-day events of an iCalendar file; DTEND is exclusive, as the format defines it.
+fn parse_calendar(calendar: &str) -> Vec<DateRange> {
+    let date = |line: &str| {
+        let value = line.rsplit_once(':')?.1.trim();
+        NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()
+    };
+    let mut ranges = Vec::new();
+    let (mut start, mut end) = (None, None);
+    for line in calendar.lines() {
+        if line.starts_with("BEGIN:VEVENT") {
+            (start, end) = (None, None);
+        } else if line.starts_with("DTSTART") {
+            start = date(line);
+        } else if line.starts_with("DTEND") {
+            end = date(line);
+        } else if line.starts_with("END:VEVENT")
+            && let Some(first) = start
+        {
+            let last = end
+                .and_then(|end| end.checked_sub_days(Days::new(1)))
+                .filter(|last| *last >= first)
+                .unwrap_or(first);
+            ranges.push(DateRange { first, last });
+        }
+    }
+    ranges
+}
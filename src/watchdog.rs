@@ -0,0 +1,130 @@
+use crate::{
+    audit, events,
+    notify::Event,
+    session::{self, SessionState},
+    util::Config,
+};
+use chrono::Local;
+use clap::ValueEnum;
+use colored::Colorize;
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+use std::{
+    env,
+    process::{self, Child, Command, Stdio},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+#[cfg(target_os = "windows")]
+const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+
+/// Keeps a watchdog process alive next to a strict session; each restarts the other.
+pub fn guard(config: Arc<Config>, running: Arc<AtomicBool>) {
+    thread::spawn(move || {
+        let Some(mut watchdog) = spawn(&config) else {
+            eprintln!("{}", "[!] Failed to start the watchdog".bold().red());
+            return;
+        };
+        while running.load(Ordering::SeqCst) {
+            thread::sleep(CHECK_INTERVAL);
+            if matches!(watchdog.try_wait(), Ok(None)) {
+                continue;
+            }
+            // The session clears its state when it ends, which is also the watchdog's cue
+            if !running.load(Ordering::SeqCst) || session::load_state(&config).is_none() {
+                break;
+            }
+            println!("{}", "[!] Watchdog stopped! Restarting it...".bold().red());
+            audit::record(&config, "watchdog", "restarted");
+            match spawn(&config) {
+                Some(child) => watchdog = child,
+                None => break,
+            }
+        }
+    });
+}
+
+fn spawn(config: &Config) -> Option<Child> {
+    let mut command = Command::new(env::current_exe().ok()?);
+    if let Some(group) = &config.scope {
+        command.args(["--group", group]);
+    }
+    command
+        .args(["watchdog", "--pid", &process::id().to_string()])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    // A session of its own so signals aimed at the daemon's group miss it
+    #[cfg(unix)]
+    unsafe {
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+    // A process group of its own, so Ctrl-C in the session's console misses it
+    #[cfg(target_os = "windows")]
+    command.creation_flags(CREATE_NEW_PROCESS_GROUP);
+    command.spawn().ok()
+}
+
+/// Runs as the watchdog: resumes the session if its process dies before the deadline.
+pub fn run(config: &Config, pid: u32) {
+    loop {
+        thread::sleep(CHECK_INTERVAL);
+        let Some(state) = session::load_state(config) else {
+            return;
+        };
+        if state.pid != pid {
+            return;
+        }
+        if session::is_alive(pid) {
+            continue;
+        }
+
+        let remaining = (session::deadline(config, &state) - Local::now()).num_seconds();
+        if remaining <= 0 {
+            return;
+        }
+        audit::record(config, "watchdog", "session resumed");
+        events::publish(config, Event::TamperDetected);
+        resume(config, &state, (remaining as u64).div_ceil(60));
+        events::flush();
+        return;
+    }
+}
+
+fn resume(config: &Config, state: &SessionState, minutes: u64) {
+    let Ok(exe) = env::current_exe() else {
+        return;
+    };
+    let mut command = Command::new(exe);
+    command.args(["--resume", &minutes.to_string(), "--background"]);
+    if let Some(group) = &config.scope {
+        command.args(["--group", group]);
+    }
+    if let Some(profile) = &state.profile {
+        command.args(["--profile", profile]);
+    }
+    if let Some(tag) = &state.tag {
+        command.args(["--tag", tag]);
+    }
+    for backend in state.backends.iter().flatten() {
+        if let Some(value) = backend.to_possible_value() {
+            command.args(["--backend", value.get_name()]);
+        }
+    }
+    let _ = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
@@ -0,0 +1,29 @@
+use crate::notify::{self, Event, Notifier};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// POSTs each event as JSON, e.g. to Home Assistant, Zapier or a custom endpoint.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebhookConfig {
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Notifier for WebhookConfig {
+    fn notify(&self, event: &Event) {
+        let mut request = notify::http_agent().post(&self.url);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        if let Err(e) = request.send_json(event.payload()) {
+            eprintln!(
+                "{}",
+                format!("[!] Failed to call webhook {}: {}", self.url, e)
+                    .bold()
+                    .yellow()
+            );
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use chrono::{Local, TimeZone};
+use focus::hosts::{self, BlockInfo, Markers};
+use proptest::prelude::*;
+
+const BLOCK_IP: &str = "0.0.0.0";
+
+fn custom_markers() -> Markers {
+    Markers {
+        begin: String::from("# focus"),
+        end: String::from("# focus end"),
+    }
+}
+
+fn info() -> BlockInfo {
+    BlockInfo {
+        session: String::from("20261016-090000"),
+        start: Local.with_ymd_and_hms(2026, 10, 16, 9, 0, 0).unwrap(),
+        deadline: Some(Local.with_ymd_and_hms(2026, 10, 16, 10, 0, 0).unwrap()),
+    }
+}
+
+/// Lines a user might have in their hosts file, none of them focus markers.
+fn user_line() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[0-9]{1,3}\\.[0-9]{1,3}\\.[0-9]{1,3}\\.[0-9]{1,3} [a-z]{1,8}(\\.[a-z]{2,3})?",
+        "::1 +localhost",
+        "# [a-zA-Z ]{0,20}",
+        "",
+    ]
+}
+
+fn user_content() -> impl Strategy<Value = String> {
+    (
+        prop::collection::vec(user_line(), 0..8),
+        prop_oneof![Just("\n"), Just("\r\n")],
+        any::<bool>(),
+    )
+        .prop_map(|(lines, line_break, trailing)| {
+            let mut content = lines.join(line_break);
+            if trailing && !content.is_empty() {
+                content.push_str(line_break);
+            }
+            content
+        })
+}
+
+fn sites() -> impl Strategy<Value = Vec<String>> {
+    prop::collection::vec("[a-z]{1,10}\\.(com|org|net)", 0..6)
+}
+
+fn scope() -> impl Strategy<Value = Option<String>> {
+    prop::option::of("[a-z]{1,6}")
+}
+
+fn markers() -> impl Strategy<Value = Markers> {
+    prop_oneof![Just(Markers::default()), Just(custom_markers())]
+}
+
+/// A file built from marker lines, entries and user lines in any order, so markers can
+/// be unpaired, nested or interleaved with other scopes.
+fn scrambled_content() -> impl Strategy<Value = String> {
+    let line = prop_oneof![
+        user_line(),
+        Just(String::from("# BEGIN FOCUS BLOCK")),
+        Just(String::from("# END FOCUS BLOCK")),
+        Just(String::from("# BEGIN FOCUS BLOCK work session=1")),
+        Just(String::from("# END FOCUS BLOCK work session=1")),
+        Just(String::from("# focus")),
+        Just(String::from("# focus end")),
+        Just(String::from("0.0.0.0\texample.com")),
+        Just(String::from(
+            "# focus session=1 start=2026-10-16T09:00:00+00:00 deadline=none"
+        )),
+    ];
+    prop::collection::vec(line, 0..16).prop_map(|lines| lines.join("\n"))
+}
+
+/// Lines shaped like `<marker>[ <scope>][ session=<id>]`; legacy markers always count.
+fn is_marker(line: &str, markers: &Markers) -> bool {
+    [markers.clone(), Markers::default()]
+        .iter()
+        .flat_map(|markers| [markers.begin.clone(), markers.end.clone()])
+        .any(|marker| match line.strip_prefix(&marker) {
+            Some("") => true,
+            Some(rest) => rest.strip_prefix(' ').is_some_and(|rest| {
+                let words: Vec<&str> = rest.split(' ').collect();
+                match words.as_slice() {
+                    [_] => true,
+                    [_, session] => session.starts_with("session="),
+                    _ => false,
+                }
+            }),
+            None => false,
+        })
+}
+
+/// True when every line of `needle` appears in `haystack` in the same order.
+fn keeps_lines(haystack: &str, needle: &str) -> bool {
+    let mut remaining = haystack.lines();
+    needle
+        .lines()
+        .all(|line| remaining.any(|candidate| candidate == line))
+}
+
+proptest! {
+    #[test]
+    fn set_then_remove_restores_the_file(
+        content in user_content(),
+        markers in markers(),
+        scope in scope(),
+        sites in sites(),
+    ) {
+        prop_assume!(scope.as_deref().is_none_or(|scope| markers.allows_scope(scope)));
+        let blocked = hosts::set_block(&content, &markers, scope.as_deref(), Some(&info()), BLOCK_IP, &sites);
+        prop_assert!(hosts::has_block(&blocked, &markers, scope.as_deref()));
+        prop_assert_eq!(hosts::remove_block(&blocked, &markers, scope.as_deref()), content);
+    }
+
+    #[test]
+    fn set_block_is_idempotent(
+        content in user_content(),
+        markers in markers(),
+        scope in scope(),
+        sites in sites(),
+    ) {
+        prop_assume!(scope.as_deref().is_none_or(|scope| markers.allows_scope(scope)));
+        let set = |content: &str| {
+            hosts::set_block(content, &markers, scope.as_deref(), Some(&info()), BLOCK_IP, &sites)
+        };
+        let once = set(&content);
+        prop_assert_eq!(set(&once), once);
+    }
+
+    #[test]
+    fn replace_block_keeps_the_block_in_place(
+        before in user_content(),
+        after in user_content(),
+        markers in markers(),
+        scope in scope(),
+        sites in sites(),
+    ) {
+        prop_assume!(scope.as_deref().is_none_or(|scope| markers.allows_scope(scope)));
+        let blocked = hosts::set_block(&before, &markers, scope.as_deref(), None, BLOCK_IP, &[]);
+        let content = format!("{}\n{}", blocked, after);
+        let replaced = hosts::replace_block(&content, &markers, scope.as_deref(), Some(&info()), BLOCK_IP, &sites);
+        let block = hosts::render_block(&markers, scope.as_deref(), Some(&info()), BLOCK_IP, &sites);
+        prop_assert_eq!(replaced.clone(), format!("{}{}\n{}", before, block, after));
+        prop_assert!(hosts::missing_sites(&replaced, BLOCK_IP, &sites).is_empty());
+        prop_assert_eq!(
+            hosts::replace_block(&replaced, &markers, scope.as_deref(), Some(&info()), BLOCK_IP, &sites),
+            replaced
+        );
+    }
+
+    #[test]
+    fn reformatted_blocks_are_still_recognized(
+        content in user_content(),
+        markers in markers(),
+        scope in scope(),
+        sites in sites(),
+        spacing in prop_oneof![Just("  "), Just("\t"), Just(" \t ")],
+    ) {
+        prop_assume!(scope.as_deref().is_none_or(|scope| markers.allows_scope(scope)));
+        let block = hosts::render_block(&markers, scope.as_deref(), Some(&info()), BLOCK_IP, &sites);
+        // As an editor on the other OS of a dual-boot system might save it
+        let reformatted = block
+            .trim_start_matches('\n')
+            .replace(['\t', ' '], spacing)
+            .replace('\n', " \r\n");
+        let edited = format!("{}\r\n{}\r\n", content, reformatted);
+        prop_assert!(hosts::has_block(&edited, &markers, scope.as_deref()));
+        prop_assert!(hosts::has_intact_block(&edited, &block));
+        prop_assert!(hosts::missing_sites(&edited, BLOCK_IP, &sites).is_empty());
+        let removed = hosts::remove_block(&edited, &markers, scope.as_deref());
+        prop_assert!(!hosts::has_block(&removed, &markers, scope.as_deref()));
+        prop_assert!(keeps_lines(&removed, &content));
+    }
+
+    #[test]
+    fn blocks_of_other_scopes_and_user_lines_survive(
+        before in user_content(),
+        after in user_content(),
+        other in "[a-z]{1,6}",
+        scope in scope(),
+        sites in sites(),
+    ) {
+        prop_assume!(scope.as_deref() != Some(other.as_str()));
+        let markers = Markers::default();
+        let mut content = hosts::set_block(&before, &markers, Some(&other), None, BLOCK_IP, &sites);
+        content.push('\n');
+        content.push_str(&after);
+        let blocked = hosts::set_block(&content, &markers, scope.as_deref(), Some(&info()), BLOCK_IP, &sites);
+        let unblocked = hosts::remove_block(&blocked, &markers, scope.as_deref());
+        prop_assert!(hosts::has_block(&unblocked, &markers, Some(&other)));
+        prop_assert!(keeps_lines(&unblocked, &before));
+        prop_assert!(keeps_lines(&unblocked, &after));
+    }
+
+    #[test]
+    fn interrupted_writes_are_repaired(
+        content in user_content(),
+        markers in markers(),
+        scope in scope(),
+        sites in sites(),
+        cut in any::<prop::sample::Index>(),
+    ) {
+        prop_assume!(scope.as_deref().is_none_or(|scope| markers.allows_scope(scope)));
+        let block = hosts::render_block(&markers, scope.as_deref(), Some(&info()), BLOCK_IP, &sites);
+        // Everything up to the end marker, as a write that stopped partway would leave it
+        let end = block.rfind('\n').unwrap_or(0);
+        let truncated = format!("{}{}", content, &block[..cut.index(end.max(1))]);
+        let repaired = hosts::repair(&truncated, &markers);
+        prop_assert!(repaired.lines().all(|line| !is_marker(line, &markers)));
+        prop_assert!(keeps_lines(&repaired, &content));
+
+        let blocked = hosts::set_block(&truncated, &markers, scope.as_deref(), Some(&info()), BLOCK_IP, &sites);
+        prop_assert_eq!(blocked.lines().filter(|line| is_marker(line, &markers)).count(), 2);
+    }
+
+    #[test]
+    fn an_unpaired_marker_never_swallows_user_lines(
+        between in prop::collection::vec(user_line(), 1..6),
+        sites in sites(),
+    ) {
+        let markers = Markers::default();
+        let between = between.join("\n");
+        let content = format!("# BEGIN FOCUS BLOCK\n{}\n", between);
+        let blocked = hosts::set_block(&content, &markers, None, Some(&info()), BLOCK_IP, &sites);
+        let unblocked = hosts::remove_block(&blocked, &markers, None);
+        prop_assert!(keeps_lines(&unblocked, &between));
+    }
+
+    #[test]
+    fn mutations_are_stable_on_any_file(
+        content in scrambled_content(),
+        markers in markers(),
+        scope in scope(),
+        sites in sites(),
+    ) {
+        prop_assume!(scope.as_deref().is_none_or(|scope| markers.allows_scope(scope)));
+        let repaired = hosts::repair(&content, &markers);
+        prop_assert_eq!(hosts::repair(&repaired, &markers), repaired.clone());
+
+        let removed = hosts::remove_block(&content, &markers, scope.as_deref());
+        prop_assert!(!hosts::has_block(&removed, &markers, scope.as_deref()));
+        prop_assert_eq!(hosts::remove_block(&removed, &markers, scope.as_deref()), removed.clone());
+
+        let blocked = hosts::set_block(&content, &markers, scope.as_deref(), Some(&info()), BLOCK_IP, &sites);
+        let block = hosts::render_block(&markers, scope.as_deref(), Some(&info()), BLOCK_IP, &sites);
+        prop_assert!(hosts::has_intact_block(&blocked, &block));
+        prop_assert_eq!(hosts::remove_block(&blocked, &markers, scope.as_deref()), removed);
+        prop_assert_eq!(hosts::find_blocks(&blocked, &markers).iter().filter(|found| found.scope == scope).count(), 1);
+    }
+}
+
+#[test]
+fn block_info_survives_a_roundtrip() {
+    let markers = Markers::default();
+    let content = hosts::set_block(
+        "127.0.0.1 localhost\n",
+        &markers,
+        Some("work"),
+        Some(&info()),
+        BLOCK_IP,
+        &[String::from("example.com")],
+    );
+    let found = hosts::find_blocks(&content, &markers);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].scope.as_deref(), Some("work"));
+    assert_eq!(found[0].info, Some(info()));
+}
+
+#[test]
+fn crlf_files_keep_their_line_breaks() {
+    let markers = Markers::default();
+    let content = "127.0.0.1 localhost\r\n\r\n# BEGIN FOCUS BLOCK\r\n0.0.0.0\texample.com\r\n# END FOCUS BLOCK\r\n::1 localhost\r\n";
+    assert_eq!(
+        hosts::remove_block(content, &markers, None),
+        "127.0.0.1 localhost\r\n\r\n::1 localhost\r\n"
+    );
+}
+
+#[test]
+fn entries_count_wherever_they_are() {
+    let sites = [String::from("example.com"), String::from("news.com")];
+    let content = "127.0.0.1 localhost\n0.0.0.0 example.com www.example.com # moved by a tool\n# 0.0.0.0 news.com\n";
+    assert_eq!(
+        hosts::missing_sites(content, BLOCK_IP, &sites),
+        ["news.com"]
+    );
+}
+
+#[test]
+fn groups_named_like_the_end_marker_are_refused() {
+    let markers = custom_markers();
+    assert!(!markers.allows_scope("end"));
+    assert!(markers.allows_scope("work"));
+    assert!(Markers::default().allows_scope("end"));
+}